@@ -1,41 +1,168 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
+use std::collections::BTreeSet;
+use std::ops::Bound as RangeBound;
 use std::str::FromStr;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Timestamp, Env, MessageInfo, Response, StdResult, Uint128, Addr};
-use cw_storage_plus::Bound;
+use cosmwasm_std::{coins, to_json_binary, from_json, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Timestamp, Env, MessageInfo, Reply, Response, StdResult, Storage, SubMsg, Uint128, WasmMsg, Addr};
+use cw20::{AllAllowancesResponse, AllowanceInfo, BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, Expiration, MinterResponse};
+use cw_utils::{must_pay, nonpayable, parse_reply_instantiate_data};
+use cw_storage_plus::{Bound, Prefix};
+use cw2::{set_contract_version, CONTRACT};
+use semver::Version;
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg,QueryMsg,ConfigResponse, ClaimResponse, OrganizationResponse, TotalCarbonCreditsResponse, ClaimsResponse, OrganizationListItem,OrganizationsResponse};
-use crate::state::{Config, CONFIG, CLAIMS, VOTES, CLAIM_COUNTER, LendRequestResponse, LEND_REQUEST_COUNTER,LEND_REQUESTS, ORGANIZATIONS, Claim, ClaimStatus,LentStatus,OrganizationInfo, VoteOption, LendRequest};
-use std::convert::TryFrom;
+use crate::geometry::{grid_cells, parse_polygon, polygon_area, polygons_overlap, Point, MIN_CLAIM_AREA};
+use crate::zk::{validate_verifying_key, verify_eligibility_proof};
+use crate::migrations::migrate_from_baseline;
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg,QueryMsg, LendResponse, EligibilityProof, CreditTokenSource, Cw20InstantiateMsg, ReceiveMsg, Cw721ReceiveMsg, Cw721ReceiverMsg, BatchesResponse, NftApproval, OwnerOfResponse, ApprovalResponse, ApprovalsResponse, OperatorsResponse, ContractInfoResponse, NftInfoResponse, AllNftInfoResponse, TokensResponse, NumTokensResponse, RetirementsResponse, EmissionsReportsResponse, NetPositionResponse, NetPositionsResponse, LendOffersResponse, PoolResponse, PoolPositionResponse, ListingsResponse, LoansResponse, LoanHealthResponse, ReputationEventsResponse, SimulateEligibilityResponse, EligibilityProofsResponse,ConfigResponse, ClaimResponse, OrganizationResponse, TotalCarbonCreditsResponse, ClaimsResponse, OrganizationListItem,OrganizationsResponse, ChallengesResponse, VerifierResponse, VerifiersResponse};
+use crate::state::{Config, CONFIG, CLAIMS, CLAIM_CELLS, VOTES, CLAIM_COUNTER, LendRequestResponse, LEND_REQUEST_COUNTER,LEND_REQUESTS, ORGANIZATIONS, Claim, ClaimStatus,LentStatus,OrganizationInfo, TallyParams, Challenge, ChallengeStatus, CHALLENGES, CHALLENGE_COUNTER, CHALLENGE_VOTES, ESCROWED_CREDITS, Verifier, VERIFIERS, VERIFIER_COUNT, MAX_VERIFIERS, Vote, VoteCommit, VOTE_COMMITS, VoteOption, VoteWeights, LendRequest, CreditBatch, credit_batches, BATCH_COUNTER, BATCH_COUNT, NFT_APPROVALS, NFT_OPERATORS, NEXT_SERIAL, RetirementCertificate, RetiredSerials, RETIREMENTS, RETIREMENT_COUNTER, EmissionEntry, EmissionScope, EmissionsReport, EMISSION_REPORTS, PERIOD_OFFSETS, UNALLOCATED_EMISSIONS_PERIOD, ALLOWANCES, Listing, listings, LISTING_COUNTER, LendOffer, LEND_OFFERS, LEND_OFFER_COUNTER, CollateralParams, PoolParams, LendingPool, PoolDebt, POOL, POOL_SHARES, POOL_DEBTS, Loan, LoanStatus, loans, LOAN_COUNTER, ReputationParams, ReputationReason, ReputationEvent, REPUTATION_EVENTS, REPUTATION_EVENT_COUNTER, ELIGIBILITY_KEY, CREDIT_TOKEN_BALANCES, EligibilityPolicy, ELIGIBILITY_POLICY, ELIGIBILITY_POLICY_VERSION, EligibilityRecord, PROOFS};
 use cosmwasm_std::StdError;
-use cw_storage_plus::Map;
 use hex;
+use sha2::{Digest, Sha256};
 use cosmwasm_std::Order;
 use crate::state::UserLendRequestsResponse;
 
+const INSTANTIATE_TOKEN_REPLY_ID: u64 = 1;
+pub const CONTRACT_NAME: &str = concat!("crates.io:", env!("CARGO_PKG_NAME"));
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// CW721 ContractInfo of the credit batch tokens
+const NFT_NAME: &str = "Carbon Credit Batches";
+const NFT_SYMBOL: &str = "CCB";
+/// Page size cap of the batch and CW721 queries
+const MAX_BATCH_LIMIT: u32 = 100;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    let config = Config {
-        owner: info.sender.clone(),
-        voting_period: msg.voting_period,
-        total_carbon_credits: Uint128::zero(),
-    };
+    let mut config = new_config(info.sender.clone(), &msg);
+    validate_tally_params(&config.tally_params)?;
+    let eligibility_policy = msg.eligibility_policy.unwrap_or_default();
+    validate_eligibility_policy(&eligibility_policy)?;
+    ELIGIBILITY_POLICY.save(deps.storage, &eligibility_policy)?;
+    ELIGIBILITY_POLICY_VERSION.save(deps.storage, &1u64)?;
+    if let Some(verifying_key) = msg.eligibility_key {
+        validate_verifying_key(&verifying_key)?;
+        ELIGIBILITY_KEY.save(deps.storage, &verifying_key)?;
+    }
+
+    // The address of a freshly instantiated token is saved in `reply`
+    let mut submessages = vec![];
+    match msg.credit_token {
+        Some(CreditTokenSource::Existing { address }) => {
+            config.credit_token = Some(deps.api.addr_validate(address.as_str())?);
+        },
+        Some(CreditTokenSource::Instantiate { code_id, name, symbol, decimals, label }) => {
+            let token_msg = Cw20InstantiateMsg {
+                name,
+                symbol,
+                decimals,
+                initial_balances: vec![],
+                mint: Some(MinterResponse {
+                    minter: env.contract.address.to_string(),
+                    cap: None,
+                }),
+            };
+            submessages.push(SubMsg::reply_on_success(WasmMsg::Instantiate {
+                admin: Some(env.contract.address.to_string()),
+                code_id,
+                msg: to_json_binary(&token_msg)?,
+                funds: vec![],
+                label,
+            }, INSTANTIATE_TOKEN_REPLY_ID));
+        },
+        None => {},
+    }
     CONFIG.save(deps.storage, &config)?;
     CLAIM_COUNTER.save(deps.storage, &0u64)?;
-    LEND_REQUEST_COUNTER.save(deps.storage, &0u64);
+    LEND_REQUEST_COUNTER.save(deps.storage, &0u64)?;
+    save_initial_state(deps.storage, &env)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new()
+        .add_submessages(submessages)
         .add_attribute("method", "instantiate")
         .add_attribute("owner", info.sender)
         .add_attribute("voting_period", msg.voting_period.to_string()))
 }
 
+/// Config with the defaults of every setting `msg` leaves out
+pub(crate) fn new_config(owner: Addr, msg: &InstantiateMsg) -> Config {
+    Config {
+        owner,
+        voting_period: msg.voting_period,
+        reveal_period: msg.reveal_period.unwrap_or(msg.voting_period),
+        unrevealed_penalty: msg.unrevealed_penalty.unwrap_or_else(|| Uint128::new(1)),
+        challenge_period: msg.challenge_period.unwrap_or(msg.voting_period),
+        challenge_slash: msg.challenge_slash.unwrap_or_else(|| Uint128::new(1)),
+        total_carbon_credits: Uint128::zero(),
+        vote_weights: msg.vote_weights.clone().unwrap_or_default(),
+        tally_params: msg.tally_params.clone().unwrap_or_default(),
+        governance: msg.governance.clone(),
+        credit_token: None,
+        default_penalty: msg.default_penalty.unwrap_or_else(|| Uint128::new(5)),
+        reborrow_reputation: msg.reborrow_reputation.unwrap_or_else(|| Uint128::new(5)),
+        collateral: msg.collateral.clone(),
+        pool: msg.pool.clone().unwrap_or_default(),
+        lend_request_expiry: msg.lend_request_expiry.unwrap_or(7 * 86400),
+        reputation: msg.reputation.clone().unwrap_or_default(),
+        proof_validity: msg.proof_validity.unwrap_or(86400),
+        require_eligibility_proof: msg.require_eligibility_proof.unwrap_or(false),
+    }
+}
+
+/// Counters and the lending pool, none of which exist in the baseline layout
+pub(crate) fn save_initial_state(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    CHALLENGE_COUNTER.save(storage, &0u64)?;
+    VERIFIER_COUNT.save(storage, &0u32)?;
+    BATCH_COUNTER.save(storage, &0u64)?;
+    BATCH_COUNT.save(storage, &0u64)?;
+    NEXT_SERIAL.save(storage, &Uint128::new(1))?;
+    RETIREMENT_COUNTER.save(storage, &0u64)?;
+    LISTING_COUNTER.save(storage, &0u64)?;
+    LOAN_COUNTER.save(storage, &0u64)?;
+    LEND_OFFER_COUNTER.save(storage, &0u64)?;
+    POOL.save(storage, &LendingPool {
+        total_shares: Uint128::zero(),
+        available: Uint128::zero(),
+        total_debt_shares: Uint128::zero(),
+        borrow_index: Decimal::one(),
+        last_accrued: env.block.time.seconds(),
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    // The baseline layout predates cw2, so a missing version means its storage still needs converting
+    let previous_version = match CONTRACT.may_load(deps.storage)? {
+        Some(stored) => {
+            if stored.contract != CONTRACT_NAME {
+                return Err(ContractError::CannotMigrate { previous_contract: stored.contract });
+            }
+            if parse_version(&stored.version)? > parse_version(CONTRACT_VERSION)? {
+                return Err(ContractError::CannotDowngrade { previous_version: stored.version, version: CONTRACT_VERSION.to_string() });
+            }
+            stored.version
+        },
+        None => {
+            migrate_from_baseline(deps.storage, &env)?;
+            "baseline".to_string()
+        },
+    };
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("previous_version", previous_version)
+        .add_attribute("version", CONTRACT_VERSION))
+}
+
+fn parse_version(version: &str) -> StdResult<Version> {
+    Version::parse(version).map_err(|_| StdError::generic_err(format!("Invalid contract version {}", version)))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -44,36 +171,265 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::CreateClaim { longitudes, latitudes, time_started, time_ended, demanded_tokens, ipfs_hashes } => {
-            execute_create_claim(deps, env, info, longitudes, latitudes, time_started, time_ended, demanded_tokens, ipfs_hashes)
+        ExecuteMsg::CreateClaim { longitudes, latitudes, time_started, time_ended, demanded_tokens, ipfs_hashes, methodology } => {
+            execute_create_claim(deps, env, info, longitudes, latitudes, time_started, time_ended, demanded_tokens, ipfs_hashes, methodology)
+        },
+        ExecuteMsg::CreateLendToken {lender, amount, interest_rate, term, proof} => {
+            execute_request_tokens(deps, env, info,lender, amount, interest_rate, term, proof)
         },
-        ExecuteMsg::CreateLendToken {lender, amount} => {
-            execute_request_tokens(deps, env, info,lender, amount)
+        ExecuteMsg::CommitVote { claim_id, hash } => {
+            execute_commit_vote(deps, env, info, claim_id, hash)
         },
-        ExecuteMsg::CastVote { claim_id, vote } => {
-            execute_cast_vote(deps, env, info, claim_id, vote)
+        ExecuteMsg::RevealVote { claim_id, vote, salt } => {
+            execute_reveal_vote(deps, env, info, claim_id, vote, salt)
         },
         ExecuteMsg::FinalizeVoting { claim_id } => {
             execute_finalize_voting(deps, env, info, claim_id)
         },
+        ExecuteMsg::ChallengeClaim { claim_id, evidence } => {
+            execute_challenge_claim(deps, env, info, claim_id, evidence)
+        },
+        ExecuteMsg::VoteOnChallenge { challenge_id, vote } => {
+            execute_vote_on_challenge(deps, env, info, challenge_id, vote)
+        },
+        ExecuteMsg::ResolveChallenge { challenge_id } => {
+            execute_resolve_challenge(deps, env, info, challenge_id)
+        },
+        ExecuteMsg::ReleaseCredits { claim_id } => {
+            execute_release_credits(deps, env, info, claim_id)
+        },
         ExecuteMsg::LendTokens { lend_request_id, response } => {
-            execute_lend_tokens(deps, env, info, lend_request_id, response)
+            execute_lend_tokens(deps, env, info, lend_request_id, response, None)
+        },
+        ExecuteMsg::CancelLendRequest { lend_request_id } => {
+            execute_cancel_lend_request(deps, env, info, lend_request_id)
+        },
+        ExecuteMsg::AcceptCounterOffer { lend_request_id } => {
+            execute_accept_counter_offer(deps, env, info, lend_request_id)
+        },
+        ExecuteMsg::RepayTokens { loan_id, amount } => {
+            execute_repay_tokens(deps, env, info, loan_id, amount, false)
+        },
+        ExecuteMsg::MarkDefaulted { loan_id } => {
+            execute_mark_defaulted(deps, env, info, loan_id)
         },
-        ExecuteMsg::RepayTokens { lender, amount } => {
-            execute_repay_tokens(deps, env, info, lender, amount)
+        ExecuteMsg::ClaimCollateral { loan_id } => {
+            execute_claim_collateral(deps, env, info, loan_id)
         },
-        ExecuteMsg::VerifyEligibility { borrower, amount, lender} => {
-            execute_verify_eligibility(deps, env, info, borrower,lender,amount)
+        ExecuteMsg::CreateLendOffer { max_amount, min_eligibility_score, rate, term } => {
+            execute_create_lend_offer(deps, env, info, max_amount, min_eligibility_score, rate, term, false)
+        },
+        ExecuteMsg::CancelLendOffer { offer_id } => {
+            execute_cancel_lend_offer(deps, env, info, offer_id)
+        },
+        ExecuteMsg::AcceptLendOffer { offer_id, amount, proof } => {
+            execute_accept_lend_offer(deps, env, info, offer_id, amount, proof)
+        },
+        ExecuteMsg::PoolDeposit { amount } => {
+            execute_pool_deposit(deps, env, info, amount, false)
+        },
+        ExecuteMsg::PoolWithdraw { shares } => {
+            execute_pool_withdraw(deps, env, info, shares)
+        },
+        ExecuteMsg::PoolBorrow { amount, proof } => {
+            execute_pool_borrow(deps, env, info, amount, proof)
+        },
+        ExecuteMsg::PoolRepay { amount } => {
+            execute_pool_repay(deps, env, info, amount, false)
+        },
+        ExecuteMsg::VerifyEligibility { borrower, amount, lender, proof } => {
+            execute_verify_eligibility(deps, env, info, borrower,lender,amount, proof)
         },
         ExecuteMsg::UpdateOrganizationName { name } => {
             execute_update_organization_name(deps, env, info, name)
         },
         ExecuteMsg::AddOrganizationEmission { emissions } => {
             add_organization_emission(deps, env, info, emissions) // Add this handler
+        },
+        ExecuteMsg::UpdateConfig { voting_period, reveal_period, unrevealed_penalty, challenge_period, challenge_slash, vote_weights, tally_params, governance, default_penalty, reborrow_reputation, collateral, pool, lend_request_expiry, reputation, proof_validity, require_eligibility_proof } => {
+            execute_update_config(deps, env, info, voting_period, reveal_period, unrevealed_penalty, challenge_period, challenge_slash, vote_weights, tally_params, governance, default_penalty, reborrow_reputation, collateral, pool, lend_request_expiry, reputation, proof_validity, require_eligibility_proof)
+        },
+        ExecuteMsg::UpdateEligibilityPolicy { policy } => {
+            execute_update_eligibility_policy(deps, env, info, policy)
+        },
+        ExecuteMsg::SetEligibilityKey { verifying_key } => {
+            execute_set_eligibility_key(deps, env, info, verifying_key)
+        },
+        ExecuteMsg::AddVerifier { address, name, metadata, accredited_until } => {
+            execute_add_verifier(deps, env, info, address, name, metadata, accredited_until)
+        },
+        ExecuteMsg::RemoveVerifier { address } => {
+            execute_remove_verifier(deps, env, info, address)
+        },
+        ExecuteMsg::Receive(wrapper) => {
+            execute_receive(deps, env, info, wrapper)
+        },
+        ExecuteMsg::TransferNft { recipient, token_id } => {
+            execute_transfer_nft(deps, env, info, recipient, token_id)
+        },
+        ExecuteMsg::SendNft { contract, token_id, msg } => {
+            execute_send_nft(deps, env, info, contract, token_id, msg)
+        },
+        ExecuteMsg::Approve { spender, token_id, expires } => {
+            execute_approve(deps, env, info, spender, token_id, expires)
+        },
+        ExecuteMsg::Revoke { spender, token_id } => {
+            execute_revoke(deps, env, info, spender, token_id)
+        },
+        ExecuteMsg::ApproveAll { operator, expires } => {
+            execute_approve_all(deps, env, info, operator, expires)
+        },
+        ExecuteMsg::RevokeAll { operator } => {
+            execute_revoke_all(deps, env, info, operator)
+        },
+        ExecuteMsg::SplitBatch { token_id, amount } => {
+            execute_split_batch(deps, env, info, token_id, amount)
+        },
+        ExecuteMsg::RetireCredits { amount, beneficiary, period, reason } => {
+            execute_retire_credits(deps, env, info, amount, beneficiary, period, reason, false)
+        },
+        ExecuteMsg::ReportEmissions { period, entries } => {
+            execute_report_emissions(deps, env, info, period, entries)
+        },
+        ExecuteMsg::TransferCredits { recipient, amount } => {
+            execute_transfer_credits(deps, env, info, recipient, amount)
+        },
+        ExecuteMsg::IncreaseAllowance { spender, amount, expires } => {
+            execute_increase_allowance(deps, env, info, spender, amount, expires)
+        },
+        ExecuteMsg::DecreaseAllowance { spender, amount, expires } => {
+            execute_decrease_allowance(deps, env, info, spender, amount, expires)
+        },
+        ExecuteMsg::TransferFrom { owner, recipient, amount } => {
+            execute_transfer_from(deps, env, info, owner, recipient, amount)
+        },
+        ExecuteMsg::CreateListing { amount, price } => {
+            execute_create_listing(deps, env, info, amount, price)
+        },
+        ExecuteMsg::CancelListing { listing_id } => {
+            execute_cancel_listing(deps, env, info, listing_id)
+        },
+        ExecuteMsg::FillListing { listing_id, amount } => {
+            execute_fill_listing(deps, env, info, listing_id, amount)
         }
     }
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    if msg.id != INSTANTIATE_TOKEN_REPLY_ID {
+        return Err(ContractError::UnknownReplyId { id: msg.id });
+    }
+    let res = parse_reply_instantiate_data(msg)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    let token = deps.api.addr_validate(&res.contract_address)?;
+    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+        config.credit_token = Some(token.clone());
+        Ok(config)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "reply_instantiate_token")
+        .add_attribute("credit_token", token))
+}
+
+/// Credits sent through the credit token's Send, acting on behalf of the original sender
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.credit_token.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let sender_info = MessageInfo {
+        sender: deps.api.addr_validate(&wrapper.sender)?,
+        funds: vec![],
+    };
+    // Tokens that reached the sender directly on the token were never tracked
+    CREDIT_TOKEN_BALANCES.update(deps.storage, &sender_info.sender, env.block.height, |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default().saturating_sub(wrapper.amount))
+    })?;
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::LendTokens { lend_request_id } => {
+            execute_lend_tokens(deps, env, sender_info, lend_request_id, LendResponse::Accept, Some(wrapper.amount))
+        },
+        ReceiveMsg::CounterOffer { lend_request_id } => {
+            let response = LendResponse::Counter { amount: wrapper.amount };
+            execute_lend_tokens(deps, env, sender_info, lend_request_id, response, Some(wrapper.amount))
+        },
+        ReceiveMsg::RepayTokens { loan_id } => {
+            execute_repay_tokens(deps, env, sender_info, loan_id, wrapper.amount, true)
+        },
+        ReceiveMsg::RetireCredits { beneficiary, period, reason } => {
+            execute_retire_credits(deps, env, sender_info, wrapper.amount, beneficiary, period, reason, true)
+        },
+        ReceiveMsg::CreateLendOffer { min_eligibility_score, rate, term } => {
+            execute_create_lend_offer(deps, env, sender_info, wrapper.amount, min_eligibility_score, rate, term, true)
+        },
+        ReceiveMsg::PoolDeposit {} => {
+            execute_pool_deposit(deps, env, sender_info, wrapper.amount, true)
+        },
+        ReceiveMsg::PoolRepay {} => {
+            execute_pool_repay(deps, env, sender_info, wrapper.amount, true)
+        },
+    }
+}
+
+/// Credits available to `address`: its credit token balance when one is configured,
+/// otherwise the internal ledger
+fn held_credits(deps: Deps, config: &Config, address: &Addr, org_info: &OrganizationInfo) -> StdResult<Uint128> {
+    match &config.credit_token {
+        Some(token) => {
+            let res: BalanceResponse = deps.querier.query_wasm_smart(token, &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            })?;
+            Ok(res.balance)
+        },
+        None => Ok(org_info.carbon_credits),
+    }
+}
+
+/// Credits `address` held at the start of block `height`, given its organization as of then.
+/// Token movements outside this contract are untracked, so the current balance caps the figure.
+fn credits_at(deps: Deps, config: &Config, address: &Addr, org_info: &OrganizationInfo, height: u64) -> StdResult<Uint128> {
+    match &config.credit_token {
+        Some(_) => {
+            let tracked = CREDIT_TOKEN_BALANCES.may_load_at_height(deps.storage, address, height)?.unwrap_or_default();
+            Ok(tracked.min(held_credits(deps, config, address, org_info)?))
+        },
+        None => Ok(org_info.carbon_credits),
+    }
+}
+
+/// Organization as it stood at the start of block `height`, with reputation decayed up to `now`
+fn organization_at(storage: &dyn Storage, config: &Config, address: &Addr, height: u64, now: u64) -> StdResult<OrganizationInfo> {
+    let mut org_info = ORGANIZATIONS.may_load_at_height(storage, address, height)?.unwrap_or_default();
+    org_info.reputation_score = org_info.reputation_at(&config.reputation, now);
+    Ok(org_info)
+}
+
+/// Organization an eligibility score is taken from: as of the start of the current block, with
+/// credits held as tokens included
+fn eligibility_snapshot(deps: Deps, env: &Env, config: &Config, address: &Addr) -> StdResult<OrganizationInfo> {
+    let mut org_info = organization_at(deps.storage, config, address, env.block.height, env.block.time.seconds())?;
+    org_info.carbon_credits = credits_at(deps, config, address, &org_info, env.block.height)?;
+    Ok(org_info)
+}
+
+/// Voting power from the voter's state as of block `height`
+fn voting_power(deps: Deps, env: &Env, config: &Config, voter: &Addr, height: u64) -> Result<Uint128, ContractError> {
+    let mut voter_info = organization_at(deps.storage, config, voter, height, env.block.time.seconds())?;
+    // Skip the token query when credits carry no weight
+    if !config.vote_weights.credit_weight.is_zero() {
+        voter_info.carbon_credits = credits_at(deps, config, voter, &voter_info, height)?;
+    }
+    vote_weight(&config.vote_weights, &voter_info)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn execute_create_claim(
     deps: DepsMut,
     env: Env,
@@ -84,24 +440,46 @@ pub fn execute_create_claim(
     time_ended: u64,
     demanded_tokens: Uint128,
     ipfs_hashes: Vec<String>,
+    methodology: String,
 ) -> Result<Response, ContractError> {
+    if time_started >= time_ended {
+        return Err(ContractError::InvalidClaimPeriod {});
+    }
+    let polygon = parse_polygon(&longitudes, &latitudes)?;
+    let area = polygon_area(&polygon);
+    if area < MIN_CLAIM_AREA {
+        return Err(ContractError::InvalidPolygon { reason: format!("covers less than {} square microdegrees", MIN_CLAIM_AREA) });
+    }
+    let cells = grid_cells(&polygon)?;
+    ensure_no_overlap(deps.as_ref(), &polygon, &cells, time_started, time_ended)?;
+
     let mut claim_counter = CLAIM_COUNTER.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
+    let voting_end_time = env.block.time.seconds() + config.voting_period;
     let claim = Claim {
         id: claim_counter,
         organization: info.sender.clone(),
         longitudes,
         latitudes,
+        area: Uint128::from(area),
         time_started,
         time_ended,
         demanded_tokens,
         ipfs_hashes,
+        methodology,
         status: ClaimStatus::Active,
-        voting_end_time: env.block.time.seconds() + config.voting_period,
+        voting_end_time,
+        reveal_end_time: voting_end_time + config.reveal_period,
         yes_votes: Uint128::zero(),
         no_votes: Uint128::zero(),
+        challenge_end_time: 0,
+        challenge_id: None,
+        created_height: env.block.height,
     };
     CLAIMS.save(deps.storage, claim_counter, &claim)?;
+    for (column, row) in cells {
+        CLAIM_CELLS.save(deps.storage, ((column, row), time_ended, claim.id), &time_started)?;
+    }
     claim_counter += 1;
     CLAIM_COUNTER.save(deps.storage, &claim_counter)?;
     
@@ -112,35 +490,150 @@ pub fn execute_create_claim(
         .add_attribute("voting_end_time", claim.voting_end_time.to_string()))
 }
 
-pub fn execute_cast_vote(
+/// Rejects a claim area that overlaps a live or approved claim covering an overlapping period,
+/// so the same land can't be credited twice. Only claims indexed in the same grid cells whose periods
+/// overlap are read; the minimum claim area bounds how many of those can share a cell at once.
+fn ensure_no_overlap(deps: Deps, polygon: &[Point], cells: &[(i32, i32)], time_started: u64, time_ended: u64) -> Result<(), ContractError> {
+    let mut checked = BTreeSet::new();
+    for cell in cells {
+        // Claims ending after this one starts, in order of their end
+        let indexed = CLAIM_CELLS
+            .sub_prefix(*cell)
+            .range(deps.storage, Some(Bound::exclusive((time_started, u64::MAX))), None, Order::Ascending);
+        for item in indexed {
+            let ((_, id), started) = item?;
+            if started >= time_ended || !checked.insert(id) {
+                continue;
+            }
+            let existing = CLAIMS.load(deps.storage, id)?;
+            let existing_polygon = parse_polygon(&existing.longitudes, &existing.latitudes)?;
+            if polygons_overlap(polygon, &existing_polygon) {
+                return Err(ContractError::OverlappingClaim { claim_id: id });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Drops a claim that is no longer live from the overlap index
+fn unindex_claim(storage: &mut dyn Storage, claim: &Claim) {
+    // Claims that predate validated polygons were never indexed
+    let cells = parse_polygon(&claim.longitudes, &claim.latitudes).and_then(|polygon| grid_cells(&polygon));
+    for (column, row) in cells.unwrap_or_default() {
+        CLAIM_CELLS.remove(storage, ((column, row), claim.time_ended, claim.id));
+    }
+}
+
+pub fn execute_commit_vote(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     claim_id: u64,
-    vote: VoteOption,
+    hash: String,
 ) -> Result<Response, ContractError> {
-    let mut claim = CLAIMS.load(deps.storage, claim_id)?;
+    let claim = CLAIMS.load(deps.storage, claim_id)?;
     
 
     if env.block.time.seconds() > claim.voting_end_time {
         return Err(ContractError::VotingEnded {});
     }
+    ensure_accredited(deps.as_ref(), &env, &info.sender)?;
+    if claim.organization == info.sender {
+        return Err(ContractError::OwnClaim {});
+    }
+
+    if VOTE_COMMITS.has(deps.storage, (claim_id, &info.sender)) {
+        return Err(ContractError::AlreadyVoted {});
+    }
 
+    let config = CONFIG.load(deps.storage)?;
+    let weight = voting_power(deps.as_ref(), &env, &config, &info.sender, claim.created_height)?;
+    if weight.is_zero() {
+        return Err(ContractError::NoVotingPower {});
+    }
+
+    VOTE_COMMITS.save(deps.storage, (claim_id, &info.sender), &VoteCommit {
+        voter: info.sender.clone(),
+        hash: hash.to_lowercase(),
+        weight,
+        timestamp: env.block.time.seconds(),
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "commit_vote")
+        .add_attribute("claim_id", claim_id.to_string())
+        .add_attribute("voter", info.sender)
+        .add_attribute("weight", weight))
+}
+
+pub fn execute_reveal_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    claim_id: u64,
+    vote: VoteOption,
+    salt: String,
+) -> Result<Response, ContractError> {
+    let mut claim = CLAIMS.load(deps.storage, claim_id)?;
+    if env.block.time.seconds() <= claim.voting_end_time {
+        return Err(ContractError::RevealNotStarted {});
+    }
+    if env.block.time.seconds() > claim.reveal_end_time {
+        return Err(ContractError::RevealEnded {});
+    }
+    let commit = VOTE_COMMITS.may_load(deps.storage, (claim_id, &info.sender))?
+        .ok_or(ContractError::CommitNotFound {})?;
     if VOTES.has(deps.storage, (claim_id, &info.sender)) {
         return Err(ContractError::AlreadyVoted {});
     }
-    VOTES.save(deps.storage, (claim_id, &info.sender), &vote)?;
+    if commit.hash != vote_commitment(claim_id, &info.sender, &vote, &salt) {
+        return Err(ContractError::InvalidCommitment {});
+    }
 
+    VOTES.save(deps.storage, (claim_id, &info.sender), &Vote {
+        voter: info.sender.clone(),
+        vote: vote.clone(),
+        weight: commit.weight,
+        timestamp: env.block.time.seconds(),
+    })?;
 
     match vote {
-        VoteOption::Yes => claim.yes_votes += Uint128::new(1),
-        VoteOption::No => claim.no_votes += Uint128::new(1),
+        VoteOption::Yes => claim.yes_votes = claim.yes_votes.checked_add(commit.weight)?,
+        VoteOption::No => claim.no_votes = claim.no_votes.checked_add(commit.weight)?,
     }
     CLAIMS.save(deps.storage, claim_id, &claim)?;
     Ok(Response::new()
-        .add_attribute("method", "cast_vote")
+        .add_attribute("method", "reveal_vote")
         .add_attribute("claim_id", claim_id.to_string())
-        .add_attribute("voter", info.sender))
+        .add_attribute("voter", info.sender)
+        .add_attribute("vote", format!("{:?}", vote))
+        .add_attribute("weight", commit.weight))
+}
+
+/// Hex sha256 of "{claim_id}:{voter}:{yes|no}:{salt}", the hash expected by CommitVote
+pub fn vote_commitment(claim_id: u64, voter: &Addr, vote: &VoteOption, salt: &str) -> String {
+    let vote = match vote {
+        VoteOption::Yes => "yes",
+        VoteOption::No => "no",
+    };
+    let preimage = format!("{}:{}:{}:{}", claim_id, voter, vote, salt);
+    hex::encode(Sha256::digest(preimage.as_bytes()))
+}
+
+fn ensure_accredited(deps: Deps, env: &Env, address: &Addr) -> Result<Verifier, ContractError> {
+    let verifier = VERIFIERS.may_load(deps.storage, address)?
+        .ok_or(ContractError::NotVerifier {})?;
+    if env.block.time.seconds() > verifier.accredited_until {
+        return Err(ContractError::AccreditationExpired {});
+    }
+    Ok(verifier)
+}
+
+/// Voting power of an organization under the configured weights
+pub fn vote_weight(weights: &VoteWeights, org_info: &OrganizationInfo) -> Result<Uint128, ContractError> {
+    let reputation_power = org_info.reputation_score.checked_mul(weights.reputation_weight)?;
+    let credit_power = org_info.carbon_credits.checked_mul(weights.credit_weight)?;
+    Ok(weights.base_weight.checked_add(reputation_power)?.checked_add(credit_power)?)
 }
 
 pub fn execute_finalize_voting(
@@ -150,414 +643,2207 @@ pub fn execute_finalize_voting(
     claim_id: u64,
 ) -> Result<Response, ContractError> {
     let mut claim = CLAIMS.load(deps.storage, claim_id)?;
-    if env.block.time.seconds() <= claim.voting_end_time {
+    if env.block.time.seconds() <= claim.reveal_end_time {
         return Err(ContractError::VotingNotEnded {});
     }
-    let approved = claim.yes_votes >= claim.no_votes;
-    claim.status = if approved { ClaimStatus::Approved } else { ClaimStatus::Rejected };
+    if claim.status != ClaimStatus::Active {
+        return Err(ContractError::ClaimNotActive {});
+    }
+    let votes: Vec<Vote> = VOTES
+        .prefix(claim_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, vote)| vote))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // Tally from the weights recorded at cast time
+    let mut yes_votes = Uint128::zero();
+    let mut no_votes = Uint128::zero();
+    for vote in &votes {
+        match vote.vote {
+            VoteOption::Yes => yes_votes = yes_votes.checked_add(vote.weight)?,
+            VoteOption::No => no_votes = no_votes.checked_add(vote.weight)?,
+        }
+    }
+    claim.yes_votes = yes_votes;
+    claim.no_votes = no_votes;
     let mut config = CONFIG.load(deps.storage)?;
+
+    // Commits that were never revealed are not counted and cost reputation
+    let unrevealed: Vec<Addr> = VOTE_COMMITS
+        .prefix(claim_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|voter| !VOTES.has(deps.storage, (claim_id, voter)))
+        .collect();
+    let now = env.block.time.seconds();
+    for voter in &unrevealed {
+        penalize(deps.storage, &config, voter, ReputationReason::Unrevealed, claim_id, config.unrevealed_penalty, &env)?;
+    }
+
+    // So do accredited verifiers that stayed out of the vote altogether; their number is capped at MAX_VERIFIERS
+    let mut absent = 0u32;
+    if !config.reputation.absent_penalty.is_zero() {
+        let absentees: Vec<Addr> = VERIFIERS
+            .range(deps.storage, None, None, Order::Ascending)
+            .take(MAX_VERIFIERS as usize)
+            .filter_map(|item| match item {
+                Ok((address, verifier)) => {
+                    let took_part = VOTE_COMMITS.has(deps.storage, (claim_id, &address)) || VOTES.has(deps.storage, (claim_id, &address));
+                    let expected = verifier.added_at <= claim.voting_end_time && verifier.accredited_until >= claim.voting_end_time;
+                    (expected && !took_part && address != claim.organization).then_some(Ok(address))
+                },
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        for address in &absentees {
+            penalize(deps.storage, &config, address, ReputationReason::Absent, claim_id, config.reputation.absent_penalty, &env)?;
+        }
+        absent = absentees.len() as u32;
+    }
+
+    let total_votes = yes_votes.checked_add(no_votes)?;
+    if total_votes.is_zero() || total_votes < config.tally_params.quorum {
+        claim.status = ClaimStatus::Expired;
+        unindex_claim(deps.storage, &claim);
+        CLAIMS.save(deps.storage, claim_id, &claim)?;
+        return Ok(Response::new()
+            .add_attribute("method", "finalize_voting")
+            .add_attribute("claim_id", claim_id.to_string())
+            .add_attribute("status", format!("{:?}", claim.status))
+            .add_attribute("total_votes", total_votes)
+            .add_attribute("unrevealed", unrevealed.len().to_string())
+            .add_attribute("absent", absent.to_string()));
+    }
+    let yes_share = Decimal::from_ratio(yes_votes, total_votes);
+    let no_share = Decimal::from_ratio(no_votes, total_votes);
+    let vetoed = no_share > config.tally_params.veto_threshold;
+    let approved = !vetoed && yes_share > config.tally_params.threshold;
+    claim.status = if approved { ClaimStatus::Approved } else { ClaimStatus::Rejected };
+    if !approved {
+        unindex_claim(deps.storage, &claim);
+    }
+    if approved {
+        claim.challenge_end_time = env.block.time.seconds() + config.challenge_period;
+    }
     
+    let mut submessages = vec![];
     if approved {
-        let mut org_info = ORGANIZATIONS.may_load(deps.storage, &claim.organization)?
-            .unwrap_or(OrganizationInfo {
-                reputation_score: Uint128::zero(),
-                carbon_credits: Uint128::zero(),
-                debt: Uint128::zero(),
-                times_borrowed: 0,
-                total_borrowed: Uint128::zero(),
-                total_returned: Uint128::zero(),
-                name: "".to_string(),
-                emissions: Uint128::zero(),
-            });
-        
-        org_info.carbon_credits += claim.demanded_tokens;
-        ORGANIZATIONS.save(deps.storage, &claim.organization, &org_info)?;
+        if let Some(token) = &config.credit_token {
+            // Tokens can't be taken back from holders, so they stay in escrow here until the claim
+            // can no longer be challenged, see `execute_release_credits`
+            submessages.push(SubMsg::new(WasmMsg::Execute {
+                contract_addr: token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: env.contract.address.to_string(),
+                    amount: claim.demanded_tokens,
+                })?,
+                funds: vec![],
+            }));
+            ESCROWED_CREDITS.save(deps.storage, claim_id, &claim.demanded_tokens)?;
+        } else {
+            let mut org_info = ORGANIZATIONS.may_load(deps.storage, &claim.organization)?
+                .unwrap_or_default();
+            org_info.carbon_credits += claim.demanded_tokens;
+            ORGANIZATIONS.save(deps.storage, &claim.organization, &org_info, env.block.height)?;
+        }
         
         config.total_carbon_credits += claim.demanded_tokens;
         CONFIG.save(deps.storage, &config)?;
+        // Tokens change hands out of sight, so their serials stay with the contract
+        let batch_owner = match &config.credit_token {
+            Some(_) => &env.contract.address,
+            None => &claim.organization,
+        };
+        issue_batch(deps.storage, &claim, batch_owner)?;
     }
-    let voters: Vec<(u64, Addr)> = VOTES
-        .prefix(claim_id)
-        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
-        .map(|key| key.map(|addr| (claim_id, addr)))
-        .collect::<Result<Vec<_>, _>>()?;
-    
-    for (_, voter_addr) in voters {
-        let vote = VOTES.load(deps.storage, (claim_id, &voter_addr))?;
-        let vote_correct = (vote == VoteOption::Yes && approved) || (vote == VoteOption::No && !approved);
+    for vote in votes {
+        let vote_correct = (vote.vote == VoteOption::Yes && approved) || (vote.vote == VoteOption::No && !approved);
+
+        // Verifiers removed since voting keep no track record
+        if let Some(mut verifier) = VERIFIERS.may_load(deps.storage, &vote.voter)? {
+            verifier.votes_cast += 1;
+            if vote_correct {
+                verifier.correct_votes += 1;
+            }
+            VERIFIERS.save(deps.storage, &vote.voter, &verifier)?;
+        }
         
+        let mut org_info = ORGANIZATIONS.may_load(deps.storage, &vote.voter)?
+            .unwrap_or_default();
         if vote_correct {
-            let mut org_info = ORGANIZATIONS.may_load(deps.storage, &voter_addr)?
-                .unwrap_or(OrganizationInfo {
-                    reputation_score: Uint128::zero(),
-                    carbon_credits: Uint128::zero(),
-                    debt: Uint128::zero(),
-                    times_borrowed: 0,
-                    total_borrowed: Uint128::zero(),
-                    total_returned: Uint128::zero(),
-                    name: "".to_string(),
-                    emissions: Uint128::zero(),
-                });
-            org_info.reputation_score += Uint128::new(1);
-            ORGANIZATIONS.save(deps.storage, &voter_addr, &org_info)?;
+            adjust_reputation(deps.storage, &config, &vote.voter, &mut org_info, ReputationReason::CorrectVote, Some(claim_id), config.reputation.correct_vote_reward, now)?;
+        } else {
+            adjust_reputation(deps.storage, &config, &vote.voter, &mut org_info, ReputationReason::IncorrectVote, Some(claim_id), config.reputation.incorrect_vote_slash, now)?;
         }
+        ORGANIZATIONS.save(deps.storage, &vote.voter, &org_info, env.block.height)?;
     }
     CLAIMS.save(deps.storage, claim_id, &claim)?;
     
     Ok(Response::new()
+        .add_submessages(submessages)
         .add_attribute("method", "finalize_voting")
         .add_attribute("claim_id", claim_id.to_string())
-        .add_attribute("status", format!("{:?}", claim.status)))
+        .add_attribute("status", format!("{:?}", claim.status))
+        .add_attribute("yes_votes", claim.yes_votes)
+        .add_attribute("no_votes", claim.no_votes)
+        .add_attribute("vetoed", vetoed.to_string())
+        .add_attribute("unrevealed", unrevealed.len().to_string())
+        .add_attribute("absent", absent.to_string()))
 }
 
-pub fn execute_request_tokens(
+/// Slashes a voter's reputation for `claim_id`; addresses without an organization have none to lose
+fn penalize(storage: &mut dyn Storage, config: &Config, voter: &Addr, reason: ReputationReason, claim_id: u64, amount: Uint128, env: &Env) -> StdResult<()> {
+    if let Some(mut org_info) = ORGANIZATIONS.may_load(storage, voter)? {
+        adjust_reputation(storage, config, voter, &mut org_info, reason, Some(claim_id), amount, env.block.time.seconds())?;
+        ORGANIZATIONS.save(storage, voter, &org_info, env.block.height)?;
+    }
+    Ok(())
+}
+
+/// Owner or governance contract
+fn is_admin(config: &Config, sender: &Addr) -> bool {
+    config.owner == *sender || config.governance.as_ref() == Some(sender)
+}
+
+fn validate_tally_params(params: &TallyParams) -> Result<(), ContractError> {
+    if params.threshold > Decimal::one() || params.veto_threshold > Decimal::one() {
+        return Err(ContractError::InvalidTallyParams {});
+    }
+    Ok(())
+}
+
+pub fn execute_challenge_claim(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    lender: Addr,
-    amount: Uint128,
+    claim_id: u64,
+    evidence: String,
 ) -> Result<Response, ContractError> {
-    let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &lender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
+    let mut claim = CLAIMS.load(deps.storage, claim_id)?;
+    if claim.challenge_id.is_some() {
+        return Err(ContractError::AlreadyChallenged {});
+    }
+    if claim.status != ClaimStatus::Approved {
+        return Err(ContractError::ClaimNotApproved {});
+    }
+    if env.block.time.seconds() > claim.challenge_end_time {
+        return Err(ContractError::ChallengePeriodEnded {});
+    }
+    ensure_accredited(deps.as_ref(), &env, &info.sender)?;
+    if claim.organization == info.sender {
+        return Err(ContractError::OwnClaim {});
+    }
 
-    let borrower_emissions = u32::try_from(borrower_info.emissions.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for emissions")))?;
-    let borrower_returned = u32::try_from(borrower_info.total_returned.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for total_returned")))?;
-    let borrower_total_borrowed = u32::try_from(borrower_info.total_borrowed.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for total_borrowed")))?;
-    let borrower_debt = u32::try_from(borrower_info.debt.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for debt")))?;
-    let borrower_credits = u32::try_from(borrower_info.carbon_credits.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for carbon_credits")))?;
-    let borrower_reputation = u32::try_from(borrower_info.reputation_score.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for reputation_score")))?;
-    let lender_credits = u32::try_from(lender_info.carbon_credits.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for lender carbon_credits")))?;
-    let lender_debt = u32::try_from(lender_info.debt.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for lender debt")))?;
-    
-    // let (eligibility_score, proof_data) = eligibility_proof(
-    //     borrower_emissions,
-    //     borrower_returned,
-    //     borrower_total_borrowed,
-    //     borrower_debt,
-    //     borrower_credits,
-    //     borrower_reputation,
-    //     lender_credits,
-    //     lender_debt,
-    // );
-    
-    let eligibility_score = borrower_reputation as u32 + borrower_returned as u32 - 
-                            (borrower_debt as u32 / 2) - (borrower_emissions as u32 / 10) + 
-                            (borrower_credits as u32) + 50; // Base score of 50
-    
-    // Cap the score between 0 and 100
-    let eligibility_score = if eligibility_score > 100 { 100 } else if eligibility_score < 0 { 0 } else { eligibility_score };
-    
-    let fake_proof = format!("{}{}{}{}{}{}{}{}",
-        borrower_emissions, borrower_returned, borrower_total_borrowed,
-        borrower_debt, borrower_credits, borrower_reputation,
-        lender_credits, lender_debt
-    );
-    let proof_hex = format!("mock_proof_{}_eligibility_{}", hex::encode(fake_proof.as_bytes()), eligibility_score);
-    
-    let mut lend_request_counter = LEND_REQUEST_COUNTER.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
-    let lendrequest = LendRequest {
-        id: lend_request_counter,
-        borrower: info.sender.clone(),
-        lender: lender.clone(),
-        status: LentStatus::Active,
-        eligibility_score: Uint128::from(eligibility_score as u128),
-        proof_data: proof_hex,
-        time: env.block.time.seconds(), 
-        amount: amount,
+    let challenge_id = CHALLENGE_COUNTER.may_load(deps.storage)?.unwrap_or_default();
+    let challenge = Challenge {
+        id: challenge_id,
+        claim_id,
+        challenger: info.sender.clone(),
+        evidence,
+        status: ChallengeStatus::Open,
+        created_at: env.block.time.seconds(),
+        created_height: env.block.height,
+        voting_end_time: env.block.time.seconds() + config.voting_period,
+        uphold_votes: Uint128::zero(),
+        dismiss_votes: Uint128::zero(),
     };
-    
-    // Save the lend request with the current counter as key
-    LEND_REQUESTS.save(deps.storage, lend_request_counter, &lendrequest)?;
-    
-    // Increment and save the counter
-    lend_request_counter += 1;
-   
-    LEND_REQUEST_COUNTER.save(deps.storage, &lend_request_counter)?;
-    
+    CHALLENGES.save(deps.storage, challenge_id, &challenge)?;
+    CHALLENGE_COUNTER.save(deps.storage, &(challenge_id + 1))?;
+
+    claim.status = ClaimStatus::Challenged;
+    claim.challenge_id = Some(challenge_id);
+    CLAIMS.save(deps.storage, claim_id, &claim)?;
+
     Ok(Response::new()
-        .add_attribute("method", "request_tokens")
-        .add_attribute("request_tokens_id", lend_request_counter.to_string())
-        .add_attribute("borrower", info.sender)
-        .add_attribute("lender", lender)
-        .add_attribute("requested_tokens", amount.to_string()))
+        .add_attribute("method", "challenge_claim")
+        .add_attribute("claim_id", claim_id.to_string())
+        .add_attribute("challenge_id", challenge_id.to_string())
+        .add_attribute("challenger", info.sender)
+        .add_attribute("voting_end_time", challenge.voting_end_time.to_string()))
 }
 
-
-pub fn execute_lend_tokens(
+pub fn execute_vote_on_challenge(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    lend_request_id: u64,
-    response: String,  // "accepted" or "denied" response
+    challenge_id: u64,
+    vote: VoteOption,
 ) -> Result<Response, ContractError> {
-    let lend_request = LEND_REQUESTS.load(deps.storage, lend_request_id)
-        .map_err(|_| ContractError::RequestNotFound {})?;
-
-    if lend_request.lender != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let mut challenge = CHALLENGES.load(deps.storage, challenge_id)?;
+    if challenge.status != ChallengeStatus::Open {
+        return Err(ContractError::ChallengeNotOpen {});
     }
-    if lend_request.status != LentStatus::Active {
-        return Err(ContractError::RequestNotActive {});
+    if env.block.time.seconds() > challenge.voting_end_time {
+        return Err(ContractError::VotingEnded {});
     }
-    let response_lowercase = response.to_lowercase();
-    if response_lowercase != "accepted" && response_lowercase != "denied" {
-        return Err(ContractError::InvalidResponse {});
+    ensure_accredited(deps.as_ref(), &env, &info.sender)?;
+    let claim = CLAIMS.load(deps.storage, challenge.claim_id)?;
+    if claim.organization == info.sender || challenge.challenger == info.sender {
+        return Err(ContractError::OwnClaim {});
     }
-    let mut updated_request = lend_request.clone();
-    if response_lowercase == "denied" {
-        updated_request.status = LentStatus::Rejected;
-        LEND_REQUESTS.save(deps.storage, lend_request_id, &updated_request)?;
-        updated_request.status = LentStatus::Rejected;
-        
-        return Ok(Response::new()
-            .add_attribute("method", "lend_tokens")
-            .add_attribute("lender", info.sender)
-            .add_attribute("borrower", updated_request.borrower)
-            .add_attribute("request_id", lend_request_id.to_string())
-            .add_attribute("response", "denied"));
-    }
-
-    // If accepted, process the lending
-    let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    
-    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &updated_request.borrower)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
+    if CHALLENGE_VOTES.has(deps.storage, (challenge_id, &info.sender)) {
+        return Err(ContractError::AlreadyVoted {});
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let weight = voting_power(deps.as_ref(), &env, &config, &info.sender, challenge.created_height)?;
+    if weight.is_zero() {
+        return Err(ContractError::NoVotingPower {});
+    }
+    CHALLENGE_VOTES.save(deps.storage, (challenge_id, &info.sender), &Vote {
+        voter: info.sender.clone(),
+        vote: vote.clone(),
+        weight,
+        timestamp: env.block.time.seconds(),
+    })?;
+    match vote {
+        VoteOption::Yes => challenge.uphold_votes = challenge.uphold_votes.checked_add(weight)?,
+        VoteOption::No => challenge.dismiss_votes = challenge.dismiss_votes.checked_add(weight)?,
+    }
+    CHALLENGES.save(deps.storage, challenge_id, &challenge)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "vote_on_challenge")
+        .add_attribute("challenge_id", challenge_id.to_string())
+        .add_attribute("voter", info.sender)
+        .add_attribute("weight", weight))
+}
+
+pub fn execute_resolve_challenge(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    challenge_id: u64,
+) -> Result<Response, ContractError> {
+    let mut challenge = CHALLENGES.load(deps.storage, challenge_id)?;
+    if challenge.status != ChallengeStatus::Open {
+        return Err(ContractError::ChallengeNotOpen {});
+    }
+    if env.block.time.seconds() <= challenge.voting_end_time {
+        return Err(ContractError::VotingNotEnded {});
+    }
+    let mut claim = CLAIMS.load(deps.storage, challenge.claim_id)?;
+    let mut config = CONFIG.load(deps.storage)?;
+
+    let total_votes = challenge.uphold_votes.checked_add(challenge.dismiss_votes)?;
+    let upheld = !total_votes.is_zero()
+        && total_votes >= config.tally_params.quorum
+        && Decimal::from_ratio(challenge.uphold_votes, total_votes) > config.tally_params.threshold;
+
+    if !upheld {
+        challenge.status = ChallengeStatus::Dismissed;
+        claim.status = ClaimStatus::Approved;
+        CHALLENGES.save(deps.storage, challenge_id, &challenge)?;
+        CLAIMS.save(deps.storage, claim.id, &claim)?;
+        return Ok(Response::new()
+            .add_attribute("method", "resolve_challenge")
+            .add_attribute("challenge_id", challenge_id.to_string())
+            .add_attribute("claim_id", claim.id.to_string())
+            .add_attribute("status", format!("{:?}", challenge.status)));
+    }
+
+    // Claw back the minted credits, whatever was already spent becomes debt. Credit tokens are
+    // still in escrow, and burned from there.
+    let mut org_info = ORGANIZATIONS.may_load(deps.storage, &claim.organization)?.unwrap_or_default();
+    let mut submessages = vec![];
+    let clawed_back = match &config.credit_token {
+        Some(token) => {
+            // Claims approved before escrow was introduced have nothing held back
+            let clawed_back = ESCROWED_CREDITS.may_load(deps.storage, claim.id)?.unwrap_or_default();
+            ESCROWED_CREDITS.remove(deps.storage, claim.id);
+            if !clawed_back.is_zero() {
+                submessages.push(SubMsg::new(WasmMsg::Execute {
+                    contract_addr: token.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::Burn { amount: clawed_back })?,
+                    funds: vec![],
+                }));
+            }
+            clawed_back
+        },
+        None => {
+            let clawed_back = org_info.carbon_credits.min(claim.demanded_tokens);
+            org_info.carbon_credits -= clawed_back;
+            clawed_back
+        },
+    };
+    let shortfall = claim.demanded_tokens - clawed_back;
+    org_info.debt = org_info.debt.checked_add(shortfall)?;
+    let now = env.block.time.seconds();
+    adjust_reputation(deps.storage, &config, &claim.organization, &mut org_info, ReputationReason::ChallengeUpheld, Some(claim.id), config.challenge_slash, now)?;
+    ORGANIZATIONS.save(deps.storage, &claim.organization, &org_info, env.block.height)?;
+    config.total_carbon_credits = config.total_carbon_credits.saturating_sub(clawed_back);
+    CONFIG.save(deps.storage, &config)?;
+
+    let yes_voters: Vec<Addr> = VOTES
+        .prefix(claim.id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((voter, vote)) if vote.vote == VoteOption::Yes => Some(Ok(voter)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    for voter in &yes_voters {
+        if let Some(mut voter_info) = ORGANIZATIONS.may_load(deps.storage, voter)? {
+            adjust_reputation(deps.storage, &config, voter, &mut voter_info, ReputationReason::ChallengeUpheld, Some(claim.id), config.challenge_slash, now)?;
+            ORGANIZATIONS.save(deps.storage, voter, &voter_info, env.block.height)?;
+        }
+    }
+
+    // The clawed back credits void the claim's serials the claimant still holds. Batches it passed
+    // on stay with their holders, the shortfall being its debt.
+    let serials_owner = match &config.credit_token {
+        Some(_) => &env.contract.address,
+        None => &claim.organization,
+    };
+    let voided_serials = void_claim_serials(deps.storage, claim.id, serials_owner, clawed_back)?;
+    // and the claimant keeps no more serials than it has credits left
+    if config.credit_token.is_none() {
+        let held = serials_held(deps.storage, &claim.organization)?;
+        if held > org_info.carbon_credits {
+            retire_serials(deps.storage, &claim.organization, held - org_info.carbon_credits)?;
+        }
+    }
+
+    challenge.status = ChallengeStatus::Upheld;
+    claim.status = ClaimStatus::Revoked;
+    unindex_claim(deps.storage, &claim);
+    CHALLENGES.save(deps.storage, challenge_id, &challenge)?;
+    CLAIMS.save(deps.storage, claim.id, &claim)?;
+
+    Ok(Response::new()
+        .add_submessages(submessages)
+        .add_attribute("method", "resolve_challenge")
+        .add_attribute("challenge_id", challenge_id.to_string())
+        .add_attribute("claim_id", claim.id.to_string())
+        .add_attribute("status", format!("{:?}", challenge.status))
+        .add_attribute("clawed_back", clawed_back)
+        .add_attribute("debited", shortfall)
+        .add_attribute("slashed_voters", yes_voters.len().to_string())
+        .add_attribute("voided_serials", voided_serials))
+}
+
+/// Consumes up to `amount` serials from the lowest end of `holder`'s batches of a claim
+fn void_claim_serials(storage: &mut dyn Storage, claim_id: u64, holder: &Addr, amount: Uint128) -> StdResult<Uint128> {
+    let batches = credit_batches()
+        .idx
+        .claim
+        .prefix(claim_id)
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| item.as_ref().map_or(true, |(_, batch)| batch.owner == *holder))
+        .map(|item| item.map(|(_, batch)| batch))
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut remaining = amount;
+    for mut batch in batches {
+        if remaining.is_zero() {
+            break;
+        }
+        let taken = remaining.min(batch.amount());
+        if taken == batch.amount() {
+            remove_batch(storage, batch.id)?;
+        } else {
+            batch.serial_start += taken;
+            credit_batches().save(storage, batch.id, &batch)?;
+        }
+        remaining -= taken;
+    }
+    Ok(amount - remaining)
+}
+
+pub fn execute_release_credits(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    claim_id: u64,
+) -> Result<Response, ContractError> {
+    let claim = CLAIMS.load(deps.storage, claim_id)?;
+    if claim.status != ClaimStatus::Approved {
+        return Err(ContractError::ClaimNotApproved {});
+    }
+    if env.block.time.seconds() <= claim.challenge_end_time {
+        return Err(ContractError::ChallengePeriodNotEnded {});
+    }
+    let amount = ESCROWED_CREDITS.may_load(deps.storage, claim_id)?.ok_or(ContractError::NoEscrowedCredits {})?;
+    ESCROWED_CREDITS.remove(deps.storage, claim_id);
+    let config = CONFIG.load(deps.storage)?;
+    let token = config.credit_token.ok_or(ContractError::NoEscrowedCredits {})?;
+    let transfer = credit_transfer(deps.storage, &token, &claim.organization, amount, env.block.height)?;
+
+    Ok(Response::new()
+        .add_submessage(transfer)
+        .add_attribute("method", "release_credits")
+        .add_attribute("claim_id", claim_id.to_string())
+        .add_attribute("organization", claim.organization)
+        .add_attribute("amount", amount))
+}
+
+/// Records the serial range for an approved claim's credits, owned by `owner`
+fn issue_batch(storage: &mut dyn Storage, claim: &Claim, owner: &Addr) -> StdResult<Option<CreditBatch>> {
+    if claim.demanded_tokens.is_zero() {
+        return Ok(None);
+    }
+    let id = BATCH_COUNTER.load(storage)?;
+    let serial_start = NEXT_SERIAL.load(storage)?;
+    let serial_end = serial_start.checked_add(claim.demanded_tokens)? - Uint128::new(1);
+    let batch = CreditBatch {
+        id,
+        claim_id: claim.id,
+        owner: owner.clone(),
+        polygon_hash: polygon_hash(&claim.longitudes, &claim.latitudes),
+        vintage_start: claim.time_started,
+        vintage_end: claim.time_ended,
+        serial_start,
+        serial_end,
+        methodology: claim.methodology.clone(),
+        parent_id: None,
+    };
+    create_batch(storage, &batch)?;
+    NEXT_SERIAL.save(storage, &(serial_end + Uint128::new(1)))?;
+    Ok(Some(batch))
+}
+
+pub fn polygon_hash(longitudes: &[String], latitudes: &[String]) -> String {
+    let preimage = format!("{}|{}", longitudes.join(","), latitudes.join(","));
+    hex::encode(Sha256::digest(preimage.as_bytes()))
+}
+
+fn load_batch(storage: &dyn Storage, token_id: &str) -> Result<CreditBatch, ContractError> {
+    let id: u64 = token_id.parse().map_err(|_| ContractError::BatchNotFound {})?;
+    credit_batches().may_load(storage, id)?.ok_or(ContractError::BatchNotFound {})
+}
+
+/// Saves a new batch, whose id must be the next from BATCH_COUNTER
+fn create_batch(storage: &mut dyn Storage, batch: &CreditBatch) -> StdResult<()> {
+    credit_batches().save(storage, batch.id, batch)?;
+    BATCH_COUNTER.save(storage, &(batch.id + 1))?;
+    BATCH_COUNT.update(storage, |count| -> StdResult<_> { Ok(count + 1) })?;
+    Ok(())
+}
+
+fn remove_batch(storage: &mut dyn Storage, id: u64) -> StdResult<()> {
+    credit_batches().remove(storage, id)?;
+    clear_approvals(storage, id)?;
+    BATCH_COUNT.update(storage, |count| -> StdResult<_> { Ok(count.saturating_sub(1)) })?;
+    Ok(())
+}
+
+/// Gives a batch to a new owner; approvals granted by the previous one lapse
+fn hand_over_batch(storage: &mut dyn Storage, batch: &mut CreditBatch, owner: &Addr) -> StdResult<()> {
+    batch.owner = owner.clone();
+    clear_approvals(storage, batch.id)?;
+    credit_batches().save(storage, batch.id, batch)
+}
+
+fn clear_approvals(storage: &mut dyn Storage, id: u64) -> StdResult<()> {
+    let spenders = NFT_APPROVALS
+        .prefix(id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for spender in spenders {
+        NFT_APPROVALS.remove(storage, (id, &spender));
+    }
+    Ok(())
+}
+
+/// The owner of a batch, or a spender or operator it approved, may transfer or send it
+fn ensure_can_send(deps: Deps, env: &Env, sender: &Addr, batch: &CreditBatch) -> Result<(), ContractError> {
+    if batch.owner == *sender {
+        return Ok(());
+    }
+    let approved = NFT_APPROVALS.may_load(deps.storage, (batch.id, sender))?
+        .is_some_and(|expires| !expires.is_expired(&env.block));
+    let operator = NFT_OPERATORS.may_load(deps.storage, (&batch.owner, sender))?
+        .is_some_and(|expires| !expires.is_expired(&env.block));
+    if !approved && !operator {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// The owner of a batch, or an operator it approved, may manage the batch's approvals
+fn ensure_can_approve(deps: Deps, env: &Env, sender: &Addr, batch: &CreditBatch) -> Result<(), ContractError> {
+    if batch.owner == *sender {
+        return Ok(());
+    }
+    let operator = NFT_OPERATORS.may_load(deps.storage, (&batch.owner, sender))?
+        .is_some_and(|expires| !expires.is_expired(&env.block));
+    if !operator {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn unexpired(env: &Env, expires: Option<Expiration>) -> Result<Expiration, ContractError> {
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+    Ok(expires)
+}
+
+pub fn execute_approve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: Addr,
+    token_id: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let batch = load_batch(deps.storage, &token_id)?;
+    ensure_can_approve(deps.as_ref(), &env, &info.sender, &batch)?;
+    let spender = deps.api.addr_validate(spender.as_str())?;
+    if spender == batch.owner {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+    NFT_APPROVALS.save(deps.storage, (batch.id, &spender), &unexpired(&env, expires)?)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "approve")
+        .add_attribute("sender", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("token_id", token_id))
+}
+
+pub fn execute_revoke(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: Addr,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let batch = load_batch(deps.storage, &token_id)?;
+    ensure_can_approve(deps.as_ref(), &env, &info.sender, &batch)?;
+    NFT_APPROVALS.remove(deps.storage, (batch.id, &spender));
+
+    Ok(Response::new()
+        .add_attribute("method", "revoke")
+        .add_attribute("sender", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("token_id", token_id))
+}
+
+pub fn execute_approve_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: Addr,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let operator = deps.api.addr_validate(operator.as_str())?;
+    if operator == info.sender {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+    NFT_OPERATORS.save(deps.storage, (&info.sender, &operator), &unexpired(&env, expires)?)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "approve_all")
+        .add_attribute("sender", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn execute_revoke_all(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operator: Addr,
+) -> Result<Response, ContractError> {
+    NFT_OPERATORS.remove(deps.storage, (&info.sender, &operator));
+
+    Ok(Response::new()
+        .add_attribute("method", "revoke_all")
+        .add_attribute("sender", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn execute_transfer_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Addr,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let mut batch = load_batch(deps.storage, &token_id)?;
+    ensure_can_send(deps.as_ref(), &env, &info.sender, &batch)?;
+    let owner = batch.owner.clone();
+    let recipient = deps.api.addr_validate(recipient.as_str())?;
+    // The credits the batch backs go with it
+    move_credits(deps.storage, &owner, &recipient, batch.amount(), env.block.height)?;
+    hand_over_batch(deps.storage, &mut batch, &recipient)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "transfer_nft")
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("token_id", token_id))
+}
+
+pub fn execute_send_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: Addr,
+    token_id: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let mut batch = load_batch(deps.storage, &token_id)?;
+    ensure_can_send(deps.as_ref(), &env, &info.sender, &batch)?;
+    let owner = batch.owner.clone();
+    let contract = deps.api.addr_validate(contract.as_str())?;
+    move_credits(deps.storage, &owner, &contract, batch.amount(), env.block.height)?;
+    hand_over_batch(deps.storage, &mut batch, &contract)?;
+
+    let receive_msg = Cw721ReceiverMsg::ReceiveNft(Cw721ReceiveMsg {
+        sender: info.sender.to_string(),
+        token_id: token_id.clone(),
+        msg,
+    });
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: contract.to_string(),
+            msg: to_json_binary(&receive_msg)?,
+            funds: vec![],
+        })
+        .add_attribute("method", "send_nft")
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", contract)
+        .add_attribute("token_id", token_id))
+}
+
+pub fn execute_split_batch(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    token_id: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let mut batch = load_batch(deps.storage, &token_id)?;
+    if batch.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if amount.is_zero() || amount >= batch.amount() {
+        return Err(ContractError::InvalidSplit {});
+    }
+
+    let new_id = BATCH_COUNTER.load(deps.storage)?;
+    let split = CreditBatch {
+        id: new_id,
+        serial_start: batch.serial_end + Uint128::new(1) - amount,
+        parent_id: Some(batch.id),
+        ..batch.clone()
+    };
+    batch.serial_end = split.serial_start - Uint128::new(1);
+    credit_batches().save(deps.storage, batch.id, &batch)?;
+    create_batch(deps.storage, &split)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "split_batch")
+        .add_attribute("token_id", token_id)
+        .add_attribute("new_token_id", new_id.to_string())
+        .add_attribute("amount", amount))
+}
+
+/// Permanently burns credits to offset the beneficiary's emissions. Serials are taken from the
+/// sender's batches, or with credit tokens from those still outstanding, oldest first; credits
+/// held since before batches were issued retire without serials. `paid_in_tokens` is set when the
+/// credits were sent through `ReceiveMsg::RetireCredits`.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_retire_credits(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    beneficiary: Addr,
+    period: u32,
+    reason: String,
+    paid_in_tokens: bool,
+) -> Result<Response, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let beneficiary = deps.api.addr_validate(beneficiary.as_str())?;
+    let mut config = CONFIG.load(deps.storage)?;
+
+    let mut submessages = vec![];
+    match &config.credit_token {
+        Some(token) => {
+            if !paid_in_tokens {
+                return Err(ContractError::TokenPaymentRequired {});
+            }
+            submessages.push(SubMsg::new(WasmMsg::Execute {
+                contract_addr: token.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn { amount })?,
+                funds: vec![],
+            }));
+        },
+        None => {
+            let mut retiree_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+            if retiree_info.carbon_credits < amount {
+                return Err(ContractError::NotEnoughCredits {});
+            }
+            retiree_info.carbon_credits -= amount;
+            ORGANIZATIONS.save(deps.storage, &info.sender, &retiree_info, env.block.height)?;
+        },
+    }
+    config.total_carbon_credits = config.total_carbon_credits.saturating_sub(amount);
+    CONFIG.save(deps.storage, &config)?;
+
+    let serials_owner = match &config.credit_token {
+        Some(_) => &env.contract.address,
+        None => &info.sender,
+    };
+    let serials = retire_serials(deps.storage, serials_owner, amount)?;
+
+    PERIOD_OFFSETS.update(deps.storage, (&beneficiary, period), |offsets| -> StdResult<_> {
+        Ok(offsets.unwrap_or_default().checked_add(amount)?)
+    })?;
+    update_net_emissions(deps.storage, &beneficiary, env.block.height)?;
+
+    let id = RETIREMENT_COUNTER.load(deps.storage)?;
+    let certificate = RetirementCertificate {
+        id,
+        retiree: info.sender.clone(),
+        beneficiary: beneficiary.clone(),
+        amount,
+        reason,
+        serials,
+        period,
+        time: env.block.time.seconds(),
+    };
+    RETIREMENTS.save(deps.storage, id, &certificate)?;
+    RETIREMENT_COUNTER.save(deps.storage, &(id + 1))?;
+
+    Ok(Response::new()
+        .add_submessages(submessages)
+        .add_attribute("method", "retire_credits")
+        .add_attribute("certificate_id", id.to_string())
+        .add_attribute("retiree", info.sender)
+        .add_attribute("beneficiary", beneficiary)
+        .add_attribute("period", period.to_string())
+        .add_attribute("amount", amount))
+}
+
+/// `owner`'s oldest batches holding up to `amount` serials between them
+fn oldest_batches(storage: &dyn Storage, owner: &Addr, amount: Uint128) -> StdResult<Vec<CreditBatch>> {
+    let mut remaining = amount;
+    let mut batches = vec![];
+    for item in credit_batches().idx.owner.prefix(owner.clone()).range(storage, None, None, Order::Ascending) {
+        if remaining.is_zero() {
+            break;
+        }
+        let (_, batch) = item?;
+        remaining = remaining.saturating_sub(batch.amount());
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+fn serials_held(storage: &dyn Storage, owner: &Addr) -> StdResult<Uint128> {
+    credit_batches()
+        .idx
+        .owner
+        .prefix(owner.clone())
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, batch)| batch.amount()))
+        .sum()
+}
+
+/// Hands `amount` of `from`'s serials to `to` along with the credits they back, oldest first,
+/// splitting the last batch like `SplitBatch`. Credits held since before batches were issued
+/// have no serials to hand over.
+fn move_serials(storage: &mut dyn Storage, from: &Addr, to: &Addr, amount: Uint128) -> StdResult<()> {
+    let mut remaining = amount;
+    for mut batch in oldest_batches(storage, from, amount)? {
+        let taken = remaining.min(batch.amount());
+        if taken == batch.amount() {
+            hand_over_batch(storage, &mut batch, to)?;
+        } else {
+            let new_id = BATCH_COUNTER.load(storage)?;
+            let split = CreditBatch {
+                id: new_id,
+                owner: to.clone(),
+                serial_start: batch.serial_end + Uint128::new(1) - taken,
+                parent_id: Some(batch.id),
+                ..batch.clone()
+            };
+            batch.serial_end = split.serial_start - Uint128::new(1);
+            credit_batches().save(storage, batch.id, &batch)?;
+            create_batch(storage, &split)?;
+        }
+        remaining -= taken;
+    }
+    Ok(())
+}
+
+/// Consumes up to `amount` serials from the lowest end of `owner`'s batches
+fn retire_serials(storage: &mut dyn Storage, owner: &Addr, amount: Uint128) -> StdResult<Vec<RetiredSerials>> {
+    let mut remaining = amount;
+    let mut serials = vec![];
+    for mut batch in oldest_batches(storage, owner, amount)? {
+        let taken = remaining.min(batch.amount());
+        serials.push(RetiredSerials {
+            batch_id: batch.id,
+            claim_id: batch.claim_id,
+            serial_start: batch.serial_start,
+            serial_end: batch.serial_start + taken - Uint128::new(1),
         });
+        if taken == batch.amount() {
+            remove_batch(storage, batch.id)?;
+        } else {
+            batch.serial_start += taken;
+            credit_batches().save(storage, batch.id, &batch)?;
+        }
+        remaining -= taken;
+    }
+    Ok(serials)
+}
+
+fn validate_eligibility_policy(policy: &EligibilityPolicy) -> Result<(), ContractError> {
+    if policy.min_score > policy.max_score {
+        return Err(ContractError::InvalidEligibilityPolicy {});
+    }
+    Ok(())
+}
+
+pub fn execute_update_eligibility_policy(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    policy: EligibilityPolicy,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !is_admin(&config, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    validate_eligibility_policy(&policy)?;
+    ELIGIBILITY_POLICY.save(deps.storage, &policy)?;
+    let version = ELIGIBILITY_POLICY_VERSION.may_load(deps.storage)?.unwrap_or_default() + 1;
+    ELIGIBILITY_POLICY_VERSION.save(deps.storage, &version)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_eligibility_policy")
+        .add_attribute("version", version.to_string())
+        .add_attribute("min_score", policy.min_score)
+        .add_attribute("max_score", policy.max_score))
+}
+
+pub fn execute_set_eligibility_key(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    verifying_key: Binary,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !is_admin(&config, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    validate_verifying_key(&verifying_key)?;
+    ELIGIBILITY_KEY.save(deps.storage, &verifying_key)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_eligibility_key")
+        .add_attribute("key_hash", hex::encode(Sha256::digest(verifying_key.as_slice()))))
+}
+
+/// Once an eligibility key is set, borrowers prove a minimum score instead of having it computed
+/// from their records. Returns the proven threshold along with the proof.
+fn checked_eligibility_proof(deps: Deps, config: &Config, env: &Env, borrower: &Addr, amount: Uint128, proof: Option<EligibilityProof>) -> Result<Option<EligibilityProof>, ContractError> {
+    let verifying_key = match ELIGIBILITY_KEY.may_load(deps.storage)? {
+        Some(verifying_key) => verifying_key,
+        None if proof.is_some() => return Err(ContractError::InvalidProof {}),
+        None => return Ok(None),
+    };
+    let proof = proof.ok_or(ContractError::InvalidProof {})?;
+    let policy = ELIGIBILITY_POLICY.load(deps.storage)?;
+    if proof.threshold < policy.min_score {
+        return Err(ContractError::BorrowerNotEligible {});
+    }
+    // The proof is checked against the factors of the same snapshot an unproven request is scored
+    // on, and can't stand for more than the score they give
+    let borrower_info = eligibility_snapshot(deps, env, config, borrower)?;
+    if proof.threshold > policy.score(&borrower_info)? {
+        return Err(ContractError::InvalidProof {});
+    }
+    verify_eligibility_proof(&verifying_key, &proof.proof, proof.threshold, borrower, amount, &policy.factors(&borrower_info)?)?;
+    Ok(Some(proof))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    voting_period: Option<u64>,
+    reveal_period: Option<u64>,
+    unrevealed_penalty: Option<Uint128>,
+    challenge_period: Option<u64>,
+    challenge_slash: Option<Uint128>,
+    vote_weights: Option<VoteWeights>,
+    tally_params: Option<TallyParams>,
+    governance: Option<Addr>,
+    default_penalty: Option<Uint128>,
+    reborrow_reputation: Option<Uint128>,
+    collateral: Option<CollateralParams>,
+    pool: Option<PoolParams>,
+    lend_request_expiry: Option<u64>,
+    reputation: Option<ReputationParams>,
+    proof_validity: Option<u64>,
+    require_eligibility_proof: Option<bool>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(voting_period) = voting_period {
+        config.voting_period = voting_period;
+    }
+    if let Some(reveal_period) = reveal_period {
+        config.reveal_period = reveal_period;
+    }
+    if let Some(unrevealed_penalty) = unrevealed_penalty {
+        config.unrevealed_penalty = unrevealed_penalty;
+    }
+    if let Some(challenge_period) = challenge_period {
+        config.challenge_period = challenge_period;
+    }
+    if let Some(challenge_slash) = challenge_slash {
+        config.challenge_slash = challenge_slash;
+    }
+    if let Some(vote_weights) = vote_weights {
+        config.vote_weights = vote_weights;
+    }
+    if let Some(tally_params) = tally_params {
+        validate_tally_params(&tally_params)?;
+        config.tally_params = tally_params;
+    }
+    if let Some(governance) = governance {
+        config.governance = Some(governance);
+    }
+    if let Some(default_penalty) = default_penalty {
+        config.default_penalty = default_penalty;
+    }
+    if let Some(reborrow_reputation) = reborrow_reputation {
+        config.reborrow_reputation = reborrow_reputation;
+    }
+    if let Some(collateral) = collateral {
+        config.collateral = Some(collateral);
+    }
+    if let Some(pool) = pool {
+        // Interest up to now accrues at the old rates
+        let mut lending_pool = POOL.load(deps.storage)?;
+        lending_pool.accrue(&config.pool, env.block.time.seconds());
+        POOL.save(deps.storage, &lending_pool)?;
+        config.pool = pool;
+    }
+    if let Some(lend_request_expiry) = lend_request_expiry {
+        config.lend_request_expiry = lend_request_expiry;
+    }
+    if let Some(reputation) = reputation {
+        config.reputation = reputation;
+    }
+    if let Some(proof_validity) = proof_validity {
+        config.proof_validity = proof_validity;
+    }
+    if let Some(require_eligibility_proof) = require_eligibility_proof {
+        config.require_eligibility_proof = require_eligibility_proof;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_config")
+        .add_attribute("owner", info.sender))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_request_tokens(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    lender: Addr,
+    amount: Uint128,
+    interest_rate: Decimal,
+    term: u64,
+    proof: Option<EligibilityProof>,
+) -> Result<Response, ContractError> {
+    if term == 0 {
+        return Err(ContractError::InvalidLoanTerm {});
+    }
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    let config = CONFIG.load(deps.storage)?;
+    decay_reputation(deps.storage, &config, &info.sender, &mut borrower_info, env.block.time.seconds())?;
+    ORGANIZATIONS.save(deps.storage, &info.sender, &borrower_info, env.block.height)?;
+    ensure_can_borrow(&config, &borrower_info)?;
+    let collateral = posted_collateral(&config, &info, amount)?;
+    let (eligibility_score, proof_hex) = match checked_eligibility_proof(deps.as_ref(), &config, &env, &info.sender, amount, proof)? {
+        Some(proof) => (proof.threshold, hex::encode(proof.proof.as_slice())),
+        None => {
+            // Scored on the state at the start of the block, so credits moved in within it don't count
+            let borrower_info = eligibility_snapshot(deps.as_ref(), &env, &config, &info.sender)?;
+            (verified_eligibility_score(&ELIGIBILITY_POLICY.load(deps.storage)?, &borrower_info)?, String::new())
+        },
+    };
+    
+    let mut lend_request_counter = LEND_REQUEST_COUNTER.load(deps.storage)?;
+    let lendrequest = LendRequest {
+        id: lend_request_counter,
+        borrower: info.sender.clone(),
+        lender: lender.clone(),
+        status: LentStatus::Active,
+        eligibility_score,
+        proof_data: proof_hex,
+        time: env.block.time.seconds(), 
+        amount,
+        interest_rate,
+        term,
+        collateral,
+        expires_at: env.block.time.seconds() + config.lend_request_expiry,
+        counter_amount: None,
+    };
+    
+    // Save the lend request with the current counter as key
+    LEND_REQUESTS.save(deps.storage, lend_request_counter, &lendrequest)?;
+    
+    // Increment and save the counter
+    lend_request_counter += 1;
+   
+    LEND_REQUEST_COUNTER.save(deps.storage, &lend_request_counter)?;
+    
+    Ok(Response::new()
+        .add_attribute("method", "request_tokens")
+        .add_attribute("request_tokens_id", lend_request_counter.to_string())
+        .add_attribute("borrower", info.sender)
+        .add_attribute("lender", lender)
+        .add_attribute("requested_tokens", amount.to_string()))
+}
+
+
+/// `payment` is the credit token amount sent through `ReceiveMsg::LendTokens` or `ReceiveMsg::CounterOffer`
+pub fn execute_lend_tokens(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    lend_request_id: u64,
+    response: LendResponse,
+    payment: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let lend_request = LEND_REQUESTS.load(deps.storage, lend_request_id)
+        .map_err(|_| ContractError::RequestNotFound {})?;
+
+    if lend_request.lender != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if lend_request.status != LentStatus::Active {
+        return Err(ContractError::RequestNotActive {});
+    }
+    if lend_request.is_expired(env.block.time.seconds()) {
+        return Err(ContractError::RequestExpired {});
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let mut updated_request = lend_request.clone();
+    match response {
+        LendResponse::Deny => {
+            updated_request.status = LentStatus::Rejected;
+            LEND_REQUESTS.save(deps.storage, lend_request_id, &updated_request)?;
+
+            Ok(Response::new()
+                .add_messages(release_collateral(&updated_request.borrower, updated_request.collateral.clone()))
+                .add_attribute("method", "lend_tokens")
+                .add_attribute("lender", info.sender)
+                .add_attribute("borrower", updated_request.borrower)
+                .add_attribute("request_id", lend_request_id.to_string())
+                .add_attribute("response", "denied"))
+        },
+        LendResponse::Counter { amount } => {
+            if amount.is_zero() {
+                return Err(ContractError::InvalidAmount {});
+            }
+            // The countered amount is held until the borrower decides
+            match (&config.credit_token, payment) {
+                (Some(_), Some(sent)) => {
+                    if sent != amount {
+                        return Err(ContractError::InvalidPayment { sent, expected: amount });
+                    }
+                },
+                (Some(_), None) => return Err(ContractError::TokenPaymentRequired {}),
+                (None, _) => {
+                    let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
+                        .unwrap_or_default();
+                    if lender_info.carbon_credits < amount {
+                        return Err(ContractError::NotEnoughCredits {});
+                    }
+                    lender_info.carbon_credits -= amount;
+                    ORGANIZATIONS.save(deps.storage, &info.sender, &lender_info, env.block.height)?;
+                    move_serials(deps.storage, &info.sender, &env.contract.address, amount)?;
+                },
+            }
+            updated_request.status = LentStatus::Countered;
+            updated_request.counter_amount = Some(amount);
+            LEND_REQUESTS.save(deps.storage, lend_request_id, &updated_request)?;
+
+            Ok(Response::new()
+                .add_attribute("method", "lend_tokens")
+                .add_attribute("lender", info.sender)
+                .add_attribute("borrower", updated_request.borrower)
+                .add_attribute("request_id", lend_request_id.to_string())
+                .add_attribute("response", "countered")
+                .add_attribute("amount", amount))
+        },
+        LendResponse::Accept => {
+            let amount = updated_request.amount;
+            match (&config.credit_token, payment) {
+                (Some(_), Some(sent)) => {
+                    if sent != amount {
+                        return Err(ContractError::InvalidPayment { sent, expected: amount });
+                    }
+                },
+                (Some(_), None) => return Err(ContractError::TokenPaymentRequired {}),
+                (None, _) => {
+                    let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
+                        .unwrap_or_default();
+                    if lender_info.carbon_credits < amount {
+                        return Err(ContractError::NotEnoughCredits {});
+                    }
+                    lender_info.carbon_credits -= amount;
+                    ORGANIZATIONS.save(deps.storage, &info.sender, &lender_info, env.block.height)?;
+                    move_serials(deps.storage, &info.sender, &env.contract.address, amount)?;
+                },
+            }
+            let (loan, submessages) = lend_on_request(deps.storage, &config, &env, lend_request_id, &mut updated_request, amount)?;
+
+            Ok(Response::new()
+                .add_submessages(submessages)
+                .add_attribute("method", "lend_tokens")
+                .add_attribute("lender", info.sender)
+                .add_attribute("borrower", updated_request.borrower)
+                .add_attribute("request_id", lend_request_id.to_string())
+                .add_attribute("loan_id", loan.id.to_string())
+                .add_attribute("due_at", loan.due_at.to_string())
+                .add_attribute("amount", amount)
+                .add_attribute("response", "accepted"))
+        },
+    }
+}
+
+/// Under `require_eligibility_proof`, a loan needs a current record of the borrower's eligibility
+/// to borrow at least `amount` from `lender`
+fn ensure_eligibility_record(storage: &dyn Storage, config: &Config, env: &Env, borrower: &Addr, lender: &Addr, amount: Uint128) -> Result<(), ContractError> {
+    if !config.require_eligibility_proof {
+        return Ok(());
+    }
+    let policy_version = ELIGIBILITY_POLICY_VERSION.may_load(storage)?.unwrap_or_default();
+    let current = PROOFS.may_load(storage, (borrower, lender))?
+        .is_some_and(|record| record.is_current(amount, policy_version, env.block.time.seconds()));
+    if !current {
+        return Err(ContractError::EligibilityProofRequired {});
+    }
+    Ok(())
+}
+
+/// Opens a loan of `amount` on an approved request. The lender's credits must already have been
+/// taken: debited from its ledger balance or received as credit tokens.
+fn lend_on_request(
+    storage: &mut dyn Storage,
+    config: &Config,
+    env: &Env,
+    lend_request_id: u64,
+    lend_request: &mut LendRequest,
+    amount: Uint128,
+) -> Result<(Loan, Vec<SubMsg>), ContractError> {
+    // The proof has to cover the amount actually lent, countered or not
+    ensure_eligibility_record(storage, config, env, &lend_request.borrower, &lend_request.lender, amount)?;
+    let mut borrower_info = ORGANIZATIONS.may_load(storage, &lend_request.borrower)?
+        .unwrap_or_default();
+    decay_reputation(storage, config, &lend_request.borrower, &mut borrower_info, env.block.time.seconds())?;
+    // The borrower may have defaulted on another loan since asking
+    ensure_can_borrow(config, &borrower_info)?;
+
+    let mut submessages = vec![];
+    match &config.credit_token {
+        Some(token) => submessages.push(credit_transfer(storage, token, &lend_request.borrower, amount, env.block.height)?),
+        None => {
+            borrower_info.carbon_credits += amount;
+            move_serials(storage, &env.contract.address, &lend_request.borrower, amount)?;
+        },
+    }
+    borrower_info.debt += amount;
+    borrower_info.times_borrowed += 1;
+    borrower_info.total_borrowed += amount;
+    lend_request.status = LentStatus::Approved;
+
+    let loan_id = LOAN_COUNTER.load(storage)?;
+    let loan = Loan {
+        id: loan_id,
+        lend_request_id: Some(lend_request_id),
+        offer_id: None,
+        borrower: lend_request.borrower.clone(),
+        lender: lend_request.lender.clone(),
+        principal: amount,
+        interest_rate: lend_request.interest_rate,
+        interest: Uint128::zero(),
+        repaid: Uint128::zero(),
+        status: LoanStatus::Open,
+        created_at: env.block.time.seconds(),
+        due_at: env.block.time.seconds() + lend_request.term,
+        last_accrued: env.block.time.seconds(),
+        collateral: lend_request.collateral.clone(),
+    };
+    loans().save(storage, loan_id, &loan)?;
+    LOAN_COUNTER.save(storage, &(loan_id + 1))?;
+
+    ORGANIZATIONS.save(storage, &lend_request.borrower, &borrower_info, env.block.height)?;
+    LEND_REQUESTS.save(storage, lend_request_id, lend_request)?;
+    Ok((loan, submessages))
+}
+
+pub fn execute_cancel_lend_request(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    lend_request_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut lend_request = LEND_REQUESTS.load(deps.storage, lend_request_id)
+        .map_err(|_| ContractError::RequestNotFound {})?;
+    let expired = lend_request.is_expired(env.block.time.seconds());
+    // An absent borrower must not lock the lender's escrow, so expired requests are settled by anyone
+    if lend_request.borrower != info.sender && !expired {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !matches!(lend_request.status, LentStatus::Active | LentStatus::Countered) {
+        return Err(ContractError::RequestNotActive {});
+    }
+
+    let mut submessages = vec![];
+    if let Some(counter_amount) = lend_request.counter_amount {
+        match &config.credit_token {
+            Some(token) => submessages.push(credit_transfer(deps.storage, token, &lend_request.lender, counter_amount, env.block.height)?),
+            None => {
+                let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &lend_request.lender)?
+                    .unwrap_or_default();
+                lender_info.carbon_credits += counter_amount;
+                ORGANIZATIONS.save(deps.storage, &lend_request.lender, &lender_info, env.block.height)?;
+                move_serials(deps.storage, &env.contract.address, &lend_request.lender, counter_amount)?;
+            },
+        }
+    }
+    lend_request.status = if expired {
+        LentStatus::Expired
+    } else {
+        LentStatus::Cancelled
+    };
+    LEND_REQUESTS.save(deps.storage, lend_request_id, &lend_request)?;
+
+    Ok(Response::new()
+        .add_submessages(submessages)
+        .add_messages(release_collateral(&lend_request.borrower, lend_request.collateral.clone()))
+        .add_attribute("method", "cancel_lend_request")
+        .add_attribute("request_id", lend_request_id.to_string())
+        .add_attribute("borrower", lend_request.borrower)
+        .add_attribute("sender", info.sender))
+}
+
+pub fn execute_accept_counter_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    lend_request_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut lend_request = LEND_REQUESTS.load(deps.storage, lend_request_id)
+        .map_err(|_| ContractError::RequestNotFound {})?;
+    if lend_request.borrower != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if lend_request.status != LentStatus::Countered {
+        return Err(ContractError::RequestNotActive {});
+    }
+    if lend_request.is_expired(env.block.time.seconds()) {
+        return Err(ContractError::RequestExpired {});
+    }
+    let amount = lend_request.counter_amount.unwrap_or_default();
+
+    // Collateral posted for the original amount can be topped up to cover the counter-offer
+    if let Some(top_up) = posted_collateral(&config, &info, Uint128::zero())? {
+        let mut collateral = lend_request.collateral.unwrap_or(Coin { denom: top_up.denom.clone(), amount: Uint128::zero() });
+        collateral.amount += top_up.amount;
+        lend_request.collateral = Some(collateral);
+    }
+    if let (Some(params), Some(collateral)) = (&config.collateral, &lend_request.collateral) {
+        let required = amount * params.ratio;
+        if collateral.amount < required {
+            return Err(ContractError::InsufficientCollateral { required });
+        }
+    }
+
+    // The countered credits were escrowed when the lender answered
+    let (loan, submessages) = lend_on_request(deps.storage, &config, &env, lend_request_id, &mut lend_request, amount)?;
+
+    Ok(Response::new()
+        .add_submessages(submessages)
+        .add_attribute("method", "accept_counter_offer")
+        .add_attribute("lender", lend_request.lender)
+        .add_attribute("borrower", info.sender)
+        .add_attribute("request_id", lend_request_id.to_string())
+        .add_attribute("loan_id", loan.id.to_string())
+        .add_attribute("due_at", loan.due_at.to_string())
+        .add_attribute("amount", amount))
+}
+
+pub fn execute_verify_eligibility(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    borrower: Addr,
+    lender: Addr,
+    amount: Uint128,
+    proof: Option<EligibilityProof>,
+) -> Result<Response, ContractError> {
+    // Records gate the borrower's loans, so nobody else may replace them
+    if info.sender != borrower {
+        return Err(ContractError::Unauthorized {});
+    }
+    let config = CONFIG.load(deps.storage)?;
+    let (eligibility_score, proof_data, zero_knowledge) = match checked_eligibility_proof(deps.as_ref(), &config, &env, &borrower, amount, proof)? {
+        Some(proof) => (proof.threshold, proof.proof, true),
+        None => {
+            let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &borrower)?
+                .unwrap_or_default();
+            decay_reputation(deps.storage, &config, &borrower, &mut borrower_info, env.block.time.seconds())?;
+            ORGANIZATIONS.save(deps.storage, &borrower, &borrower_info, env.block.height)?;
+            let borrower_info = eligibility_snapshot(deps.as_ref(), &env, &config, &borrower)?;
+            let eligibility_score = verified_eligibility_score(&ELIGIBILITY_POLICY.load(deps.storage)?, &borrower_info)?;
+            (eligibility_score, Binary::default(), false)
+        },
+    };
+    let proof_hex = hex::encode(proof_data.as_slice());
+
+    // Replaces any earlier proof towards the same lender
+    let record = EligibilityRecord {
+        borrower: borrower.clone(),
+        lender: lender.clone(),
+        amount,
+        score: eligibility_score,
+        policy_version: ELIGIBILITY_POLICY_VERSION.may_load(deps.storage)?.unwrap_or_default(),
+        proof: proof_data,
+        zero_knowledge,
+        created_at: env.block.time.seconds(),
+        expires_at: env.block.time.seconds() + config.proof_validity,
+    };
+    PROOFS.save(deps.storage, (&borrower, &lender), &record)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "verify_eligibility")
+        .add_attribute("borrower", borrower)
+        .add_attribute("lender", lender)
+        .add_attribute("amount", amount)
+        .add_attribute("eligibility_score", eligibility_score.to_string())
+        .add_attribute("proof_hex", proof_hex)
+        .add_attribute("expires_at", record.expires_at.to_string()))
+}
+
+
+
+
+/// Score under the eligibility policy, failing for borrowers below its minimum
+fn verified_eligibility_score(policy: &EligibilityPolicy, borrower_info: &OrganizationInfo) -> Result<Uint128, ContractError> {
+    let eligibility_score = policy.score(borrower_info)?;
+    if eligibility_score < policy.min_score {
+        return Err(ContractError::BorrowerNotEligible {});
+    }
+    Ok(eligibility_score)
+}
+
+/// `paid_in_tokens` is set when `amount` of credit token was sent through `ReceiveMsg::RepayTokens`
+pub fn execute_repay_tokens(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    loan_id: u64,
+    amount: Uint128,
+    paid_in_tokens: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut loan = loans().may_load(deps.storage, loan_id)?.ok_or(ContractError::LoanNotFound {})?;
+    if loan.borrower != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if loan.is_closed() {
+        return Err(ContractError::LoanNotOpen {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+
+    let lender = loan.lender.clone();
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    // Repaying late still defaults the loan
+    update_loan(deps.storage, &config, &mut loan, &mut borrower_info, env.block.time.seconds())?;
+    if amount > loan.remaining() {
+        return Err(ContractError::RepaymentExceedsLoan { remaining: loan.remaining() });
+    }
+    let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &lender)?
+        .unwrap_or_default();
+
+    let mut submessages = vec![];
+    match &config.credit_token {
+        Some(token) => {
+            if !paid_in_tokens {
+                return Err(ContractError::TokenPaymentRequired {});
+            }
+            submessages.push(credit_transfer(deps.storage, token, &lender, amount, env.block.height)?);
+        },
+        None => {
+            if borrower_info.carbon_credits < amount {
+                return Err(ContractError::NotEnoughCredits {});
+            }
+            borrower_info.carbon_credits -= amount;
+            lender_info.carbon_credits += amount;
+            move_serials(deps.storage, &loan.borrower, &lender, amount)?;
+        },
+    }
+
+    loan.repaid += amount;
+    let mut released = vec![];
+    if loan.remaining().is_zero() {
+        loan.status = LoanStatus::Repaid;
+        released = release_collateral(&loan.borrower, loan.collateral.take());
+    }
+    loans().save(deps.storage, loan_id, &loan)?;
+
+    borrower_info.debt -= amount;
+    borrower_info.total_returned += amount;
+    ORGANIZATIONS.save(deps.storage, &info.sender, &borrower_info, env.block.height)?;
+    ORGANIZATIONS.save(deps.storage, &lender, &lender_info, env.block.height)?;
     
-    if lender_info.carbon_credits < updated_request.amount {
-        return Err(ContractError::NotEnoughCredits {});
+    Ok(Response::new()
+        .add_submessages(submessages)
+        .add_messages(released)
+        .add_attribute("method", "repay_tokens")
+        .add_attribute("borrower", info.sender)
+        .add_attribute("lender", lender)
+        .add_attribute("loan_id", loan_id.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("remaining", loan.remaining()))
+}
+
+pub fn execute_mark_defaulted(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    loan_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut loan = loans().may_load(deps.storage, loan_id)?.ok_or(ContractError::LoanNotFound {})?;
+    if loan.status != LoanStatus::Open {
+        return Err(ContractError::LoanNotOpen {});
+    }
+    if !loan.is_overdue(env.block.time.seconds()) {
+        return Err(ContractError::LoanNotOverdue {});
+    }
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &loan.borrower)?
+        .unwrap_or_default();
+    update_loan(deps.storage, &config, &mut loan, &mut borrower_info, env.block.time.seconds())?;
+    loans().save(deps.storage, loan_id, &loan)?;
+    ORGANIZATIONS.save(deps.storage, &loan.borrower, &borrower_info, env.block.height)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "mark_defaulted")
+        .add_attribute("loan_id", loan_id.to_string())
+        .add_attribute("remaining", loan.remaining())
+        .add_attribute("borrower", loan.borrower))
+}
+
+pub fn execute_claim_collateral(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    loan_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut loan = loans().may_load(deps.storage, loan_id)?.ok_or(ContractError::LoanNotFound {})?;
+    if loan.lender != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &loan.borrower)?
+        .unwrap_or_default();
+    update_loan(deps.storage, &config, &mut loan, &mut borrower_info, env.block.time.seconds())?;
+    if loan.status != LoanStatus::Defaulted {
+        return Err(ContractError::LoanNotDefaulted {});
+    }
+    let collateral = loan.collateral.take().ok_or(ContractError::NoCollateral {})?;
+
+    // The collateral settles whatever is still owed
+    let written_off = loan.remaining();
+    borrower_info.debt -= written_off;
+    loan.status = LoanStatus::Liquidated;
+    loans().save(deps.storage, loan_id, &loan)?;
+    ORGANIZATIONS.save(deps.storage, &loan.borrower, &borrower_info, env.block.height)?;
+
+    Ok(Response::new()
+        .add_messages(release_collateral(&info.sender, Some(collateral.clone())))
+        .add_attribute("method", "claim_collateral")
+        .add_attribute("loan_id", loan_id.to_string())
+        .add_attribute("lender", info.sender)
+        .add_attribute("borrower", loan.borrower)
+        .add_attribute("collateral", collateral.to_string())
+        .add_attribute("written_off", written_off))
+}
+
+/// Coins sent with a borrowing message, checked against the configured collateral ratio
+fn posted_collateral(config: &Config, info: &MessageInfo, amount: Uint128) -> Result<Option<Coin>, ContractError> {
+    match &config.collateral {
+        Some(params) if !info.funds.is_empty() => {
+            let posted = must_pay(info, &params.denom)?;
+            let required = amount * params.ratio;
+            if posted < required {
+                return Err(ContractError::InsufficientCollateral { required });
+            }
+            Ok(Some(Coin { denom: params.denom.clone(), amount: posted }))
+        },
+        Some(_) => Ok(None),
+        None => {
+            nonpayable(info)?;
+            Ok(None)
+        },
+    }
+}
+
+/// Pays escrowed collateral out to `recipient`
+fn release_collateral(recipient: &Addr, collateral: Option<Coin>) -> Vec<BankMsg> {
+    collateral
+        .into_iter()
+        .map(|coin| BankMsg::Send { to_address: recipient.to_string(), amount: vec![coin] })
+        .collect()
+}
+
+/// Accrues interest into the borrower's debt and defaults the loan once it is overdue.
+/// The caller saves the loan and the borrower.
+fn update_loan(storage: &mut dyn Storage, config: &Config, loan: &mut Loan, borrower_info: &mut OrganizationInfo, now: u64) -> StdResult<()> {
+    borrower_info.debt += loan.accrue(now);
+    if loan.is_overdue(now) {
+        loan.status = LoanStatus::Defaulted;
+        if let Some(lend_request_id) = loan.lend_request_id {
+            LEND_REQUESTS.update(storage, lend_request_id, |request| -> StdResult<_> {
+                let mut request = request.ok_or_else(|| StdError::not_found("LendRequest"))?;
+                request.status = LentStatus::Defaulted;
+                Ok(request)
+            })?;
+        }
+        adjust_reputation(storage, config, &loan.borrower, borrower_info, ReputationReason::LoanDefault, Some(loan.id), config.default_penalty, now)?;
+        borrower_info.defaults += 1;
+    }
+    Ok(())
+}
+
+/// Applies the reputation decay due up to `now`, recording it. The caller saves the organization.
+fn decay_reputation(storage: &mut dyn Storage, config: &Config, address: &Addr, org_info: &mut OrganizationInfo, now: u64) -> StdResult<()> {
+    let previous = org_info.reputation_score;
+    if !org_info.decay_reputation(&config.reputation, now).is_zero() {
+        record_reputation_event(storage, address, ReputationReason::Decay, None, previous, org_info.reputation_score, now)?;
+    }
+    Ok(())
+}
+
+/// Decays reputation, then rewards or slashes `amount` of it depending on `reason`.
+/// The caller saves the organization.
+#[allow(clippy::too_many_arguments)]
+fn adjust_reputation(
+    storage: &mut dyn Storage,
+    config: &Config,
+    address: &Addr,
+    org_info: &mut OrganizationInfo,
+    reason: ReputationReason,
+    reference: Option<u64>,
+    amount: Uint128,
+    now: u64,
+) -> StdResult<()> {
+    decay_reputation(storage, config, address, org_info, now)?;
+    let previous = org_info.reputation_score;
+    org_info.reputation_score = match reason {
+        ReputationReason::CorrectVote => previous.checked_add(amount)?,
+        _ => previous.saturating_sub(amount),
+    };
+    if org_info.reputation_score == previous {
+        return Ok(());
+    }
+    record_reputation_event(storage, address, reason, reference, previous, org_info.reputation_score, now)
+}
+
+fn record_reputation_event(
+    storage: &mut dyn Storage,
+    address: &Addr,
+    reason: ReputationReason,
+    reference: Option<u64>,
+    previous: Uint128,
+    current: Uint128,
+    now: u64,
+) -> StdResult<()> {
+    let id = REPUTATION_EVENT_COUNTER.may_load(storage)?.unwrap_or_default();
+    REPUTATION_EVENT_COUNTER.save(storage, &(id + 1))?;
+    REPUTATION_EVENTS.save(storage, (address, id), &ReputationEvent {
+        id,
+        organization: address.clone(),
+        reason,
+        reference,
+        previous,
+        current,
+        timestamp: now,
+    })
+}
+
+/// Borrowers who have defaulted must rebuild their reputation before borrowing again
+fn ensure_can_borrow(config: &Config, borrower_info: &OrganizationInfo) -> Result<(), ContractError> {
+    if borrower_info.defaults > 0 && borrower_info.reputation_score < config.reborrow_reputation {
+        return Err(ContractError::NotEnoughReputation {});
+    }
+    Ok(())
+}
+
+/// Loads the lending pool with interest accrued up to `now`
+fn load_pool(storage: &dyn Storage, config: &Config, now: u64) -> StdResult<LendingPool> {
+    let mut pool = POOL.load(storage)?;
+    pool.accrue(&config.pool, now);
+    Ok(pool)
+}
+
+/// Brings the borrower's aggregate debt up to date with what it owes the pool
+fn sync_pool_debt(pool: &LendingPool, pool_debt: &mut PoolDebt, borrower_info: &mut OrganizationInfo) {
+    let owed = pool.debt_value(pool_debt.debt_shares);
+    if owed > pool_debt.accounted {
+        borrower_info.debt += owed - pool_debt.accounted;
+        pool_debt.accounted = owed;
+    }
+}
+
+/// `paid_in_tokens` is set when `amount` of credit token was sent through `ReceiveMsg::PoolDeposit`
+pub fn execute_pool_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    paid_in_tokens: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let mut pool = load_pool(deps.storage, &config, env.block.time.seconds())?;
+    match &config.credit_token {
+        Some(_) => {
+            if !paid_in_tokens {
+                return Err(ContractError::TokenPaymentRequired {});
+            }
+        },
+        None => {
+            let mut depositor_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+            if depositor_info.carbon_credits < amount {
+                return Err(ContractError::NotEnoughCredits {});
+            }
+            depositor_info.carbon_credits -= amount;
+            ORGANIZATIONS.save(deps.storage, &info.sender, &depositor_info, env.block.height)?;
+            move_serials(deps.storage, &info.sender, &env.contract.address, amount)?;
+        },
+    }
+
+    let shares = if pool.total_shares.is_zero() {
+        amount
+    } else {
+        amount.multiply_ratio(pool.total_shares, pool.total_assets())
+    };
+    if shares.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    pool.available += amount;
+    pool.total_shares += shares;
+    POOL.save(deps.storage, &pool)?;
+    let held = POOL_SHARES.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    POOL_SHARES.save(deps.storage, &info.sender, &(held + shares))?;
+
+    Ok(Response::new()
+        .add_attribute("method", "pool_deposit")
+        .add_attribute("depositor", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("shares", shares))
+}
+
+pub fn execute_pool_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    shares: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if shares.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let held = POOL_SHARES.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    if held < shares {
+        return Err(ContractError::InsufficientShares {});
+    }
+    let mut pool = load_pool(deps.storage, &config, env.block.time.seconds())?;
+    let amount = pool.share_value(shares);
+    if amount > pool.available {
+        return Err(ContractError::InsufficientLiquidity { available: pool.available });
+    }
+    pool.available -= amount;
+    pool.total_shares -= shares;
+    POOL.save(deps.storage, &pool)?;
+    if held == shares {
+        POOL_SHARES.remove(deps.storage, &info.sender);
+    } else {
+        POOL_SHARES.save(deps.storage, &info.sender, &(held - shares))?;
+    }
+
+    let mut submessages = vec![];
+    match &config.credit_token {
+        Some(token) => submessages.push(credit_transfer(deps.storage, token, &info.sender, amount, env.block.height)?),
+        None => {
+            let mut depositor_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+            depositor_info.carbon_credits += amount;
+            ORGANIZATIONS.save(deps.storage, &info.sender, &depositor_info, env.block.height)?;
+            move_serials(deps.storage, &env.contract.address, &info.sender, amount)?;
+        },
+    }
+
+    Ok(Response::new()
+        .add_submessages(submessages)
+        .add_attribute("method", "pool_withdraw")
+        .add_attribute("depositor", info.sender)
+        .add_attribute("shares", shares)
+        .add_attribute("amount", amount))
+}
+
+pub fn execute_pool_borrow(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    proof: Option<EligibilityProof>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    decay_reputation(deps.storage, &config, &info.sender, &mut borrower_info, env.block.time.seconds())?;
+    ensure_can_borrow(&config, &borrower_info)?;
+    // The pool lends as this contract, which is the lender a record for it names
+    let eligibility_score = match checked_eligibility_proof(deps.as_ref(), &config, &env, &info.sender, amount, proof)? {
+        Some(proof) => proof.threshold,
+        None => {
+            let snapshot = eligibility_snapshot(deps.as_ref(), &env, &config, &info.sender)?;
+            verified_eligibility_score(&ELIGIBILITY_POLICY.load(deps.storage)?, &snapshot)?
+        },
+    };
+    ensure_eligibility_record(deps.storage, &config, &env, &info.sender, &env.contract.address, amount)?;
+    if eligibility_score < config.pool.min_eligibility_score {
+        return Err(ContractError::BorrowerNotEligible {});
+    }
+    let mut pool = load_pool(deps.storage, &config, env.block.time.seconds())?;
+    if amount > pool.available {
+        return Err(ContractError::InsufficientLiquidity { available: pool.available });
+    }
+
+    let mut pool_debt = POOL_DEBTS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    let debt_shares = pool.debt_shares(amount, true);
+    pool_debt.debt_shares += debt_shares;
+    pool.total_debt_shares += debt_shares;
+    pool.available -= amount;
+    // Picks up the new principal along with any interest accrued since the last sync
+    sync_pool_debt(&pool, &mut pool_debt, &mut borrower_info);
+    POOL.save(deps.storage, &pool)?;
+    POOL_DEBTS.save(deps.storage, &info.sender, &pool_debt)?;
+
+    let mut submessages = vec![];
+    match &config.credit_token {
+        Some(token) => submessages.push(credit_transfer(deps.storage, token, &info.sender, amount, env.block.height)?),
+        None => {
+            borrower_info.carbon_credits += amount;
+            move_serials(deps.storage, &env.contract.address, &info.sender, amount)?;
+        },
     }
-    lender_info.carbon_credits -= updated_request.amount;
-    borrower_info.carbon_credits += updated_request.amount;
-    borrower_info.debt += updated_request.amount;
     borrower_info.times_borrowed += 1;
-    borrower_info.total_borrowed += updated_request.amount;
-    updated_request.status = LentStatus::Approved;
+    borrower_info.total_borrowed += amount;
+    ORGANIZATIONS.save(deps.storage, &info.sender, &borrower_info, env.block.height)?;
 
-    ORGANIZATIONS.save(deps.storage, &info.sender, &lender_info)?;
-    ORGANIZATIONS.save(deps.storage, &updated_request.borrower, &borrower_info)?;
-    LEND_REQUESTS.save(deps.storage, lend_request_id, &updated_request)?;
-    
     Ok(Response::new()
-        .add_attribute("method", "lend_tokens")
-        .add_attribute("lender", info.sender)
-        .add_attribute("borrower", updated_request.borrower)
-        .add_attribute("request_id", lend_request_id.to_string())
-        .add_attribute("amount", updated_request.amount)
-        .add_attribute("response", "accepted"))
+        .add_submessages(submessages)
+        .add_attribute("method", "pool_borrow")
+        .add_attribute("borrower", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("borrow_rate", pool.borrow_rate(&config.pool).to_string()))
 }
-pub fn execute_verify_eligibility(
+
+/// `paid_in_tokens` is set when `amount` of credit token was sent through `ReceiveMsg::PoolRepay`
+pub fn execute_pool_repay(
     deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
-    borrower: Addr,
-    lender: Addr,
+    env: Env,
+    info: MessageInfo,
     amount: Uint128,
+    paid_in_tokens: bool,
 ) -> Result<Response, ContractError> {
-    let borrower_info = ORGANIZATIONS.may_load(deps.storage, &borrower)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    let lender_info = ORGANIZATIONS.may_load(deps.storage, &lender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    let borrower_emissions = u32::try_from(borrower_info.emissions.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for emissions")))?;
-    let borrower_returned = u32::try_from(borrower_info.total_returned.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for total_returned")))?;
-    let borrower_total_borrowed = u32::try_from(borrower_info.total_borrowed.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for total_borrowed")))?;
-    let borrower_debt = u32::try_from(borrower_info.debt.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for debt")))?;
-    let borrower_credits = u32::try_from(borrower_info.carbon_credits.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for carbon_credits")))?;
-    let borrower_reputation = u32::try_from(borrower_info.reputation_score.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for reputation_score")))?;
-    let lender_credits = u32::try_from(lender_info.carbon_credits.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for lender carbon_credits")))?;
-    let lender_debt = u32::try_from(lender_info.debt.u128())
-        .map_err(|_| ContractError::Std(StdError::generic_err("Conversion error for lender debt")))?;
-    
-    // Comment out the eligibility_proof function call
-    // let (eligibility_score, proof_data) = eligibility_proof(
-    //     borrower_emissions,
-    //     borrower_returned,
-    //     borrower_total_borrowed,
-    //     borrower_debt,
-    //     borrower_credits,
-    //     borrower_reputation,
-    //     lender_credits,
-    //     lender_debt,
-    // );
-    
-    // Hardcode the eligibility score based on the parameters
-    // Formula: reputation + returns + credits - debt - (emissions/10) + base score
-    let eligibility_score = borrower_reputation as i32 + borrower_returned as i32 - 
-                           (borrower_debt as i32 / 2) - (borrower_emissions as i32 / 10) + 
-                           (borrower_credits as i32) + 50; // Base score of 50
-    
-    // Cap the score between 0 and 100
-    let eligibility_score = if eligibility_score > 100 { 100 } else if eligibility_score < 0 { 0 } else { eligibility_score as u32 };
-    
-    if eligibility_score <= 0 {
-        return Err(ContractError::BorrowerNotEligible {});
+    let config = CONFIG.load(deps.storage)?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
     }
-    
-    // Create a mock proof data that incorporates the parameters
-    let fake_proof = format!("{}{}{}{}{}{}{}{}{}",
-        borrower_emissions, borrower_returned, borrower_total_borrowed,
-        borrower_debt, borrower_credits, borrower_reputation,
-        lender_credits, lender_debt, eligibility_score
-    );
-    let proof_data = fake_proof.as_bytes().to_vec();
-    let proof_hex = hex::encode(&proof_data);
-    
-    PROOFS.save(deps.storage, (&borrower, &lender), &proof_data)?;
+    let mut pool = load_pool(deps.storage, &config, env.block.time.seconds())?;
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    let mut pool_debt = POOL_DEBTS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    sync_pool_debt(&pool, &mut pool_debt, &mut borrower_info);
+    let owed = pool.debt_value(pool_debt.debt_shares);
+    if amount > owed {
+        return Err(ContractError::RepaymentExceedsLoan { remaining: owed });
+    }
+    match &config.credit_token {
+        Some(_) => {
+            if !paid_in_tokens {
+                return Err(ContractError::TokenPaymentRequired {});
+            }
+        },
+        None => {
+            if borrower_info.carbon_credits < amount {
+                return Err(ContractError::NotEnoughCredits {});
+            }
+            borrower_info.carbon_credits -= amount;
+            move_serials(deps.storage, &info.sender, &env.contract.address, amount)?;
+        },
+    }
+
+    let burned = if amount == owed {
+        pool_debt.debt_shares
+    } else {
+        pool.debt_shares(amount, false)
+    };
+    pool_debt.debt_shares -= burned;
+    pool_debt.accounted -= amount;
+    pool.total_debt_shares -= burned;
+    pool.available += amount;
+    POOL.save(deps.storage, &pool)?;
+    if pool_debt.debt_shares.is_zero() {
+        POOL_DEBTS.remove(deps.storage, &info.sender);
+    } else {
+        POOL_DEBTS.save(deps.storage, &info.sender, &pool_debt)?;
+    }
+    borrower_info.debt -= amount;
+    borrower_info.total_returned += amount;
+    ORGANIZATIONS.save(deps.storage, &info.sender, &borrower_info, env.block.height)?;
 
     Ok(Response::new()
-        .add_attribute("method", "verify_eligibility")
-        .add_attribute("borrower", borrower)
-        .add_attribute("lender", lender)
+        .add_attribute("method", "pool_repay")
+        .add_attribute("borrower", info.sender)
         .add_attribute("amount", amount)
-        .add_attribute("eligibility_score", eligibility_score.to_string())
-        .add_attribute("proof_hex", proof_hex))
+        .add_attribute("remaining", pool.debt_value(pool_debt.debt_shares)))
+}
+
+/// `paid_in_tokens` is set when `max_amount` of credit token was sent through `ReceiveMsg::CreateLendOffer`
+#[allow(clippy::too_many_arguments)]
+pub fn execute_create_lend_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max_amount: Uint128,
+    min_eligibility_score: Uint128,
+    rate: Decimal,
+    term: u64,
+    paid_in_tokens: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if max_amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    if term == 0 {
+        return Err(ContractError::InvalidLoanTerm {});
+    }
+    match &config.credit_token {
+        Some(_) => {
+            if !paid_in_tokens {
+                return Err(ContractError::TokenPaymentRequired {});
+            }
+        },
+        None => {
+            let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+            if lender_info.carbon_credits < max_amount {
+                return Err(ContractError::NotEnoughCredits {});
+            }
+            lender_info.carbon_credits -= max_amount;
+            ORGANIZATIONS.save(deps.storage, &info.sender, &lender_info, env.block.height)?;
+            move_serials(deps.storage, &info.sender, &env.contract.address, max_amount)?;
+        },
+    }
+
+    let id = LEND_OFFER_COUNTER.load(deps.storage)?;
+    let offer = LendOffer {
+        id,
+        lender: info.sender.clone(),
+        max_amount,
+        min_eligibility_score,
+        rate,
+        term,
+        created_at: env.block.time.seconds(),
+    };
+    LEND_OFFERS.save(deps.storage, id, &offer)?;
+    LEND_OFFER_COUNTER.save(deps.storage, &(id + 1))?;
+
+    Ok(Response::new()
+        .add_attribute("method", "create_lend_offer")
+        .add_attribute("offer_id", id.to_string())
+        .add_attribute("lender", info.sender)
+        .add_attribute("max_amount", max_amount)
+        .add_attribute("min_eligibility_score", min_eligibility_score))
 }
 
+pub fn execute_cancel_lend_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    offer_id: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let offer = LEND_OFFERS.may_load(deps.storage, offer_id)?.ok_or(ContractError::OfferNotFound {})?;
+    if offer.lender != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    LEND_OFFERS.remove(deps.storage, offer_id);
 
+    let mut submessages = vec![];
+    match &config.credit_token {
+        Some(token) => submessages.push(credit_transfer(deps.storage, token, &offer.lender, offer.max_amount, env.block.height)?),
+        None => {
+            let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &offer.lender)?.unwrap_or_default();
+            lender_info.carbon_credits += offer.max_amount;
+            ORGANIZATIONS.save(deps.storage, &offer.lender, &lender_info, env.block.height)?;
+            move_serials(deps.storage, &env.contract.address, &offer.lender, offer.max_amount)?;
+        },
+    }
 
+    Ok(Response::new()
+        .add_submessages(submessages)
+        .add_attribute("method", "cancel_lend_offer")
+        .add_attribute("offer_id", offer_id.to_string())
+        .add_attribute("returned", offer.max_amount))
+}
 
-pub fn execute_repay_tokens(
+pub fn execute_accept_lend_offer(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    lender: Addr,
+    offer_id: u64,
     amount: Uint128,
+    proof: Option<EligibilityProof>,
 ) -> Result<Response, ContractError> {
-    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    
-    let mut lender_info = ORGANIZATIONS.may_load(deps.storage, &lender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    
+    let config = CONFIG.load(deps.storage)?;
+    let mut offer = LEND_OFFERS.may_load(deps.storage, offer_id)?.ok_or(ContractError::OfferNotFound {})?;
+    if offer.lender == info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    if amount > offer.max_amount {
+        return Err(ContractError::ExceedsOffer { available: offer.max_amount });
+    }
 
-    if borrower_info.carbon_credits < amount {
-        return Err(ContractError::NotEnoughCredits {});
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    decay_reputation(deps.storage, &config, &info.sender, &mut borrower_info, env.block.time.seconds())?;
+    ensure_can_borrow(&config, &borrower_info)?;
+    // Checked as a request to the offer's lender would be
+    let eligibility_score = match checked_eligibility_proof(deps.as_ref(), &config, &env, &info.sender, amount, proof)? {
+        Some(proof) => proof.threshold,
+        None => {
+            let snapshot = eligibility_snapshot(deps.as_ref(), &env, &config, &info.sender)?;
+            verified_eligibility_score(&ELIGIBILITY_POLICY.load(deps.storage)?, &snapshot)?
+        },
+    };
+    ensure_eligibility_record(deps.storage, &config, &env, &info.sender, &offer.lender, amount)?;
+    if eligibility_score < offer.min_eligibility_score {
+        return Err(ContractError::BorrowerNotEligible {});
     }
-    if borrower_info.debt < amount {
-        return Err(ContractError::NotEnoughCredits {});
+    let collateral = posted_collateral(&config, &info, amount)?;
+
+    // The offered credits are already in escrow
+    let mut submessages = vec![];
+    match &config.credit_token {
+        Some(token) => submessages.push(credit_transfer(deps.storage, token, &info.sender, amount, env.block.height)?),
+        None => {
+            borrower_info.carbon_credits += amount;
+            move_serials(deps.storage, &env.contract.address, &info.sender, amount)?;
+        },
+    }
+    borrower_info.debt += amount;
+    borrower_info.times_borrowed += 1;
+    borrower_info.total_borrowed += amount;
+    ORGANIZATIONS.save(deps.storage, &info.sender, &borrower_info, env.block.height)?;
+
+    let loan_id = LOAN_COUNTER.load(deps.storage)?;
+    let loan = Loan {
+        id: loan_id,
+        lend_request_id: None,
+        offer_id: Some(offer_id),
+        borrower: info.sender.clone(),
+        lender: offer.lender.clone(),
+        principal: amount,
+        interest_rate: offer.rate,
+        interest: Uint128::zero(),
+        repaid: Uint128::zero(),
+        status: LoanStatus::Open,
+        created_at: env.block.time.seconds(),
+        due_at: env.block.time.seconds() + offer.term,
+        last_accrued: env.block.time.seconds(),
+        collateral,
+    };
+    loans().save(deps.storage, loan_id, &loan)?;
+    LOAN_COUNTER.save(deps.storage, &(loan_id + 1))?;
+
+    offer.max_amount -= amount;
+    if offer.max_amount.is_zero() {
+        LEND_OFFERS.remove(deps.storage, offer_id);
+    } else {
+        LEND_OFFERS.save(deps.storage, offer_id, &offer)?;
     }
 
-    borrower_info.carbon_credits -= amount;
-    borrower_info.debt -= amount;
-    borrower_info.total_returned += amount;
-    lender_info.carbon_credits += amount;
-    ORGANIZATIONS.save(deps.storage, &info.sender, &borrower_info)?;
-    ORGANIZATIONS.save(deps.storage, &lender, &lender_info)?;
-    
     Ok(Response::new()
-        .add_attribute("method", "repay_tokens")
+        .add_submessages(submessages)
+        .add_attribute("method", "accept_lend_offer")
+        .add_attribute("offer_id", offer_id.to_string())
+        .add_attribute("lender", offer.lender)
         .add_attribute("borrower", info.sender)
-        .add_attribute("lender", lender)
-        .add_attribute("amount", amount))
+        .add_attribute("loan_id", loan_id.to_string())
+        .add_attribute("due_at", loan.due_at.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("eligibility_score", eligibility_score.to_string()))
 }
 
-
-pub const PROOFS: Map<(&Addr, &Addr), Vec<u8>> = Map::new("proofs");
+/// Pays out credit tokens held by this contract
+fn credit_transfer(storage: &mut dyn Storage, token: &Addr, recipient: &Addr, amount: Uint128, height: u64) -> StdResult<SubMsg> {
+    CREDIT_TOKEN_BALANCES.update(storage, recipient, height, |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(SubMsg::new(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    }))
+}
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetConfig {} => to_binary(&query_config(deps)?),
-        QueryMsg::GetClaim { id } => to_binary(&query_claim(deps,_env,id)?),
-        QueryMsg::GetOrganization { address } => to_binary(&query_organization(deps, address)?),
-        QueryMsg::GetTotalCarbonCredits {} => to_binary(&query_total_carbon_credits(deps)?),
-        QueryMsg::GetClaims { start_after, limit } => to_binary(&query_claims(deps,_env,start_after, limit)?),
-        QueryMsg::GetClaimsByStatus { status, start_after, limit } => to_binary(&query_claims_by_status(deps, _env,status, start_after, limit)?),
-        QueryMsg::GetAllOrganizations { start_after, limit } => to_binary(&query_all_organizations(deps, start_after, limit)?),
+        QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::GetClaim { id } => to_json_binary(&query_claim(deps,env,id)?),
+        QueryMsg::GetOrganization { address, at_height } => to_json_binary(&query_organization(deps, env, address, at_height)?),
+        QueryMsg::GetTotalCarbonCredits {} => to_json_binary(&query_total_carbon_credits(deps)?),
+        QueryMsg::GetClaims { start_after, limit } => to_json_binary(&query_claims(deps,env,start_after, limit)?),
+        QueryMsg::GetClaimsByStatus { status, start_after, limit } => to_json_binary(&query_claims_by_status(deps, env,status, start_after, limit)?),
+        QueryMsg::GetAllOrganizations { start_after, limit } => to_json_binary(&query_all_organizations(deps, env, start_after, limit)?),
+        QueryMsg::GetChallenge { id } => to_json_binary(&CHALLENGES.load(deps.storage, id)?),
+        QueryMsg::GetChallenges { start_after, limit } => to_json_binary(&query_challenges(deps, start_after, limit)?),
+        QueryMsg::GetVerifier { address } => to_json_binary(&query_verifier(deps, env, address)?),
+        QueryMsg::GetVerifiers { start_after, limit } => to_json_binary(&query_verifiers(deps, env, start_after, limit)?),
+        QueryMsg::GetBatch { id } => to_json_binary(&credit_batches().load(deps.storage, id)?),
+        QueryMsg::GetBatchesByOwner { owner, start_after, limit } => {
+            to_json_binary(&query_batches(deps, credit_batches().idx.owner.prefix(owner), start_after, limit)?)
+        },
+        QueryMsg::GetBatchesByClaim { claim_id, start_after, limit } => {
+            to_json_binary(&query_batches(deps, credit_batches().idx.claim.prefix(claim_id), start_after, limit)?)
+        },
+        QueryMsg::OwnerOf { token_id, include_expired } => to_json_binary(&query_owner_of(deps, env, token_id, include_expired)?),
+        QueryMsg::Approval { token_id, spender, include_expired } => {
+            to_json_binary(&query_approval(deps, env, token_id, spender, include_expired)?)
+        },
+        QueryMsg::Approvals { token_id, include_expired } => {
+            let batch = load_batch(deps.storage, &token_id).map_err(|e| StdError::generic_err(e.to_string()))?;
+            to_json_binary(&ApprovalsResponse { approvals: nft_approvals(deps, &env, batch.id, include_expired)? })
+        },
+        QueryMsg::AllOperators { owner, include_expired, start_after, limit } => {
+            to_json_binary(&query_all_operators(deps, env, owner, include_expired, start_after, limit)?)
+        },
+        QueryMsg::NumTokens {} => to_json_binary(&NumTokensResponse { count: BATCH_COUNT.load(deps.storage)? }),
+        QueryMsg::ContractInfo {} => to_json_binary(&ContractInfoResponse {
+            name: NFT_NAME.to_string(),
+            symbol: NFT_SYMBOL.to_string(),
+        }),
+        QueryMsg::NftInfo { token_id } => to_json_binary(&query_nft_info(deps, token_id)?),
+        QueryMsg::AllNftInfo { token_id, include_expired } => to_json_binary(&AllNftInfoResponse {
+            access: query_owner_of(deps, env, token_id.clone(), include_expired)?,
+            info: query_nft_info(deps, token_id)?,
+        }),
+        QueryMsg::Tokens { owner, start_after, limit } => to_json_binary(&query_tokens(deps, owner, start_after, limit)?),
+        QueryMsg::AllTokens { start_after, limit } => to_json_binary(&query_all_tokens(deps, start_after, limit)?),
+        QueryMsg::GetEmissionsReport { organization, period, version } => {
+            to_json_binary(&query_emissions_report(deps, organization, period, version)?)
+        },
+        QueryMsg::GetEmissionsReportHistory { organization, period } => {
+            let reports = EMISSION_REPORTS
+                .prefix((&organization, period))
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, report)| report))
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&EmissionsReportsResponse { reports })
+        },
+        QueryMsg::GetNetPosition { organization, period } => to_json_binary(&query_net_position(deps, organization, period)?),
+        QueryMsg::GetNetPositions { organization, start_after, limit } => {
+            to_json_binary(&query_net_positions(deps, organization, start_after, limit)?)
+        },
+        QueryMsg::GetAllowance { owner, spender } => {
+            to_json_binary(&ALLOWANCES.may_load(deps.storage, (&owner, &spender))?.unwrap_or_default())
+        },
+        QueryMsg::GetAllAllowances { owner, start_after, limit } => to_json_binary(&query_all_allowances(deps, owner, start_after, limit)?),
+        QueryMsg::GetListing { id } => to_json_binary(&listings().load(deps.storage, id)?),
+        QueryMsg::GetListingsBySeller { seller, start_after, limit } => {
+            to_json_binary(&query_listings_by_seller(deps, seller, start_after, limit)?)
+        },
+        QueryMsg::GetOrderBook { denom, start_after, limit } => to_json_binary(&query_order_book(deps, denom, start_after, limit)?),
+        QueryMsg::GetLoan { id } => {
+            let mut loan = loans().load(deps.storage, id)?;
+            loan.accrue(env.block.time.seconds());
+            to_json_binary(&loan)
+        },
+        QueryMsg::GetPool {} => to_json_binary(&query_pool(deps, env)?),
+        QueryMsg::GetPoolPosition { address } => to_json_binary(&query_pool_position(deps, env, address)?),
+        QueryMsg::GetLendOffer { id } => to_json_binary(&LEND_OFFERS.load(deps.storage, id)?),
+        QueryMsg::GetLendOffers { eligibility_score, start_after, limit } => {
+            to_json_binary(&query_lend_offers(deps, eligibility_score, start_after, limit)?)
+        },
+        QueryMsg::GetLoanHealth { id } => to_json_binary(&query_loan_health(deps, env, id)?),
+        QueryMsg::GetEligibilityPolicy {} => to_json_binary(&ELIGIBILITY_POLICY.load(deps.storage)?),
+        QueryMsg::SimulateEligibility { borrower, lender, amount } => {
+            to_json_binary(&query_simulate_eligibility(deps, env, borrower, lender, amount)?)
+        },
+        QueryMsg::GetEligibilityProof { borrower, lender } => to_json_binary(&PROOFS.load(deps.storage, (&borrower, &lender))?),
+        QueryMsg::GetEligibilityProofs { borrower, start_after, limit } => {
+            to_json_binary(&query_eligibility_proofs(deps, borrower, start_after, limit)?)
+        },
+        QueryMsg::GetReputationEvents { address, start_after, limit } => {
+            to_json_binary(&query_reputation_events(deps, address, start_after, limit)?)
+        },
+        // Defaulted loans are still owed and count as open
+        QueryMsg::GetOpenLoansByBorrower { borrower, start_after, limit } => {
+            to_json_binary(&query_open_loans(deps, env, loans().idx.borrower.prefix((borrower, 1)), start_after, limit)?)
+        },
+        QueryMsg::GetOpenLoansByLender { lender, start_after, limit } => {
+            to_json_binary(&query_open_loans(deps, env, loans().idx.lender.prefix((lender, 1)), start_after, limit)?)
+        },
+        QueryMsg::GetRetirement { id } => to_json_binary(&RETIREMENTS.load(deps.storage, id)?),
+        QueryMsg::GetRetirements { start_after, limit } => {
+            to_json_binary(&query_retirements(deps, start_after, limit, |_| true)?)
+        },
+        QueryMsg::GetRetirementsByBeneficiary { beneficiary, start_after, limit } => {
+            to_json_binary(&query_retirements(deps, start_after, limit, |certificate| certificate.beneficiary == beneficiary)?)
+        },
         QueryMsg::UserLendRequests { user, start_after, limit } => {
             let user_addr = deps.api.addr_validate(&user)?;
-            let response = query_user_lend_requests(deps, user_addr, start_after, limit)?;
-            to_binary(&response)
+            let response = query_user_lend_requests(deps, env, user_addr, start_after, limit)?;
+            to_json_binary(&response)
         }
     }
 
@@ -568,55 +2854,77 @@ fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     Ok(ConfigResponse {
         owner: config.owner,
         voting_period: config.voting_period,
+        reveal_period: config.reveal_period,
+        unrevealed_penalty: config.unrevealed_penalty,
+        challenge_period: config.challenge_period,
+        challenge_slash: config.challenge_slash,
         total_carbon_credits: config.total_carbon_credits,
+        vote_weights: config.vote_weights,
+        tally_params: config.tally_params,
+        governance: config.governance,
+        credit_token: config.credit_token,
+        default_penalty: config.default_penalty,
+        reborrow_reputation: config.reborrow_reputation,
+        collateral: config.collateral,
+        pool: config.pool,
+        lend_request_expiry: config.lend_request_expiry,
+        reputation: config.reputation,
+        proof_validity: config.proof_validity,
+        require_eligibility_proof: config.require_eligibility_proof,
     })
 }
 
 fn query_claim(deps: Deps, env: Env, id: u64) -> StdResult<ClaimResponse> {
     let claim = CLAIMS.load(deps.storage, id)?;
-    
-    // Convert u64 to Timestamp for comparison
-    let voting_end_timestamp = Timestamp::from_seconds(claim.voting_end_time);
-    
-    // Check if voting has ended using env.block.time
-    let (yes_votes, no_votes) = if env.block.time >= voting_end_timestamp {
+    Ok(claim_response(claim, &env))
+}
+
+fn claim_response(claim: Claim, env: &Env) -> ClaimResponse {
+    // Tallies stay hidden until every vote had the chance to be revealed
+    let reveal_end_timestamp = Timestamp::from_seconds(claim.reveal_end_time);
+    let (yes_votes, no_votes) = if env.block.time >= reveal_end_timestamp {
         (claim.yes_votes, claim.no_votes)
     } else {
         (Uint128::zero(), Uint128::zero())
     };
     
-    Ok(ClaimResponse {
+    ClaimResponse {
         id: claim.id,
         organization: claim.organization,
         longitudes: claim.longitudes,
         latitudes: claim.latitudes,
+        area: claim.area,
         time_started: claim.time_started,
         time_ended: claim.time_ended,
         demanded_tokens: claim.demanded_tokens,
         ipfs_hashes: claim.ipfs_hashes,
+        methodology: claim.methodology,
         status: claim.status,
         voting_end_time: claim.voting_end_time,
+        reveal_end_time: claim.reveal_end_time,
         yes_votes,
         no_votes,
-    })
+        challenge_end_time: claim.challenge_end_time,
+        challenge_id: claim.challenge_id,
+    }
 }
 
-fn query_organization(deps: Deps, address: Addr) -> StdResult<OrganizationResponse> {
-    let org_info = ORGANIZATIONS.may_load(deps.storage, &address)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
+fn query_organization(deps: Deps, env: Env, address: Addr, at_height: Option<u64>) -> StdResult<OrganizationResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let org_info = match at_height {
+        Some(height) => ORGANIZATIONS.may_load_at_height(deps.storage, &address, height)?,
+        None => ORGANIZATIONS.may_load(deps.storage, &address)?,
+    }
+    .unwrap_or_default();
+    // Past states are reported as stored, without decay since
+    let reputation_score = match at_height {
+        Some(_) => org_info.reputation_score,
+        None => org_info.reputation_at(&config.reputation, env.block.time.seconds()),
+    };
     
     Ok(OrganizationResponse {
         address,
-        reputation_score: org_info.reputation_score,
+        reputation_score,
         carbon_credits: org_info.carbon_credits,
         debt: org_info.debt,
         times_borrowed: org_info.times_borrowed,
@@ -624,6 +2932,7 @@ fn query_organization(deps: Deps, address: Addr) -> StdResult<OrganizationRespon
         total_returned: org_info.total_returned,
         name: org_info.name,
         emissions: org_info.emissions,
+        defaults: org_info.defaults,
     })
 }
 
@@ -636,34 +2945,14 @@ fn query_total_carbon_credits(deps: Deps) -> StdResult<TotalCarbonCreditsRespons
 
 fn query_claims(deps: Deps, env: Env, start_after: Option<u64>, limit: Option<u32>) -> StdResult<ClaimsResponse> {
     let limit = limit.unwrap_or(30) as usize;
-    let start = start_after.map(|s| Bound::exclusive(s));
+    let start = start_after.map(Bound::exclusive);
     
     let claims: Vec<ClaimResponse> = CLAIMS
-        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
             let (_, claim) = item?;
-            let voting_end_timestamp = Timestamp::from_seconds(claim.voting_end_time);
-            let (yes_votes, no_votes) = if env.block.time >= voting_end_timestamp {
-                (claim.yes_votes, claim.no_votes)
-            } else {
-                (Uint128::zero(), Uint128::zero())
-            };
-            
-            Ok(ClaimResponse {
-                id: claim.id,
-                organization: claim.organization,
-                longitudes: claim.longitudes,
-                latitudes: claim.latitudes,
-                time_started: claim.time_started,
-                time_ended: claim.time_ended,
-                demanded_tokens: claim.demanded_tokens,
-                ipfs_hashes: claim.ipfs_hashes,
-                status: claim.status,
-                voting_end_time: claim.voting_end_time,
-                yes_votes,
-                no_votes,
-            })
+            Ok(claim_response(claim, &env))
         })
         .collect::<StdResult<Vec<_>>>()?;
     
@@ -672,10 +2961,10 @@ fn query_claims(deps: Deps, env: Env, start_after: Option<u64>, limit: Option<u3
 
 fn query_claims_by_status(deps: Deps, env: Env, status: ClaimStatus, start_after: Option<u64>, limit: Option<u32>) -> StdResult<ClaimsResponse> {
     let limit = limit.unwrap_or(30) as usize;
-    let start = start_after.map(|s| Bound::exclusive(s));
+    let start = start_after.map(Bound::exclusive);
     
     let claims: Vec<ClaimResponse> = CLAIMS
-        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .range(deps.storage, start, None, Order::Ascending)
         .filter(|item| {
             match item {
                 Ok((_, claim)) => claim.status == status,
@@ -685,88 +2974,410 @@ fn query_claims_by_status(deps: Deps, env: Env, status: ClaimStatus, start_after
         .take(limit)
         .map(|item| {
             let (_, claim) = item?;
-            let voting_end_timestamp = Timestamp::from_seconds(claim.voting_end_time);
-            let (yes_votes, no_votes) = if env.block.time >= voting_end_timestamp {
-                (claim.yes_votes, claim.no_votes)
-            } else {
-                (Uint128::zero(), Uint128::zero())
-            };
-            
-            Ok(ClaimResponse {
-                id: claim.id,
-                organization: claim.organization,
-                longitudes: claim.longitudes,
-                latitudes: claim.latitudes,
-                time_started: claim.time_started,
-                time_ended: claim.time_ended,
-                demanded_tokens: claim.demanded_tokens,
-                ipfs_hashes: claim.ipfs_hashes,
-                status: claim.status,
-                voting_end_time: claim.voting_end_time,
-                yes_votes,
-                no_votes,
-            })
+            Ok(claim_response(claim, &env))
         })
         .collect::<StdResult<Vec<_>>>()?;
     
     Ok(ClaimsResponse { claims })
 }
-pub fn execute_update_organization_name(
+pub fn execute_update_organization_name(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+) -> Result<Response, ContractError> {
+    let mut org_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
+        .unwrap_or_default();
+    org_info.name = name.clone();
+    ORGANIZATIONS.save(deps.storage, &info.sender, &org_info, env.block.height)?;
+    
+    Ok(Response::new()
+        .add_attribute("method", "update_organization_name")
+        .add_attribute("organization", info.sender)
+        .add_attribute("name", name))
+}
+
+
+pub fn add_organization_emission(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    emissions: String,
+) -> Result<Response, ContractError> {
+    let new_emissions = Uint128::from_str(&emissions)?;
+    // Recorded as a report like any other; the scope isn't known, so it counts under scope 1
+    let mut entries = latest_emissions_report(deps.storage, &info.sender, UNALLOCATED_EMISSIONS_PERIOD)?
+        .map(|report| report.entries)
+        .unwrap_or_default();
+    entries.push(EmissionEntry {
+        scope: EmissionScope::Scope1,
+        category: "unallocated".to_string(),
+        amount: new_emissions,
+    });
+    record_emissions_report(deps.storage, &env, &info.sender, UNALLOCATED_EMISSIONS_PERIOD, entries)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_organization_emission")
+        .add_attribute("organization", info.sender)
+        .add_attribute("emissions_added", emissions))
+}
+
+/// Allowances and direct transfers only exist while credits are kept in the internal ledger
+fn ensure_ledger(storage: &dyn Storage) -> Result<(), ContractError> {
+    if CONFIG.load(storage)?.credit_token.is_some() {
+        return Err(ContractError::LedgerDisabled {});
+    }
+    Ok(())
+}
+
+fn move_credits(storage: &mut dyn Storage, from: &Addr, to: &Addr, amount: Uint128, height: u64) -> Result<(), ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    let mut from_info = ORGANIZATIONS.may_load(storage, from)?.unwrap_or_default();
+    if from_info.carbon_credits < amount {
+        return Err(ContractError::NotEnoughCredits {});
+    }
+    from_info.carbon_credits -= amount;
+    ORGANIZATIONS.save(storage, from, &from_info, height)?;
+    let mut to_info = ORGANIZATIONS.may_load(storage, to)?.unwrap_or_default();
+    to_info.carbon_credits = to_info.carbon_credits.checked_add(amount)?;
+    ORGANIZATIONS.save(storage, to, &to_info, height)?;
+    Ok(())
+}
+
+pub fn execute_transfer_credits(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let recipient = deps.api.addr_validate(recipient.as_str())?;
+    move_credits(deps.storage, &info.sender, &recipient, amount, env.block.height)?;
+    move_serials(deps.storage, &info.sender, &recipient, amount)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "transfer_credits")
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", amount))
+}
+
+pub fn execute_increase_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: Addr,
+    amount: Uint128,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let spender = deps.api.addr_validate(spender.as_str())?;
+    if spender == info.sender {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+    let allowance = ALLOWANCES.update(deps.storage, (&info.sender, &spender), |allowance| -> Result<_, ContractError> {
+        let mut allowance = allowance.unwrap_or_default();
+        if let Some(expires) = expires {
+            if expires.is_expired(&env.block) {
+                return Err(ContractError::InvalidExpiration {});
+            }
+            allowance.expires = expires;
+        }
+        allowance.allowance = allowance.allowance.checked_add(amount)?;
+        Ok(allowance)
+    })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "increase_allowance")
+        .add_attribute("owner", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("amount", amount)
+        .add_attribute("allowance", allowance.allowance))
+}
+
+pub fn execute_decrease_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: Addr,
+    amount: Uint128,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let spender = deps.api.addr_validate(spender.as_str())?;
+    if spender == info.sender {
+        return Err(ContractError::CannotSetOwnAccount {});
+    }
+    let key = (&info.sender, &spender);
+    let mut allowance = ALLOWANCES.may_load(deps.storage, key)?.unwrap_or_default();
+    // Decreasing by the whole allowance or more removes it
+    if amount >= allowance.allowance {
+        ALLOWANCES.remove(deps.storage, key);
+        allowance.allowance = Uint128::zero();
+    } else {
+        if let Some(expires) = expires {
+            if expires.is_expired(&env.block) {
+                return Err(ContractError::InvalidExpiration {});
+            }
+            allowance.expires = expires;
+        }
+        allowance.allowance -= amount;
+        ALLOWANCES.save(deps.storage, key, &allowance)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "decrease_allowance")
+        .add_attribute("owner", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("amount", amount)
+        .add_attribute("allowance", allowance.allowance))
+}
+
+/// Spends from `owner`'s allowance to the sender
+pub fn execute_transfer_from(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: Addr,
+    recipient: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    let recipient = deps.api.addr_validate(recipient.as_str())?;
+    let key = (&owner, &info.sender);
+    let mut allowance = ALLOWANCES.may_load(deps.storage, key)?.unwrap_or_default();
+    if allowance.expires.is_expired(&env.block) {
+        return Err(ContractError::AllowanceExpired {});
+    }
+    if allowance.allowance < amount {
+        return Err(ContractError::InsufficientAllowance {});
+    }
+    allowance.allowance -= amount;
+    if allowance.allowance.is_zero() {
+        ALLOWANCES.remove(deps.storage, key);
+    } else {
+        ALLOWANCES.save(deps.storage, key, &allowance)?;
+    }
+    move_credits(deps.storage, &owner, &recipient, amount, env.block.height)?;
+    move_serials(deps.storage, &owner, &recipient, amount)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "transfer_from")
+        .add_attribute("from", owner)
+        .add_attribute("to", recipient)
+        .add_attribute("by", info.sender)
+        .add_attribute("amount", amount))
+}
+
+pub fn execute_create_listing(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    price: Coin,
+) -> Result<Response, ContractError> {
+    ensure_ledger(deps.storage)?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    if price.amount.is_zero() {
+        return Err(ContractError::InvalidPrice {});
+    }
+    let mut seller_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    if seller_info.carbon_credits < amount {
+        return Err(ContractError::NotEnoughCredits {});
+    }
+    seller_info.carbon_credits -= amount;
+    ORGANIZATIONS.save(deps.storage, &info.sender, &seller_info, env.block.height)?;
+    // Escrowed serials are held by the contract
+    move_serials(deps.storage, &info.sender, &env.contract.address, amount)?;
+
+    let id = LISTING_COUNTER.load(deps.storage)?;
+    let listing = Listing {
+        id,
+        seller: info.sender.clone(),
+        amount,
+        price: price.amount,
+        denom: price.denom,
+        created_at: env.block.time.seconds(),
+    };
+    listings().save(deps.storage, id, &listing)?;
+    LISTING_COUNTER.save(deps.storage, &(id + 1))?;
+
+    Ok(Response::new()
+        .add_attribute("method", "create_listing")
+        .add_attribute("listing_id", id.to_string())
+        .add_attribute("seller", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("price", listing.price)
+        .add_attribute("denom", listing.denom))
+}
+
+pub fn execute_cancel_listing(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    name: String,
+    listing_id: u64,
 ) -> Result<Response, ContractError> {
-    let mut org_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    org_info.name = name.clone();
-    ORGANIZATIONS.save(deps.storage, &info.sender, &org_info)?;
-    
+    let listing = listings().may_load(deps.storage, listing_id)?.ok_or(ContractError::ListingNotFound {})?;
+    if listing.seller != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Release whatever is still in escrow
+    let mut seller_info = ORGANIZATIONS.may_load(deps.storage, &listing.seller)?.unwrap_or_default();
+    seller_info.carbon_credits = seller_info.carbon_credits.checked_add(listing.amount)?;
+    ORGANIZATIONS.save(deps.storage, &listing.seller, &seller_info, env.block.height)?;
+    move_serials(deps.storage, &env.contract.address, &listing.seller, listing.amount)?;
+    listings().remove(deps.storage, listing_id)?;
+
     Ok(Response::new()
-        .add_attribute("method", "update_organization_name")
-        .add_attribute("organization", info.sender)
-        .add_attribute("name", name))
+        .add_attribute("method", "cancel_listing")
+        .add_attribute("listing_id", listing_id.to_string())
+        .add_attribute("returned", listing.amount))
 }
 
+pub fn execute_fill_listing(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    listing_id: u64,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let mut listing = listings().may_load(deps.storage, listing_id)?.ok_or(ContractError::ListingNotFound {})?;
+    if amount.is_zero() {
+        return Err(ContractError::InvalidAmount {});
+    }
+    if amount > listing.amount {
+        return Err(ContractError::NotEnoughCredits {});
+    }
+    let cost = amount.checked_mul(listing.price)?;
+    let paid = must_pay(&info, &listing.denom)?;
+    if paid != cost {
+        return Err(ContractError::InvalidPayment { sent: paid, expected: cost });
+    }
 
-pub fn add_organization_emission(
+    let mut buyer_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    buyer_info.carbon_credits = buyer_info.carbon_credits.checked_add(amount)?;
+    ORGANIZATIONS.save(deps.storage, &info.sender, &buyer_info, env.block.height)?;
+    move_serials(deps.storage, &env.contract.address, &info.sender, amount)?;
+    listing.amount -= amount;
+    if listing.amount.is_zero() {
+        listings().remove(deps.storage, listing_id)?;
+    } else {
+        listings().save(deps.storage, listing_id, &listing)?;
+    }
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: listing.seller.to_string(),
+            amount: coins(cost.u128(), &listing.denom),
+        })
+        .add_attribute("method", "fill_listing")
+        .add_attribute("listing_id", listing_id.to_string())
+        .add_attribute("buyer", info.sender)
+        .add_attribute("seller", listing.seller)
+        .add_attribute("amount", amount)
+        .add_attribute("cost", cost)
+        .add_attribute("remaining", listing.amount))
+}
+
+pub fn execute_report_emissions(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    emissions: String,
+    period: u32,
+    entries: Vec<EmissionEntry>,
 ) -> Result<Response, ContractError> {
-    let mut org_info = ORGANIZATIONS.may_load(deps.storage, &info.sender)?
-        .unwrap_or(OrganizationInfo {
-            reputation_score: Uint128::zero(),
-            carbon_credits: Uint128::zero(),
-            debt: Uint128::zero(),
-            times_borrowed: 0,
-            total_borrowed: Uint128::zero(),
-            total_returned: Uint128::zero(),
-            name: "".to_string(),
-            emissions: Uint128::zero(),
-        });
-    let new_emissions = Uint128::from_str(&emissions)?;
-    org_info.emissions = org_info.emissions.checked_add(new_emissions)?;
-    ORGANIZATIONS.save(deps.storage, &info.sender, &org_info)?;
+    if entries.is_empty() {
+        return Err(ContractError::EmptyEmissionsReport {});
+    }
+    let report = record_emissions_report(deps.storage, &env, &info.sender, period, entries)?;
 
     Ok(Response::new()
-        .add_attribute("method", "add_organization_emission")
+        .add_attribute("method", "report_emissions")
         .add_attribute("organization", info.sender)
-        .add_attribute("emissions_added", emissions))
+        .add_attribute("period", period.to_string())
+        .add_attribute("version", report.version.to_string())
+        .add_attribute("gross", report.gross))
+}
+
+/// Saves `entries` as the next version of the organization's report for `period`
+fn record_emissions_report(
+    storage: &mut dyn Storage,
+    env: &Env,
+    organization: &Addr,
+    period: u32,
+    entries: Vec<EmissionEntry>,
+) -> Result<EmissionsReport, ContractError> {
+    let (mut scope_1, mut scope_2, mut scope_3) = (Uint128::zero(), Uint128::zero(), Uint128::zero());
+    for entry in &entries {
+        let total = match entry.scope {
+            EmissionScope::Scope1 => &mut scope_1,
+            EmissionScope::Scope2 => &mut scope_2,
+            EmissionScope::Scope3 => &mut scope_3,
+        };
+        *total = total.checked_add(entry.amount)?;
+    }
+    let gross = scope_1.checked_add(scope_2)?.checked_add(scope_3)?;
+
+    let version = latest_emissions_report(storage, organization, period)?.map_or(1, |report| report.version + 1);
+    let report = EmissionsReport {
+        organization: organization.clone(),
+        period,
+        version,
+        entries,
+        scope_1,
+        scope_2,
+        scope_3,
+        gross,
+        reported_at: env.block.time.seconds(),
+    };
+    EMISSION_REPORTS.save(storage, (organization, period, version), &report)?;
+    update_net_emissions(storage, organization, env.block.height)?;
+    Ok(report)
+}
+
+/// Rederives the organization's net lifetime emissions from its reports and offsets. Offsets only
+/// count against the period they were retired for, so a surplus in one period is not carried over.
+fn update_net_emissions(storage: &mut dyn Storage, organization: &Addr, height: u64) -> StdResult<()> {
+    let mut net = Uint128::zero();
+    for period in emission_periods(storage, organization)? {
+        let gross = latest_emissions_report(storage, organization, period)?.map(|report| report.gross).unwrap_or_default();
+        let offsets = PERIOD_OFFSETS.may_load(storage, (organization, period))?.unwrap_or_default();
+        net = net.checked_add(gross.saturating_sub(offsets))?;
+    }
+    let mut org_info = ORGANIZATIONS.may_load(storage, organization)?.unwrap_or_default();
+    org_info.emissions = net;
+    ORGANIZATIONS.save(storage, organization, &org_info, height)
+}
+
+/// Every period the organization reported emissions or received offsets in
+fn emission_periods(storage: &dyn Storage, organization: &Addr) -> StdResult<BTreeSet<u32>> {
+    let mut periods = BTreeSet::new();
+    for item in EMISSION_REPORTS.sub_prefix(organization).keys(storage, None, None, Order::Ascending) {
+        periods.insert(item?.0);
+    }
+    for item in PERIOD_OFFSETS.prefix(organization).keys(storage, None, None, Order::Ascending) {
+        periods.insert(item?);
+    }
+    Ok(periods)
+}
+
+fn latest_emissions_report(storage: &dyn Storage, organization: &Addr, period: u32) -> StdResult<Option<EmissionsReport>> {
+    EMISSION_REPORTS
+        .prefix((organization, period))
+        .range(storage, None, None, Order::Descending)
+        .next()
+        .transpose()
+        .map(|item| item.map(|(_, report)| report))
 }
 
 pub fn query_user_lend_requests(
     deps: Deps,
+    env: Env,
     user: Addr,
     start_after: Option<u64>, 
     limit: Option<u32>,
@@ -785,15 +3396,26 @@ pub fn query_user_lend_requests(
             } else {
                 None
             };
+            // Expired requests keep their stored status until the borrower reclaims the escrow
+            let status = if lend_request.is_expired(env.block.time.seconds()) {
+                LentStatus::Expired
+            } else {
+                lend_request.status.clone()
+            };
             role.map(|role| LendRequestResponse {
                 id,
                 borrower: lend_request.borrower,
                 lender: lend_request.lender,
-                status: lend_request.status,
+                status,
                 eligibility_score: lend_request.eligibility_score,
                 proof_data: lend_request.proof_data,
                 time: lend_request.time,
                 amount: lend_request.amount,
+                interest_rate: lend_request.interest_rate,
+                term: lend_request.term,
+                collateral: lend_request.collateral,
+                expires_at: lend_request.expires_at,
+                counter_amount: lend_request.counter_amount,
                 role,
             })
         })
@@ -802,23 +3424,21 @@ pub fn query_user_lend_requests(
     Ok(UserLendRequestsResponse { lend_requests })
 }
 
-fn query_all_organizations(deps: Deps, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<OrganizationsResponse> {
+fn query_all_organizations(deps: Deps, env: Env, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<OrganizationsResponse> {
+    let config = CONFIG.load(deps.storage)?;
     let limit = limit.unwrap_or(30) as usize;
     
-    let start = match start_after {
-        Some(addr) => Some(Bound::ExclusiveRaw(addr.to_string().into())),
-        None => None,
-    };
+    let start = start_after.map(|addr| Bound::ExclusiveRaw(addr.to_string().into()));
     
     let organizations: Vec<OrganizationListItem> = ORGANIZATIONS
-        .range(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
             let (addr, org_info) = item?;
             Ok(OrganizationListItem {
                 address: addr,
+                reputation_score: org_info.reputation_at(&config.reputation, env.block.time.seconds()),
                 name: org_info.name,
-                reputation_score: org_info.reputation_score,
                 carbon_credits: org_info.carbon_credits
             })
         })
@@ -827,3 +3447,456 @@ fn query_all_organizations(deps: Deps, start_after: Option<Addr>, limit: Option<
     Ok(OrganizationsResponse { organizations })
     
 }
+
+pub fn execute_add_verifier(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: Addr,
+    name: String,
+    metadata: String,
+    accredited_until: u64,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !is_admin(&config, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Re-adding renews accreditation and metadata but keeps the track record
+    let verifier = match VERIFIERS.may_load(deps.storage, &address)? {
+        Some(existing) => Verifier {
+            name,
+            metadata,
+            accredited_until,
+            ..existing
+        },
+        None => {
+            let count = VERIFIER_COUNT.may_load(deps.storage)?.unwrap_or_default();
+            if count >= MAX_VERIFIERS {
+                return Err(ContractError::TooManyVerifiers { max: MAX_VERIFIERS });
+            }
+            VERIFIER_COUNT.save(deps.storage, &(count + 1))?;
+            Verifier {
+                address: address.clone(),
+                name,
+                metadata,
+                accredited_until,
+                added_at: env.block.time.seconds(),
+                votes_cast: 0,
+                correct_votes: 0,
+            }
+        },
+    };
+    VERIFIERS.save(deps.storage, &address, &verifier)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_verifier")
+        .add_attribute("verifier", address)
+        .add_attribute("accredited_until", accredited_until.to_string()))
+}
+
+pub fn execute_remove_verifier(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: Addr,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !is_admin(&config, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !VERIFIERS.has(deps.storage, &address) {
+        return Err(ContractError::VerifierNotFound {});
+    }
+    VERIFIERS.remove(deps.storage, &address);
+    VERIFIER_COUNT.update(deps.storage, |count| -> StdResult<_> { Ok(count.saturating_sub(1)) })?;
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_verifier")
+        .add_attribute("verifier", address))
+}
+
+fn query_verifier(deps: Deps, env: Env, address: Addr) -> StdResult<VerifierResponse> {
+    let verifier = VERIFIERS.load(deps.storage, &address)?;
+    Ok(VerifierResponse {
+        accredited: env.block.time.seconds() <= verifier.accredited_until,
+        verifier,
+    })
+}
+
+fn query_verifiers(deps: Deps, env: Env, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<VerifiersResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(|addr| Bound::ExclusiveRaw(addr.to_string().into()));
+
+    let verifiers: Vec<VerifierResponse> = VERIFIERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, verifier) = item?;
+            Ok(VerifierResponse {
+                accredited: env.block.time.seconds() <= verifier.accredited_until,
+                verifier,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(VerifiersResponse { verifiers })
+}
+
+fn query_challenges(deps: Deps, start_after: Option<u64>, limit: Option<u32>) -> StdResult<ChallengesResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let challenges = CHALLENGES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, challenge)| challenge))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ChallengesResponse { challenges })
+}
+
+/// Batches under an index prefix of `credit_batches`
+fn query_batches(deps: Deps, batches: Prefix<u64, CreditBatch, u64>, start_after: Option<u64>, limit: Option<u32>) -> StdResult<BatchesResponse> {
+    let limit = limit.unwrap_or(30).min(MAX_BATCH_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let batches = batches
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, batch)| batch))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(BatchesResponse { batches })
+}
+
+fn query_owner_of(deps: Deps, env: Env, token_id: String, include_expired: Option<bool>) -> StdResult<OwnerOfResponse> {
+    let batch = load_batch(deps.storage, &token_id).map_err(|e| StdError::generic_err(e.to_string()))?;
+    Ok(OwnerOfResponse {
+        approvals: nft_approvals(deps, &env, batch.id, include_expired)?,
+        owner: batch.owner,
+    })
+}
+
+fn nft_approvals(deps: Deps, env: &Env, id: u64, include_expired: Option<bool>) -> StdResult<Vec<NftApproval>> {
+    NFT_APPROVALS
+        .prefix(id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| include_expired.unwrap_or(false) || item.as_ref().map_or(true, |(_, expires)| !expires.is_expired(&env.block)))
+        .map(|item| item.map(|(spender, expires)| NftApproval { spender, expires }))
+        .collect()
+}
+
+/// As in CW721, the owner counts as approved without expiry
+fn query_approval(deps: Deps, env: Env, token_id: String, spender: Addr, include_expired: Option<bool>) -> StdResult<ApprovalResponse> {
+    let batch = load_batch(deps.storage, &token_id).map_err(|e| StdError::generic_err(e.to_string()))?;
+    if batch.owner == spender {
+        return Ok(ApprovalResponse { approval: NftApproval { spender, expires: Expiration::Never {} } });
+    }
+    match NFT_APPROVALS.may_load(deps.storage, (batch.id, &spender))? {
+        Some(expires) if include_expired.unwrap_or(false) || !expires.is_expired(&env.block) => {
+            Ok(ApprovalResponse { approval: NftApproval { spender, expires } })
+        },
+        _ => Err(StdError::not_found("Approval")),
+    }
+}
+
+fn query_all_operators(deps: Deps, env: Env, owner: Addr, include_expired: Option<bool>, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<OperatorsResponse> {
+    let limit = limit.unwrap_or(30).min(MAX_BATCH_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let operators = NFT_OPERATORS
+        .prefix(&owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| include_expired.unwrap_or(false) || item.as_ref().map_or(true, |(_, expires)| !expires.is_expired(&env.block)))
+        .take(limit)
+        .map(|item| item.map(|(spender, expires)| NftApproval { spender, expires }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(OperatorsResponse { operators })
+}
+
+fn query_nft_info(deps: Deps, token_id: String) -> StdResult<NftInfoResponse> {
+    let batch = load_batch(deps.storage, &token_id).map_err(|e| StdError::generic_err(e.to_string()))?;
+    Ok(NftInfoResponse { token_uri: None, extension: batch })
+}
+
+fn query_tokens(deps: Deps, owner: Addr, start_after: Option<String>, limit: Option<u32>) -> StdResult<TokensResponse> {
+    let start_after = start_after
+        .map(|token_id| token_id.parse::<u64>().map_err(|_| StdError::generic_err("Invalid token id")))
+        .transpose()?;
+    let batches = query_batches(deps, credit_batches().idx.owner.prefix(owner), start_after, limit)?.batches;
+    Ok(TokensResponse {
+        tokens: batches.iter().map(|batch| batch.id.to_string()).collect(),
+    })
+}
+
+fn query_all_tokens(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(30).min(MAX_BATCH_LIMIT) as usize;
+    let start = start_after
+        .map(|token_id| token_id.parse::<u64>().map_err(|_| StdError::generic_err("Invalid token id")))
+        .transpose()?
+        .map(Bound::exclusive);
+
+    let tokens = credit_batches()
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|id| id.to_string()))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TokensResponse { tokens })
+}
+
+fn query_retirements(deps: Deps, start_after: Option<u64>, limit: Option<u32>, filter: impl Fn(&RetirementCertificate) -> bool) -> StdResult<RetirementsResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let retirements = RETIREMENTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, certificate)) => filter(certificate),
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|item| item.map(|(_, certificate)| certificate))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(RetirementsResponse { retirements })
+}
+
+fn query_emissions_report(deps: Deps, organization: Addr, period: u32, version: Option<u32>) -> StdResult<EmissionsReport> {
+    let report = match version {
+        Some(version) => EMISSION_REPORTS.may_load(deps.storage, (&organization, period, version))?,
+        None => latest_emissions_report(deps.storage, &organization, period)?,
+    };
+    report.ok_or_else(|| StdError::generic_err(ContractError::EmissionsReportNotFound {}.to_string()))
+}
+
+fn query_net_position(deps: Deps, organization: Addr, period: u32) -> StdResult<NetPositionResponse> {
+    let report = latest_emissions_report(deps.storage, &organization, period)?;
+    let retired_offsets = PERIOD_OFFSETS.may_load(deps.storage, (&organization, period))?.unwrap_or_default();
+    let gross_emissions = report.as_ref().map(|report| report.gross).unwrap_or_default();
+
+    Ok(NetPositionResponse {
+        organization,
+        period,
+        reported: report.is_some(),
+        gross_emissions,
+        retired_offsets,
+        net_emissions: gross_emissions.saturating_sub(retired_offsets),
+        surplus_offsets: retired_offsets.saturating_sub(gross_emissions),
+        // Without a report there is nothing to be net-zero against
+        net_zero: report.is_some() && retired_offsets >= gross_emissions,
+    })
+}
+
+/// Positions for every period the organization reported emissions or received offsets in
+fn query_net_positions(deps: Deps, organization: Addr, start_after: Option<u32>, limit: Option<u32>) -> StdResult<NetPositionsResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+
+    let periods = emission_periods(deps.storage, &organization)?;
+    let start = start_after.map_or(RangeBound::Unbounded, RangeBound::Excluded);
+    let positions = periods
+        .range((start, RangeBound::Unbounded))
+        .take(limit)
+        .map(|period| query_net_position(deps, organization.clone(), *period))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(NetPositionsResponse { positions })
+}
+
+fn query_all_allowances(deps: Deps, owner: Addr, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<AllAllowancesResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let allowances = ALLOWANCES
+        .prefix(&owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(spender, allowance)| AllowanceInfo {
+                spender: spender.to_string(),
+                allowance: allowance.allowance,
+                expires: allowance.expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AllAllowancesResponse { allowances })
+}
+
+fn query_listings_by_seller(deps: Deps, seller: Addr, start_after: Option<u64>, limit: Option<u32>) -> StdResult<ListingsResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let listings = listings()
+        .idx
+        .seller
+        .prefix(seller)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, listing)| listing))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ListingsResponse { listings })
+}
+
+fn query_order_book(deps: Deps, denom: String, start_after: Option<(Uint128, u64)>, limit: Option<u32>) -> StdResult<ListingsResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(|(price, id)| Bound::exclusive((price.u128(), id)));
+
+    let listings = listings()
+        .idx
+        .price
+        .sub_prefix(denom)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, listing)| listing))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ListingsResponse { listings })
+}
+
+/// Loans under an open-loan prefix of a `loans` index
+fn query_open_loans(deps: Deps, env: Env, open_loans: Prefix<u64, Loan, u64>, start_after: Option<u64>, limit: Option<u32>) -> StdResult<LoansResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let loans = open_loans
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, mut loan)| {
+            loan.accrue(env.block.time.seconds());
+            loan
+        }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(LoansResponse { loans })
+}
+
+fn query_loan_health(deps: Deps, env: Env, id: u64) -> StdResult<LoanHealthResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut loan = loans().load(deps.storage, id)?;
+    loan.accrue(env.block.time.seconds());
+
+    let remaining = loan.remaining();
+    let required_collateral = config.collateral.map(|params| remaining * params.ratio).unwrap_or_default();
+    let health = match &loan.collateral {
+        Some(collateral) if !required_collateral.is_zero() => Some(Decimal::from_ratio(collateral.amount, required_collateral)),
+        _ => None,
+    };
+    Ok(LoanHealthResponse {
+        loan_id: id,
+        remaining,
+        overdue: !loan.is_closed() && env.block.time.seconds() > loan.due_at,
+        collateral: loan.collateral,
+        required_collateral,
+        health,
+    })
+}
+
+fn query_lend_offers(deps: Deps, eligibility_score: Option<Uint128>, start_after: Option<u64>, limit: Option<u32>) -> StdResult<LendOffersResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let offers = LEND_OFFERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match (item, eligibility_score) {
+            (Ok((_, offer)), Some(score)) => offer.min_eligibility_score <= score,
+            _ => true,
+        })
+        .take(limit)
+        .map(|item| item.map(|(_, offer)| offer))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(LendOffersResponse { offers })
+}
+
+/// Mirrors the checks of `execute_request_tokens` and `lend_on_request`, without collateral
+fn query_simulate_eligibility(deps: Deps, env: Env, borrower: Addr, lender: Addr, amount: Uint128) -> StdResult<SimulateEligibilityResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let policy = ELIGIBILITY_POLICY.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    let mut borrower_info = ORGANIZATIONS.may_load(deps.storage, &borrower)?.unwrap_or_default();
+    borrower_info.reputation_score = borrower_info.reputation_at(&config.reputation, now);
+    let snapshot = eligibility_snapshot(deps, &env, &config, &borrower)?;
+    let score = policy.score(&snapshot)?;
+    let policy_version = ELIGIBILITY_POLICY_VERSION.may_load(deps.storage)?.unwrap_or_default();
+
+    // With a verifying key the request itself must carry a proof, which a query cannot supply;
+    // otherwise acceptance may need a stored record covering the amount
+    let proof_required = ELIGIBILITY_KEY.may_load(deps.storage)?.is_some() || (config.require_eligibility_proof && !PROOFS
+        .may_load(deps.storage, (&borrower, &lender))?
+        .is_some_and(|record| record.is_current(amount, policy_version, now)));
+    let lender_info = ORGANIZATIONS.may_load(deps.storage, &lender)?.unwrap_or_default();
+    let lender_funded = held_credits(deps, &config, &lender, &lender_info)? >= amount;
+
+    Ok(SimulateEligibilityResponse {
+        borrower,
+        lender,
+        amount,
+        score,
+        factors: policy.factors(&snapshot)?,
+        policy_version,
+        min_score: policy.min_score,
+        proof_required,
+        eligible: score >= policy.min_score
+            && ensure_can_borrow(&config, &borrower_info).is_ok()
+            && !amount.is_zero()
+            && lender_funded
+            && !proof_required,
+    })
+}
+
+fn query_eligibility_proofs(deps: Deps, borrower: Addr, start_after: Option<Addr>, limit: Option<u32>) -> StdResult<EligibilityProofsResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+    let proofs = PROOFS
+        .prefix(&borrower)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(EligibilityProofsResponse { proofs })
+}
+
+fn query_reputation_events(deps: Deps, address: Addr, start_after: Option<u64>, limit: Option<u32>) -> StdResult<ReputationEventsResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let events = REPUTATION_EVENTS
+        .prefix(&address)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, event)| event))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ReputationEventsResponse { events })
+}
+
+fn query_pool(deps: Deps, env: Env) -> StdResult<PoolResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let pool = load_pool(deps.storage, &config, env.block.time.seconds())?;
+    Ok(PoolResponse {
+        total_shares: pool.total_shares,
+        available: pool.available,
+        borrowed: pool.borrowed(),
+        total_assets: pool.total_assets(),
+        utilisation: pool.utilisation(),
+        borrow_rate: pool.borrow_rate(&config.pool),
+        borrow_index: pool.borrow_index,
+    })
+}
+
+fn query_pool_position(deps: Deps, env: Env, address: Addr) -> StdResult<PoolPositionResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let pool = load_pool(deps.storage, &config, env.block.time.seconds())?;
+    let shares = POOL_SHARES.may_load(deps.storage, &address)?.unwrap_or_default();
+    let pool_debt = POOL_DEBTS.may_load(deps.storage, &address)?.unwrap_or_default();
+    Ok(PoolPositionResponse {
+        address,
+        shares,
+        value: pool.share_value(shares),
+        debt: pool.debt_value(pool_debt.debt_shares),
+    })
+}
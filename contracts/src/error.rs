@@ -1,6 +1,7 @@
 use cosmwasm_std::StdError;
 use thiserror::Error;
-use cosmwasm_std::OverflowError;
+use cosmwasm_std::{OverflowError, Uint128};
+use cw_utils::PaymentError;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
@@ -10,9 +11,6 @@ pub enum ContractError {
     #[error("Unauthorized")]
     Unauthorized {},
 
-    #[error("Unauthorized")]
-    InvalidResponse{},
-
     #[error("RequestNotActive")]
     RequestNotActive{},
 
@@ -22,6 +20,27 @@ pub enum ContractError {
     #[error("Claim not found")]
     ClaimNotFound {},
 
+    #[error("Claim is not active")]
+    ClaimNotActive {},
+
+    #[error("Claim is not approved")]
+    ClaimNotApproved {},
+
+    #[error("Challenge period has ended")]
+    ChallengePeriodEnded {},
+
+    #[error("Challenge period has not ended")]
+    ChallengePeriodNotEnded {},
+
+    #[error("No credits are held in escrow for this claim")]
+    NoEscrowedCredits {},
+
+    #[error("Claim has already been challenged")]
+    AlreadyChallenged {},
+
+    #[error("Challenge is not open")]
+    ChallengeNotOpen {},
+
     #[error("Voting period has ended")]
     VotingEnded {},
 
@@ -31,9 +50,156 @@ pub enum ContractError {
     #[error("Already voted")]
     AlreadyVoted {},
 
+    #[error("Reveal period has not started")]
+    RevealNotStarted {},
+
+    #[error("Reveal period has ended")]
+    RevealEnded {},
+
+    #[error("No vote commitment found")]
+    CommitNotFound {},
+
+    #[error("Revealed vote does not match commitment")]
+    InvalidCommitment {},
+
+    #[error("No voting power")]
+    NoVotingPower {},
+
+    #[error("Sender is not an accredited verifier")]
+    NotVerifier {},
+
+    #[error("Verifier accreditation has expired")]
+    AccreditationExpired {},
+
+    #[error("Cannot vote on own claim")]
+    OwnClaim {},
+
+    #[error("Verifier not found")]
+    VerifierNotFound {},
+
+    #[error("No more than {max} verifiers may be registered")]
+    TooManyVerifiers { max: u32 },
+
+    #[error("Invalid tally params: thresholds must not exceed 1")]
+    InvalidTallyParams {},
+
+    #[error("Invalid coordinate: {value}")]
+    InvalidCoordinate { value: String },
+
+    #[error("Longitudes and latitudes differ in length")]
+    MismatchedCoordinates {},
+
+    #[error("Polygon must be closed and have at least three vertices")]
+    PolygonNotClosed {},
+
+    #[error("Invalid polygon: {reason}")]
+    InvalidPolygon { reason: String },
+
+    #[error("Polygon intersects itself")]
+    SelfIntersectingPolygon {},
+
+    #[error("Claim period must end after it starts")]
+    InvalidClaimPeriod {},
+
+    #[error("Claim area overlaps claim {claim_id}")]
+    OverlappingClaim { claim_id: u64 },
+
+    #[error("Credits are a CW20 token; send them to this contract instead")]
+    TokenPaymentRequired {},
+
+    #[error("Sent {sent} tokens, expected {expected}")]
+    InvalidPayment { sent: Uint128, expected: Uint128 },
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Amount must be greater than zero")]
+    InvalidAmount {},
+
+    #[error("Emissions report must have at least one entry")]
+    EmptyEmissionsReport {},
+
+    #[error("Emissions report not found")]
+    EmissionsReportNotFound {},
+
+    #[error("Credits live on the CW20 token; use its transfer and allowance messages")]
+    LedgerDisabled {},
+
+    #[error("Cannot set allowance to own account")]
+    CannotSetOwnAccount {},
+
+    #[error("Invalid expiration value")]
+    InvalidExpiration {},
+
+    #[error("Allowance is expired")]
+    AllowanceExpired {},
+
+    #[error("Insufficient allowance")]
+    InsufficientAllowance {},
+
+    #[error("Listing not found")]
+    ListingNotFound {},
+
+    #[error("Loan not found")]
+    LoanNotFound {},
+
+    #[error("Loan is no longer open")]
+    LoanNotOpen {},
+
+    #[error("Loan is not past its due date")]
+    LoanNotOverdue {},
+
+    #[error("Loan term must be greater than zero")]
+    InvalidLoanTerm {},
+
+    #[error("At least {required} collateral is required")]
+    InsufficientCollateral { required: Uint128 },
+
+    #[error("Loan has no collateral to claim")]
+    NoCollateral {},
+
+    #[error("Loan has not defaulted")]
+    LoanNotDefaulted {},
+
+    #[error("Lend offer not found")]
+    OfferNotFound {},
+
+    #[error("Lend request has expired")]
+    RequestExpired {},
+
+    #[error("Only {available} credits are available in the pool")]
+    InsufficientLiquidity { available: Uint128 },
+
+    #[error("Not enough pool shares")]
+    InsufficientShares {},
+
+    #[error("Only {available} credits are left on the offer")]
+    ExceedsOffer { available: Uint128 },
+
+    #[error("Repayment exceeds the {remaining} credits outstanding on the loan")]
+    RepaymentExceedsLoan { remaining: Uint128 },
+
+    #[error("Price must be greater than zero")]
+    InvalidPrice {},
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("Credit batch not found")]
+    BatchNotFound {},
+
+    #[error("Split amount must be positive and smaller than the batch")]
+    InvalidSplit {},
+
     #[error("Invalid proof")]
     InvalidProof {},
 
+    #[error("Lender requires a current eligibility proof from the borrower")]
+    EligibilityProofRequired {},
+
+    #[error("Invalid eligibility verifying key")]
+    InvalidVerifyingKey {},
+
     #[error("Not enough carbon credits")]
     NotEnoughCredits {},
 
@@ -43,6 +209,15 @@ pub enum ContractError {
     #[error("Borrower not eligible")]
     BorrowerNotEligible {},
 
+    #[error("Minimum eligibility score must not exceed the maximum")]
+    InvalidEligibilityPolicy {},
+
+    #[error("Cannot migrate from contract {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from version {previous_version} down to {version}")]
+    CannotDowngrade { previous_version: String, version: String },
+
     #[error("Overflow: {0}")]
     Overflow(#[from] OverflowError),
 }
\ No newline at end of file
@@ -0,0 +1,247 @@
+use crate::error::ContractError;
+
+/// Coordinates are stored as fixed-point integers in microdegrees
+pub const COORDINATE_SCALE: i64 = 1_000_000;
+const COORDINATE_DECIMALS: usize = 6;
+pub const MAX_VERTICES: usize = 256;
+/// Claims are indexed by the cells of a grid this many microdegrees wide (a quarter degree)
+pub const GRID_CELL_SIZE: i64 = COORDINATE_SCALE / 4;
+/// Cells the bounding box of a claim may span, which bounds its overlap check
+pub const MAX_GRID_CELLS: usize = 64;
+/// Smallest area, in square microdegrees, a claim may cover: a 1024th of a grid cell. It bounds how
+/// many claims with overlapping periods a cell can hold, and so the overlap check of a new claim.
+pub const MIN_CLAIM_AREA: u128 = (GRID_CELL_SIZE as u128 / 32) * (GRID_CELL_SIZE as u128 / 32);
+
+/// (longitude, latitude) in microdegrees
+pub type Point = (i64, i64);
+
+type ScaledPoint = (i128, i128);
+
+// Probe points are nudged by one unit in a grid this much finer than a microdegree
+const PROBE_SCALE: i128 = 1024;
+
+pub fn parse_coordinate(value: &str, max_degrees: i64) -> Result<i64, ContractError> {
+    let invalid = || ContractError::InvalidCoordinate { value: value.to_string() };
+    let trimmed = value.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (unsigned, ""),
+    };
+    if whole.is_empty()
+        || fraction.len() > COORDINATE_DECIMALS
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let whole: i64 = whole.parse().map_err(|_| invalid())?;
+    let fraction: i64 = if fraction.is_empty() {
+        0
+    } else {
+        let padded = format!("{:0<width$}", fraction, width = COORDINATE_DECIMALS);
+        padded.parse().map_err(|_| invalid())?
+    };
+    let magnitude = whole
+        .checked_mul(COORDINATE_SCALE)
+        .and_then(|w| w.checked_add(fraction))
+        .ok_or_else(invalid)?;
+    if magnitude > max_degrees * COORDINATE_SCALE {
+        return Err(invalid());
+    }
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// (column, row) of every grid cell the bounding box of `polygon` touches. Overlapping polygons
+/// share an interior point, so they always share a cell.
+pub fn grid_cells(polygon: &[Point]) -> Result<Vec<(i32, i32)>, ContractError> {
+    let cell = |value: i64| value.div_euclid(GRID_CELL_SIZE) as i32;
+    let columns = cell(polygon.iter().map(|p| p.0).min().unwrap_or_default())..=cell(polygon.iter().map(|p| p.0).max().unwrap_or_default());
+    let rows = cell(polygon.iter().map(|p| p.1).min().unwrap_or_default())..=cell(polygon.iter().map(|p| p.1).max().unwrap_or_default());
+    if columns.clone().count() * rows.clone().count() > MAX_GRID_CELLS {
+        return Err(ContractError::InvalidPolygon { reason: format!("spans more than {} grid cells", MAX_GRID_CELLS) });
+    }
+    Ok(columns.flat_map(|column| rows.clone().map(move |row| (column, row))).collect())
+}
+
+/// Parses a claim boundary into its vertices. The ring must be closed (last point repeats the
+/// first), have at least three distinct vertices, enclose a positive area and not cross itself.
+/// The closing point is dropped from the result.
+pub fn parse_polygon(longitudes: &[String], latitudes: &[String]) -> Result<Vec<Point>, ContractError> {
+    if longitudes.len() != latitudes.len() {
+        return Err(ContractError::MismatchedCoordinates {});
+    }
+    let mut points = longitudes
+        .iter()
+        .zip(latitudes.iter())
+        .map(|(lon, lat)| Ok((parse_coordinate(lon, 180)?, parse_coordinate(lat, 90)?)))
+        .collect::<Result<Vec<Point>, ContractError>>()?;
+
+    if points.len() < 4 || points.first() != points.last() {
+        return Err(ContractError::PolygonNotClosed {});
+    }
+    points.pop();
+    if points.len() > MAX_VERTICES {
+        return Err(ContractError::InvalidPolygon { reason: format!("more than {} vertices", MAX_VERTICES) });
+    }
+    let n = points.len();
+    if (0..n).any(|i| points[i] == points[(i + 1) % n]) {
+        return Err(ContractError::InvalidPolygon { reason: "repeated vertex".to_string() });
+    }
+    if doubled_signed_area(&points) == 0 {
+        return Err(ContractError::InvalidPolygon { reason: "zero area".to_string() });
+    }
+    if is_self_intersecting(&points) {
+        return Err(ContractError::SelfIntersectingPolygon {});
+    }
+    Ok(points)
+}
+
+/// Area in square microdegrees (shoelace formula)
+pub fn polygon_area(points: &[Point]) -> u128 {
+    doubled_signed_area(points).unsigned_abs() / 2
+}
+
+/// True when the interiors of two simple polygons intersect. Polygons that only share
+/// an edge or a vertex do not overlap.
+pub fn polygons_overlap(a: &[Point], b: &[Point]) -> bool {
+    let (a_min, a_max) = bounding_box(a);
+    let (b_min, b_max) = bounding_box(b);
+    if a_max.0 <= b_min.0 || b_max.0 <= a_min.0 || a_max.1 <= b_min.1 || b_max.1 <= a_min.1 {
+        return false;
+    }
+
+    let a = scale(a);
+    let b = scale(b);
+    for i in 0..a.len() {
+        let (p1, p2) = (a[i], a[(i + 1) % a.len()]);
+        for j in 0..b.len() {
+            if segments_cross(p1, p2, b[j], b[(j + 1) % b.len()]) {
+                return true;
+            }
+        }
+    }
+    a.iter().any(|p| strictly_inside(*p, &b))
+        || b.iter().any(|p| strictly_inside(*p, &a))
+        || interior_probes(&a).any(|p| strictly_inside(p, &b))
+        || interior_probes(&b).any(|p| strictly_inside(p, &a))
+}
+
+fn doubled_signed_area(points: &[Point]) -> i128 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[(i + 1) % n];
+            x1 as i128 * y2 as i128 - x2 as i128 * y1 as i128
+        })
+        .sum()
+}
+
+fn is_self_intersecting(points: &[Point]) -> bool {
+    let p = scale(points);
+    let n = p.len();
+    for i in 0..n {
+        let (a1, a2) = (p[i], p[(i + 1) % n]);
+        // Adjacent edges may only share their common vertex, not fold back over each other
+        let a3 = p[(i + 2) % n];
+        if orientation(a1, a2, a3) == 0 && dot((a2.0 - a1.0, a2.1 - a1.1), (a3.0 - a2.0, a3.1 - a2.1)) < 0 {
+            return true;
+        }
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            if segments_touch(a1, a2, p[j], p[(j + 1) % n]) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn scale(points: &[Point]) -> Vec<ScaledPoint> {
+    points
+        .iter()
+        .map(|(x, y)| (*x as i128 * PROBE_SCALE, *y as i128 * PROBE_SCALE))
+        .collect()
+}
+
+fn bounding_box(points: &[Point]) -> (Point, Point) {
+    let min = (points.iter().map(|p| p.0).min().unwrap_or(0), points.iter().map(|p| p.1).min().unwrap_or(0));
+    let max = (points.iter().map(|p| p.0).max().unwrap_or(0), points.iter().map(|p| p.1).max().unwrap_or(0));
+    (min, max)
+}
+
+/// A point just inside each edge midpoint, on the interior side of the ring
+fn interior_probes(points: &[ScaledPoint]) -> impl Iterator<Item = ScaledPoint> + '_ {
+    let n = points.len();
+    let area: i128 = (0..n)
+        .map(|i| points[i].0 * points[(i + 1) % n].1 - points[(i + 1) % n].0 * points[i].1)
+        .sum();
+    let side = area.signum(); // interior is left of each edge for counter-clockwise rings
+    (0..n).map(move |i| {
+        let (p, q) = (points[i], points[(i + 1) % n]);
+        let (dx, dy) = (q.0 - p.0, q.1 - p.1);
+        ((p.0 + q.0) / 2 - side * dy.signum(), (p.1 + q.1) / 2 + side * dx.signum())
+    })
+}
+
+fn strictly_inside(point: ScaledPoint, polygon: &[ScaledPoint]) -> bool {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+        if orientation(a, b, point) == 0 && on_segment(a, b, point) {
+            return false;
+        }
+        if (a.1 > point.1) != (b.1 > point.1) {
+            let cross = orientation(a, b, point);
+            if (b.1 > a.1 && cross > 0) || (b.1 < a.1 && cross < 0) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn orientation(a: ScaledPoint, b: ScaledPoint, c: ScaledPoint) -> i128 {
+    ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).signum()
+}
+
+fn dot(u: ScaledPoint, v: ScaledPoint) -> i128 {
+    u.0 * v.0 + u.1 * v.1
+}
+
+/// `c` is collinear with `a`-`b`; checks it lies within the segment
+fn on_segment(a: ScaledPoint, b: ScaledPoint, c: ScaledPoint) -> bool {
+    c.0 >= a.0.min(b.0) && c.0 <= a.0.max(b.0) && c.1 >= a.1.min(b.1) && c.1 <= a.1.max(b.1)
+}
+
+/// Segments cross at a single point interior to both
+fn segments_cross(p1: ScaledPoint, p2: ScaledPoint, p3: ScaledPoint, p4: ScaledPoint) -> bool {
+    let d1 = orientation(p3, p4, p1);
+    let d2 = orientation(p3, p4, p2);
+    let d3 = orientation(p1, p2, p3);
+    let d4 = orientation(p1, p2, p4);
+    d1 * d2 < 0 && d3 * d4 < 0
+}
+
+/// Segments share at least one point, endpoints included
+fn segments_touch(p1: ScaledPoint, p2: ScaledPoint, p3: ScaledPoint, p4: ScaledPoint) -> bool {
+    let d1 = orientation(p3, p4, p1);
+    let d2 = orientation(p3, p4, p2);
+    let d3 = orientation(p1, p2, p3);
+    let d4 = orientation(p1, p2, p4);
+    if d1 * d2 < 0 && d3 * d4 < 0 {
+        return true;
+    }
+    (d1 == 0 && on_segment(p3, p4, p1))
+        || (d2 == 0 && on_segment(p3, p4, p2))
+        || (d3 == 0 && on_segment(p1, p2, p3))
+        || (d4 == 0 && on_segment(p1, p2, p4))
+}
@@ -1 +0,0 @@
-use cosmwasm_std::{StdError, StdResult, Uint128};
@@ -1,13 +1,53 @@
 #[cfg(test)]
-
 mod tests {
-    use cosmwasm_std::{Addr, Uint128, testing::{mock_dependencies, mock_env, mock_info}, from_binary};
-    use crate::{contract::{instantiate, execute, query}, msg::{InstantiateMsg, ExecuteMsg, QueryMsg, ConfigResponse, ClaimResponse,OrganizationsResponse,OrganizationResponse, TotalCarbonCreditsResponse, ClaimsResponse}, state::{VoteOption, ClaimStatus}};
-    use cosmwasm_std::coins;
-    use crate::state::ORGANIZATIONS;
-    use crate::contract::add_organization_emission;
+    use cosmwasm_std::{coin, coins, BankMsg};
+    use cw_utils::PaymentError;
+    use cosmwasm_std::{Addr, Decimal, Response, Uint128, testing::{mock_dependencies, mock_env, mock_info}, from_json};
+    use crate::{contract::{instantiate, execute, migrate, query, reply, polygon_hash, vote_commitment, CONTRACT_NAME, CONTRACT_VERSION}, msg::{InstantiateMsg, MigrateMsg, BatchesResponse, CreditTokenSource, EligibilityProof, Cw721ReceiverMsg, NftInfoResponse, ApprovalResponse, OperatorsResponse, ContractInfoResponse, LendOffersResponse, PoolResponse, PoolPositionResponse, ListingsResponse, LoanHealthResponse, LoansResponse, ReputationEventsResponse, SimulateEligibilityResponse, EligibilityProofsResponse, NumTokensResponse, OwnerOfResponse, RetirementsResponse, EmissionsReportsResponse, NetPositionResponse, NetPositionsResponse, TokensResponse, Cw20InstantiateMsg, ReceiveMsg, ExecuteMsg, LendResponse, QueryMsg, ConfigResponse, ClaimResponse,OrganizationsResponse,OrganizationResponse, TotalCarbonCreditsResponse, ClaimsResponse, ChallengesResponse, VerifierResponse, VerifiersResponse}, state::{Challenge, ChallengeStatus, CreditBatch, Listing, Loan, LoanStatus, EmissionEntry, EmissionScope, EmissionsReport, RetirementCertificate, RetiredSerials, VoteOption, VoteWeights, TallyParams, ClaimStatus}};
     use crate::ContractError;
-    use cosmwasm_std::OverflowError;
+    use crate::state::{ORGANIZATIONS, MAX_VERIFIERS, CollateralParams, EligibilityFactors, EligibilityPolicy, EligibilityRecord, LendOffer, PoolParams, ReputationParams, ReputationReason, LentStatus, UserLendRequestsResponse, SECONDS_PER_YEAR};
+    use cosmwasm_std::{to_json_binary, Binary, ContractResult, CosmosMsg, DepsMut, Env, Reply, ReplyOn, SubMsg, SubMsgResponse, SubMsgResult, SystemError, SystemResult, WasmMsg, WasmQuery};
+    use cw20::{AllAllowancesResponse, AllowanceResponse, BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, Expiration};
+    use crate::zk::EligibilityCircuit;
+    use crate::migrations::baseline;
+    use cw2::{get_contract_version, set_contract_version};
+    use ark_bn254::{Bn254, Fr};
+    use ark_groth16::{Groth16, ProvingKey};
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use ark_serialize::CanonicalSerialize;
+
+    // Closed half-degree square; distinct offsets give non-overlapping plots
+    fn square_longitudes(offset: u64) -> Vec<String> {
+        let west = format!("{}.0", offset);
+        let east = format!("{}.5", offset);
+        vec![west.clone(), east.clone(), east, west.clone(), west]
+    }
+
+    fn square_latitudes() -> Vec<String> {
+        ["10.0", "10.0", "10.5", "10.5", "10.0"].iter().map(|s| s.to_string()).collect()
+    }
+
+    fn add_verifiers(mut deps: DepsMut, env: &Env, verifiers: &[&str]) {
+        for verifier in verifiers {
+            let msg = ExecuteMsg::AddVerifier {
+                address: Addr::unchecked(*verifier),
+                name: verifier.to_string(),
+                metadata: "".to_string(),
+                accredited_until: env.block.time.seconds() + 365 * 86400,
+            };
+            execute(deps.branch(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        }
+    }
+
+    fn commit_vote(deps: DepsMut, env: &Env, voter: &str, claim_id: u64, vote: VoteOption) -> Result<Response, ContractError> {
+        let hash = vote_commitment(claim_id, &Addr::unchecked(voter), &vote, "salt");
+        execute(deps, env.clone(), mock_info(voter, &[]), ExecuteMsg::CommitVote { claim_id, hash })
+    }
+
+    fn reveal_vote(deps: DepsMut, env: &Env, voter: &str, claim_id: u64, vote: VoteOption) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::RevealVote { claim_id, vote, salt: "salt".to_string() };
+        execute(deps, env.clone(), mock_info(voter, &[]), msg)
+    }
 
     #[test]
     fn proper_initialization() {
@@ -17,12 +57,13 @@ mod tests {
         
         let msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         
         let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
         assert_eq!(0, res.messages.len());
         let config_query = QueryMsg::GetConfig {};
-        let config_res: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), config_query).unwrap()).unwrap();
+        let config_res: ConfigResponse = from_json(query(deps.as_ref(), mock_env(), config_query).unwrap()).unwrap();
         assert_eq!(config_res.owner, Addr::unchecked("creator"));
         assert_eq!(config_res.voting_period, 86400);
         assert_eq!(config_res.total_carbon_credits, Uint128::zero());
@@ -35,21 +76,23 @@ mod tests {
         let info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
         let create_claim_msg = ExecuteMsg::CreateClaim {
-            longitudes: vec!["123.456".to_string()],
-            latitudes: vec!["78.90".to_string()],
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
             time_started: 1000,
             time_ended: 2000,
             demanded_tokens: Uint128::new(100),
             ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
         };
         
         let res = execute(deps.as_mut(), env.clone(), info, create_claim_msg).unwrap();
         assert_eq!(4, res.attributes.len());
         let claim_query = QueryMsg::GetClaim { id: 0 };
-        let claim_res: ClaimResponse = from_binary(&query(deps.as_ref(), env.clone(), claim_query).unwrap()).unwrap();
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), claim_query).unwrap()).unwrap();
         
         assert_eq!(claim_res.id, 0);
         assert_eq!(claim_res.organization, Addr::unchecked("creator"));
@@ -66,45 +109,39 @@ mod tests {
         let info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
         
         // Create a claim
         let create_claim_msg = ExecuteMsg::CreateClaim {
-            longitudes: vec!["123.456".to_string()],
-            latitudes: vec!["78.90".to_string()],
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
             time_started: 1000,
             time_ended: 2000,
             demanded_tokens: Uint128::new(100),
             ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
         };
         
         execute(deps.as_mut(), env.clone(), info, create_claim_msg).unwrap();
         
         // Cast votes
-        let voter1_info = mock_info("voter1", &[]);
-        let vote1_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::Yes,
-        };
-        execute(deps.as_mut(), env.clone(), voter1_info, vote1_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
         
-        let voter2_info = mock_info("voter2", &[]);
-        let vote2_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::No,
-        };
-        execute(deps.as_mut(), env.clone(), voter2_info, vote2_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter2", 0, VoteOption::No).unwrap();
         
-        let voter3_info = mock_info("voter3", &[]);
-        let vote3_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::Yes,
-        };
-        execute(deps.as_mut(), env.clone(), voter3_info, vote3_msg).unwrap();
-        let claim_query = QueryMsg::GetClaim { id: 0 };
-        let claim_res: ClaimResponse = from_binary(&query(deps.as_ref(), env.clone(), claim_query).unwrap()).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter3", 0, VoteOption::Yes).unwrap();
         env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        reveal_vote(deps.as_mut(), &env, "voter2", 0, VoteOption::No).unwrap();
+        reveal_vote(deps.as_mut(), &env, "voter3", 0, VoteOption::Yes).unwrap();
+        let claim_query = QueryMsg::GetClaim { id: 0 };
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), claim_query).unwrap()).unwrap();
+        // Tallies stay hidden until the reveal period is over
+        assert_eq!(claim_res.yes_votes, Uint128::zero());
+        env.block.time = env.block.time.plus_seconds(86400);
         
         
         let finalize_msg = ExecuteMsg::FinalizeVoting {
@@ -113,27 +150,27 @@ mod tests {
         let finalize_info = mock_info("anyone", &[]);
         execute(deps.as_mut(), env.clone(), finalize_info, finalize_msg).unwrap();
         let claim_query = QueryMsg::GetClaim { id: 0 };
-        let claim_res: ClaimResponse = from_binary(&query(deps.as_ref(), env.clone(), claim_query).unwrap()).unwrap();
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), claim_query).unwrap()).unwrap();
         assert_eq!(claim_res.yes_votes, Uint128::new(2));
         assert_eq!(claim_res.no_votes, Uint128::new(1));
         
         assert_eq!(claim_res.status, ClaimStatus::Approved);
         let total_query = QueryMsg::GetTotalCarbonCredits {};
-        let total_res: TotalCarbonCreditsResponse = from_binary(&query(deps.as_ref(), env.clone(), total_query).unwrap()).unwrap();
+        let total_res: TotalCarbonCreditsResponse = from_json(query(deps.as_ref(), env.clone(), total_query).unwrap()).unwrap();
         
         assert_eq!(total_res.total, Uint128::new(100));
-        let org_query = QueryMsg::GetOrganization { address: Addr::unchecked("creator") };
-        let org_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env.clone(), org_query).unwrap()).unwrap();
+        let org_query = QueryMsg::GetOrganization { address: Addr::unchecked("creator"), at_height: None };
+        let org_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), org_query).unwrap()).unwrap();
         
         assert_eq!(org_res.carbon_credits, Uint128::new(100));
         
-        let voter1_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter1") };
-        let voter1_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env.clone(), voter1_query).unwrap()).unwrap();
+        let voter1_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter1"), at_height: None };
+        let voter1_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), voter1_query).unwrap()).unwrap();
         
         assert_eq!(voter1_res.reputation_score, Uint128::new(1));
         
-        let voter2_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter2") };
-        let voter2_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env.clone(), voter2_query).unwrap()).unwrap();
+        let voter2_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter2"), at_height: None };
+        let voter2_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), voter2_query).unwrap()).unwrap();
         
         assert_eq!(voter2_res.reputation_score, Uint128::zero());
     }
@@ -145,42 +182,50 @@ mod tests {
         let creator_info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), creator_info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter"]);
         let create_claim_msg = ExecuteMsg::CreateClaim {
-            longitudes: vec!["123.456".to_string()],
-            latitudes: vec!["78.90".to_string()],
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
             time_started: 1000,
             time_ended: 2000,
             demanded_tokens: Uint128::new(100),
             ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
         };
         
         execute(deps.as_mut(), env.clone(), creator_info.clone(), create_claim_msg).unwrap();
-        let voter_info = mock_info("voter", &[]);
-        let vote_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::Yes,
-        };
-        execute(deps.as_mut(), env.clone(), voter_info, vote_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter", 0, VoteOption::Yes).unwrap();
         let mut env2 = env.clone();
         env2.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env2, "voter", 0, VoteOption::Yes).unwrap();
+        env2.block.time = env2.block.time.plus_seconds(86400);
         
         let finalize_msg = ExecuteMsg::FinalizeVoting {
             claim_id: 0,
         };
         execute(deps.as_mut(), env2.clone(), creator_info.clone(), finalize_msg).unwrap();
-        let lend_msg = ExecuteMsg::LendTokens {
-            borrower: Addr::unchecked("borrower"),
+        let request_msg = ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("creator"),
             amount: Uint128::new(50),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof: None,
+        };
+        execute(deps.as_mut(), env2.clone(), mock_info("borrower", &[]), request_msg).unwrap();
+        let lend_msg = ExecuteMsg::LendTokens {
+            lend_request_id: 0,
+            response: LendResponse::Accept,
         };
         execute(deps.as_mut(), env2.clone(), creator_info.clone(), lend_msg).unwrap();
-        let creator_query = QueryMsg::GetOrganization { address: Addr::unchecked("creator") };
-        let creator_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env2.clone(), creator_query).unwrap()).unwrap();
+        let creator_query = QueryMsg::GetOrganization { address: Addr::unchecked("creator"), at_height: None };
+        let creator_res: OrganizationResponse = from_json(query(deps.as_ref(), env2.clone(), creator_query).unwrap()).unwrap();
         
         assert_eq!(creator_res.carbon_credits, Uint128::new(50));
-        let borrower_query = QueryMsg::GetOrganization { address: Addr::unchecked("borrower") };
-        let borrower_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env2.clone(), borrower_query).unwrap()).unwrap();
+        let borrower_query = QueryMsg::GetOrganization { address: Addr::unchecked("borrower"), at_height: None };
+        let borrower_res: OrganizationResponse = from_json(query(deps.as_ref(), env2.clone(), borrower_query).unwrap()).unwrap();
         
         assert_eq!(borrower_res.carbon_credits, Uint128::new(50));
         assert_eq!(borrower_res.debt, Uint128::new(50));
@@ -188,17 +233,17 @@ mod tests {
         assert_eq!(borrower_res.total_borrowed, Uint128::new(50));
         let borrower_info = mock_info("borrower", &[]);
         let repay_msg = ExecuteMsg::RepayTokens {
-            lender: Addr::unchecked("creator"),
+            loan_id: 0,
             amount: Uint128::new(30),
         };
         execute(deps.as_mut(), env2.clone(), borrower_info, repay_msg).unwrap();
-        let creator_query = QueryMsg::GetOrganization { address: Addr::unchecked("creator") };
-        let creator_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env2.clone(), creator_query).unwrap()).unwrap();
+        let creator_query = QueryMsg::GetOrganization { address: Addr::unchecked("creator"), at_height: None };
+        let creator_res: OrganizationResponse = from_json(query(deps.as_ref(), env2.clone(), creator_query).unwrap()).unwrap();
         
         assert_eq!(creator_res.carbon_credits, Uint128::new(80));
         
-        let borrower_query = QueryMsg::GetOrganization { address: Addr::unchecked("borrower") };
-        let borrower_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env2.clone(), borrower_query).unwrap()).unwrap();
+        let borrower_query = QueryMsg::GetOrganization { address: Addr::unchecked("borrower"), at_height: None };
+        let borrower_res: OrganizationResponse = from_json(query(deps.as_ref(), env2.clone(), borrower_query).unwrap()).unwrap();
         
         assert_eq!(borrower_res.carbon_credits, Uint128::new(20));
         assert_eq!(borrower_res.debt, Uint128::new(20));
@@ -214,16 +259,19 @@ mod tests {
         let creator_info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             voting_period: 86400, // 1 day
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), creator_info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter"]);
         for i in 0..3 {
             let create_claim_msg = ExecuteMsg::CreateClaim {
-                longitudes: vec![format!("123.{}", i)],
-                latitudes: vec![format!("78.{}", i)],
+                longitudes: square_longitudes(i),
+                latitudes: square_latitudes(),
                 time_started: 1000 + i,
                 time_ended: 2000 + i,
                 demanded_tokens: Uint128::new(100 + i as u128),
                 ipfs_hashes: vec![format!("QmHash{}", i)],
+                methodology: "VM0047".to_string(),
             };
             
             execute(deps.as_mut(), env.clone(), creator_info.clone(), create_claim_msg).unwrap();
@@ -231,7 +279,7 @@ mod tests {
         
         // Query all claims
         let claims_query = QueryMsg::GetClaims { start_after: None, limit: None };
-        let claims_res: ClaimsResponse = from_binary(&query(deps.as_ref(), env.clone(), claims_query).unwrap()).unwrap();
+        let claims_res: ClaimsResponse = from_json(query(deps.as_ref(), env.clone(), claims_query).unwrap()).unwrap();
         
         assert_eq!(claims_res.claims.len(), 3);
         assert_eq!(claims_res.claims[0].id, 0);
@@ -240,18 +288,16 @@ mod tests {
         
         // Query with pagination
         let paginated_query = QueryMsg::GetClaims { start_after: Some(1), limit: Some(1) };
-        let paginated_res: ClaimsResponse = from_binary(&query(deps.as_ref(), env.clone(), paginated_query).unwrap()).unwrap();
+        let paginated_res: ClaimsResponse = from_json(query(deps.as_ref(), env.clone(), paginated_query).unwrap()).unwrap();
         assert_eq!(paginated_res.claims.len(), 1);
         assert_eq!(paginated_res.claims[0].id, 2);
         // Approve one claim
-        let vote_msg = ExecuteMsg::CastVote {
-            claim_id: 1,
-            vote: VoteOption::Yes,
-        };
-        execute(deps.as_mut(), env.clone(), creator_info.clone(), vote_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter", 1, VoteOption::Yes).unwrap();
         
         let mut env2 = env.clone();
         env2.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env2, "voter", 1, VoteOption::Yes).unwrap();
+        env2.block.time = env2.block.time.plus_seconds(86400);
         
         let finalize_msg = ExecuteMsg::FinalizeVoting {
             claim_id: 1,
@@ -260,14 +306,14 @@ mod tests {
         
         // Query by status
         let status_query = QueryMsg::GetClaimsByStatus { status: ClaimStatus::Approved, start_after: None, limit: None };
-        let status_res: ClaimsResponse = from_binary(&query(deps.as_ref(), env2.clone(), status_query).unwrap()).unwrap();
+        let status_res: ClaimsResponse = from_json(query(deps.as_ref(), env2.clone(), status_query).unwrap()).unwrap();
         
         assert_eq!(status_res.claims.len(), 1);
         assert_eq!(status_res.claims[0].id, 1);
         assert_eq!(status_res.claims[0].status, ClaimStatus::Approved);
         
         let active_query = QueryMsg::GetClaimsByStatus { status: ClaimStatus::Active, start_after: None, limit: None };
-        let active_res: ClaimsResponse = from_binary(&query(deps.as_ref(), env2, active_query).unwrap()).unwrap();
+        let active_res: ClaimsResponse = from_json(query(deps.as_ref(), env2, active_query).unwrap()).unwrap();
         
         assert_eq!(active_res.claims.len(), 2);
         assert!(active_res.claims.iter().all(|c| c.status == ClaimStatus::Active));
@@ -281,28 +327,22 @@ mod tests {
 
         let msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1"]);
         let create_claim_msg = ExecuteMsg::CreateClaim {
-            longitudes: vec!["123.456".to_string()],
-            latitudes: vec!["78.90".to_string()],
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
             time_started: 1000,
             time_ended: 2000,
             demanded_tokens: Uint128::new(100),
             ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
         };
         execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
-        let voter_info = mock_info("voter1", &[]);
-        let vote_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::Yes,
-        };
-        execute(deps.as_mut(), env.clone(), voter_info.clone(), vote_msg).unwrap();
-        let vote_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::Yes,
-        };
-        let res = execute(deps.as_mut(), env.clone(), voter_info, vote_msg);
+        commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        let res = commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::No);
         assert!(res.is_err()); 
     }
 
@@ -313,15 +353,17 @@ mod tests {
         let info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
         let create_claim_msg = ExecuteMsg::CreateClaim {
-            longitudes: vec!["123.456".to_string()],
-            latitudes: vec!["78.90".to_string()],
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
             time_started: 1000,
             time_ended: 2000,
             demanded_tokens: Uint128::new(100),
             ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
         };
         execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
         let finalize_msg = ExecuteMsg::FinalizeVoting { claim_id: 0 };
@@ -336,41 +378,40 @@ mod tests {
         let info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             voting_period: 86400, // 1 day
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
         let create_claim_msg = ExecuteMsg::CreateClaim {
-            longitudes: vec!["123.456".to_string()],
-            latitudes: vec!["78.90".to_string()],
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
             time_started: 1000,
             time_ended: 2000,
             demanded_tokens: Uint128::new(100),
             ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
         };
         execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
 
         // Cast votes
-        let voter1_info = mock_info("voter1", &[]);
-        let vote1_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::Yes,
-        };
-        execute(deps.as_mut(), env.clone(), voter1_info.clone(), vote1_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
 
-        let voter2_info = mock_info("voter2", &[]);
-        let vote2_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::No,
-        };
-        execute(deps.as_mut(), env.clone(), voter2_info.clone(), vote2_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter2", 0, VoteOption::No).unwrap();
+
+        commit_vote(deps.as_mut(), &env, "voter3", 0, VoteOption::Yes).unwrap();
         env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        reveal_vote(deps.as_mut(), &env, "voter2", 0, VoteOption::No).unwrap();
+        reveal_vote(deps.as_mut(), &env, "voter3", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
         let finalize_msg = ExecuteMsg::FinalizeVoting { claim_id: 0 };
         execute(deps.as_mut(), env.clone(), info.clone(), finalize_msg).unwrap();
-        let voter1_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter1") };
-        let voter1_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env.clone(), voter1_query).unwrap()).unwrap();
+        let voter1_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter1"), at_height: None };
+        let voter1_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), voter1_query).unwrap()).unwrap();
         assert_eq!(voter1_res.reputation_score, Uint128::new(1)); // Voted correctly
 
-        let voter2_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter2") };
-        let voter2_res: OrganizationResponse = from_binary(&query(deps.as_ref(), env.clone(), voter2_query).unwrap()).unwrap();
+        let voter2_query = QueryMsg::GetOrganization { address: Addr::unchecked("voter2"), at_height: None };
+        let voter2_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), voter2_query).unwrap()).unwrap();
         assert_eq!(voter2_res.reputation_score, Uint128::zero()); // Voted incorrectly
     }
 
@@ -381,37 +422,45 @@ mod tests {
         let info = mock_info("creator", &[]);
         let msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter"]);
         let create_claim_msg = ExecuteMsg::CreateClaim {
-            longitudes: vec!["123.456".to_string()],
-            latitudes: vec!["78.90".to_string()],
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
             time_started: 1000,
             time_ended: 2000,
             demanded_tokens: Uint128::new(100),
             ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
         };
         execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
-        let voter_info = mock_info("voter", &[]);
-        let vote_msg = ExecuteMsg::CastVote {
-            claim_id: 0,
-            vote: VoteOption::Yes,
-        };
-        execute(deps.as_mut(), env.clone(), voter_info, vote_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter", 0, VoteOption::Yes).unwrap();
         let mut env2 = env.clone();
         env2.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env2, "voter", 0, VoteOption::Yes).unwrap();
+        env2.block.time = env2.block.time.plus_seconds(86400);
 
         let finalize_msg = ExecuteMsg::FinalizeVoting { claim_id: 0 };
         execute(deps.as_mut(), env2.clone(), info.clone(), finalize_msg).unwrap();
-        let lend_msg = ExecuteMsg::LendTokens {
-            borrower: Addr::unchecked("borrower"),
+        let request_msg = ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("creator"),
             amount: Uint128::new(150),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof: None,
+        };
+        execute(deps.as_mut(), env2.clone(), mock_info("borrower", &[]), request_msg).unwrap();
+        let lend_msg = ExecuteMsg::LendTokens {
+            lend_request_id: 0,
+            response: LendResponse::Accept,
         };
         let res = execute(deps.as_mut(), env2.clone(), info.clone(), lend_msg);
         assert!(res.is_err()); 
         let borrower_info = mock_info("borrower", &[]);
         let repay_msg = ExecuteMsg::RepayTokens {
-            lender: Addr::unchecked("creator"),
+            loan_id: 0,
             amount: Uint128::new(150),
         };
         let res = execute(deps.as_mut(), env2.clone(), borrower_info, repay_msg);
@@ -422,14 +471,16 @@ mod tests {
         let mut deps = mock_dependencies();
         let instantiate_msg = InstantiateMsg {
             voting_period: 86400,
+            ..Default::default()
         };
         let info = mock_info("creator", &coins(1000, "earth"));
         let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), instantiate_msg).unwrap();
         let query_msg = QueryMsg::GetOrganization {
             address: Addr::unchecked("creator"),
+            at_height: None,
         };
         let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
-        let org_response: OrganizationResponse = from_binary(&res).unwrap();
+        let org_response: OrganizationResponse = from_json(res).unwrap();
         assert_eq!(org_response.address, Addr::unchecked("creator"));
         assert_eq!(org_response.reputation_score, Uint128::zero());
         assert_eq!(org_response.carbon_credits, Uint128::zero());
@@ -443,7 +494,7 @@ mod tests {
             limit: None,
         };
         let res = query(deps.as_ref(), mock_env(), query_all_msg).unwrap();
-        let orgs_response: OrganizationsResponse = from_binary(&res).unwrap();
+        let orgs_response: OrganizationsResponse = from_json(res).unwrap();
         assert_eq!(orgs_response.organizations.len(), 1);
         assert_eq!(orgs_response.organizations[0].address, Addr::unchecked("creator"));
         assert_eq!(orgs_response.organizations[0].name, "Green Earth Foundation");
@@ -455,6 +506,7 @@ mod tests {
         let mut deps = mock_dependencies();
         let instantiate_msg = InstantiateMsg {
             voting_period: 86400, 
+            ..Default::default()
         };
         let info = mock_info("creator", &coins(1000, "earth"));
         let _res = instantiate(deps.as_mut(), mock_env(), info, instantiate_msg).unwrap();
@@ -463,8 +515,8 @@ mod tests {
             name: "Creator Org".to_string(),
         };
         let _res = execute(deps.as_mut(), mock_env(), info, update_name_msg).unwrap();
-        let org_addresses = vec!["org1", "org2", "org3", "org4", "org5"];
-        let org_names = vec!["First Org", "Second Org", "Third Org", "Fourth Org", "Fifth Org"];
+        let org_addresses = ["org1", "org2", "org3", "org4", "org5"];
+        let org_names = ["First Org", "Second Org", "Third Org", "Fourth Org", "Fifth Org"];
         
         for i in 0..5 {
             let info = mock_info(org_addresses[i], &coins(1000, "earth"));
@@ -478,14 +530,14 @@ mod tests {
             limit: None,
         };
         let res = query(deps.as_ref(), mock_env(), query_all_msg).unwrap();
-        let orgs_response: OrganizationsResponse = from_binary(&res).unwrap();
+        let orgs_response: OrganizationsResponse = from_json(res).unwrap();
         assert_eq!(orgs_response.organizations.len(), 6);
         let query_page1 = QueryMsg::GetAllOrganizations {
             start_after: None,
             limit: Some(2),
         };
         let res = query(deps.as_ref(), mock_env(), query_page1).unwrap();
-        let page1: OrganizationsResponse = from_binary(&res).unwrap();
+        let page1: OrganizationsResponse = from_json(res).unwrap();
         
         assert_eq!(page1.organizations.len(), 2);
         assert_eq!(page1.organizations[0].address, Addr::unchecked("creator"));
@@ -495,7 +547,7 @@ mod tests {
             limit: Some(2),
         };
         let res = query(deps.as_ref(), mock_env(), query_page2).unwrap();
-        let page2: OrganizationsResponse = from_binary(&res).unwrap();
+        let page2: OrganizationsResponse = from_json(res).unwrap();
         
         assert_eq!(page2.organizations.len(), 2);
         assert_eq!(page2.organizations[0].address, Addr::unchecked("org2"));
@@ -505,10 +557,2457 @@ mod tests {
             limit: Some(2),
         };
         let res = query(deps.as_ref(), mock_env(), query_page3).unwrap();
-        let page3: OrganizationsResponse = from_binary(&res).unwrap();
+        let page3: OrganizationsResponse = from_json(res).unwrap();
         
         assert_eq!(page3.organizations.len(), 2);
         assert_eq!(page3.organizations[0].address, Addr::unchecked("org4"));
         assert_eq!(page3.organizations[1].address, Addr::unchecked("org5"));
     }   
-}
\ No newline at end of file
+
+    #[test]
+    fn credit_weighted_voting() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            vote_weights: Some(VoteWeights {
+                base_weight: Uint128::new(1),
+                reputation_weight: Uint128::zero(),
+                credit_weight: Uint128::new(1),
+            }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["creator", "voter", "verifier1", "verifier2"]);
+
+        // Creator earns 100 credits through an approved claim
+        let create_claim_msg = ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "voter", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+        env.block.height += 1;
+
+        // A second claim is opposed by the creator and supported by two verifiers without credits
+        let create_claim_msg = ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(1),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(500),
+            ipfs_hashes: vec!["QmHash2".to_string()],
+            methodology: "VM0047".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("other", &[]), create_claim_msg).unwrap();
+        let res = commit_vote(deps.as_mut(), &env, "creator", 1, VoteOption::No).unwrap();
+        assert!(res.attributes.iter().any(|attr| attr.key == "weight" && attr.value == "101"));
+        for verifier in ["verifier1", "verifier2"] {
+            commit_vote(deps.as_mut(), &env, verifier, 1, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "creator", 1, VoteOption::No).unwrap();
+        for verifier in ["verifier1", "verifier2"] {
+            reveal_vote(deps.as_mut(), &env, verifier, 1, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::FinalizeVoting { claim_id: 1 }).unwrap();
+
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetClaim { id: 1 }).unwrap()).unwrap();
+        assert_eq!(claim_res.yes_votes, Uint128::new(2));
+        assert_eq!(claim_res.no_votes, Uint128::new(101));
+        assert_eq!(claim_res.status, ClaimStatus::Rejected);
+    }
+
+    #[test]
+    fn voters_without_power_are_rejected() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1"]);
+
+        // Only the owner may change the weights
+        let update_msg = ExecuteMsg::UpdateConfig {
+            voting_period: None,
+            reveal_period: None,
+            unrevealed_penalty: None,
+            challenge_period: None,
+            challenge_slash: None,
+            vote_weights: Some(VoteWeights {
+                base_weight: Uint128::zero(),
+                reputation_weight: Uint128::new(1),
+                credit_weight: Uint128::zero(),
+            }),
+            tally_params: None,
+            governance: None,
+            default_penalty: None,
+            reborrow_reputation: None,
+            collateral: None,
+            pool: None,
+            lend_request_expiry: None,
+            reputation: None,
+            proof_validity: None,
+            require_eligibility_proof: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), update_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), info.clone(), update_msg).unwrap();
+
+        let create_claim_msg = ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), info, create_claim_msg).unwrap();
+        let err = commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::NoVotingPower {});
+    }
+    #[test]
+    fn claim_without_quorum_expires() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let create_claim_msg = ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400 * 2 + 1);
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: 0 }).unwrap()).unwrap();
+        assert_eq!(claim_res.status, ClaimStatus::Expired);
+        let total_res: TotalCarbonCreditsResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetTotalCarbonCredits {}).unwrap()).unwrap();
+        assert_eq!(total_res.total, Uint128::zero());
+    }
+
+    #[test]
+    fn tally_thresholds_and_veto() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            tally_params: Some(TallyParams {
+                quorum: Uint128::new(2),
+                threshold: Decimal::percent(50),
+                veto_threshold: Decimal::percent(30),
+            }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+
+        let invalid_msg = ExecuteMsg::UpdateConfig {
+            voting_period: None,
+            reveal_period: None,
+            unrevealed_penalty: None,
+            challenge_period: None,
+            challenge_slash: None,
+            vote_weights: None,
+            tally_params: Some(TallyParams {
+                quorum: Uint128::new(2),
+                threshold: Decimal::percent(150),
+                veto_threshold: Decimal::percent(30),
+            }),
+            governance: None,
+            default_penalty: None,
+            reborrow_reputation: None,
+            collateral: None,
+            pool: None,
+            lend_request_expiry: None,
+            reputation: None,
+            proof_validity: None,
+            require_eligibility_proof: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), invalid_msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidTallyParams {});
+
+        // Claim 0 ends in a tie, claim 1 passes the threshold but is vetoed by a 1/3 minority
+        let ballots = [
+            vec![("voter1", VoteOption::Yes), ("voter2", VoteOption::No)],
+            vec![("voter1", VoteOption::Yes), ("voter2", VoteOption::Yes), ("voter3", VoteOption::No)],
+        ];
+        for (claim_id, ballot) in ballots.iter().enumerate() {
+            let create_claim_msg = ExecuteMsg::CreateClaim {
+                longitudes: square_longitudes(claim_id as u64),
+                latitudes: square_latitudes(),
+                time_started: 1000,
+                time_ended: 2000,
+                demanded_tokens: Uint128::new(100),
+                ipfs_hashes: vec!["QmHash1".to_string()],
+                methodology: "VM0047".to_string(),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
+            for (voter, vote) in ballot {
+                commit_vote(deps.as_mut(), &env, voter, claim_id as u64, vote.clone()).unwrap();
+            }
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for (claim_id, ballot) in ballots.iter().enumerate() {
+            for (voter, vote) in ballot {
+                reveal_vote(deps.as_mut(), &env, voter, claim_id as u64, vote.clone()).unwrap();
+            }
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        for claim_id in 0..2 {
+            execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::FinalizeVoting { claim_id }).unwrap();
+            let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: claim_id }).unwrap()).unwrap();
+            assert_eq!(claim_res.status, ClaimStatus::Rejected);
+        }
+    }
+    #[test]
+    fn only_accredited_verifiers_vote() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            governance: Some(Addr::unchecked("dao")),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let add_msg = ExecuteMsg::AddVerifier {
+            address: Addr::unchecked("auditor"),
+            name: "Auditor".to_string(),
+            metadata: "ipfs://accreditation".to_string(),
+            accredited_until: env.block.time.seconds() + 86400 * 3,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("stranger", &[]), add_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("dao", &[]), add_msg).unwrap();
+        let short_msg = ExecuteMsg::AddVerifier {
+            address: Addr::unchecked("lapsed"),
+            name: "Lapsed".to_string(),
+            metadata: "".to_string(),
+            accredited_until: env.block.time.seconds() + 10,
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), short_msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["creator"]);
+
+        for claim in 0..2 {
+            let create_claim_msg = ExecuteMsg::CreateClaim {
+                longitudes: square_longitudes(claim),
+                latitudes: square_latitudes(),
+                time_started: 1000 + claim,
+                time_ended: 2000,
+                demanded_tokens: Uint128::new(100),
+                ipfs_hashes: vec!["QmHash1".to_string()],
+                methodology: "VM0047".to_string(),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
+        }
+        let err = commit_vote(deps.as_mut(), &env, "stranger", 0, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::NotVerifier {});
+        let err = commit_vote(deps.as_mut(), &env, "creator", 0, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::OwnClaim {});
+        commit_vote(deps.as_mut(), &env, "auditor", 0, VoteOption::Yes).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(11);
+        let err = commit_vote(deps.as_mut(), &env, "lapsed", 0, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::AccreditationExpired {});
+
+        // The auditor keeps a track record of decided claims
+        commit_vote(deps.as_mut(), &env, "auditor", 1, VoteOption::No).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "auditor", 0, VoteOption::Yes).unwrap();
+        reveal_vote(deps.as_mut(), &env, "auditor", 1, VoteOption::No).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        for claim_id in 0..2 {
+            execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::FinalizeVoting { claim_id }).unwrap();
+        }
+        let verifier_res: VerifierResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetVerifier { address: Addr::unchecked("auditor") }).unwrap()).unwrap();
+        assert_eq!(verifier_res.verifier.votes_cast, 2);
+        assert_eq!(verifier_res.verifier.correct_votes, 2);
+        assert!(verifier_res.accredited);
+
+        let verifiers_res: VerifiersResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetVerifiers { start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(verifiers_res.verifiers.len(), 3);
+        assert!(!verifiers_res.verifiers.iter().find(|v| v.verifier.address == "lapsed").unwrap().accredited);
+
+        let remove_msg = ExecuteMsg::RemoveVerifier { address: Addr::unchecked("lapsed") };
+        execute(deps.as_mut(), env.clone(), mock_info("dao", &[]), remove_msg.clone()).unwrap();
+        let err = execute(deps.as_mut(), env, mock_info("dao", &[]), remove_msg).unwrap_err();
+        assert_eq!(err, ContractError::VerifierNotFound {});
+    }
+    #[test]
+    fn commit_reveal_voting() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+
+        for claim in 0..2 {
+            let create_claim_msg = ExecuteMsg::CreateClaim {
+                longitudes: square_longitudes(claim),
+                latitudes: square_latitudes(),
+                time_started: 1000 + claim,
+                time_ended: 2000,
+                demanded_tokens: Uint128::new(100),
+                ipfs_hashes: vec!["QmHash1".to_string()],
+                methodology: "VM0047".to_string(),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
+        }
+
+        // Claim 0 earns voter2 some reputation to lose later
+        commit_vote(deps.as_mut(), &env, "voter2", 0, VoteOption::Yes).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter1", 1, VoteOption::Yes).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter2", 1, VoteOption::No).unwrap();
+        let err = reveal_vote(deps.as_mut(), &env, "voter1", 1, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::RevealNotStarted {});
+
+        env.block.time = env.block.time.plus_seconds(86401);
+        let err = commit_vote(deps.as_mut(), &env, "voter3", 1, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::VotingEnded {});
+        let err = reveal_vote(deps.as_mut(), &env, "voter3", 1, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::CommitNotFound {});
+        let err = reveal_vote(deps.as_mut(), &env, "voter1", 1, VoteOption::No).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCommitment {});
+        reveal_vote(deps.as_mut(), &env, "voter2", 0, VoteOption::Yes).unwrap();
+        reveal_vote(deps.as_mut(), &env, "voter1", 1, VoteOption::Yes).unwrap();
+        let err = reveal_vote(deps.as_mut(), &env, "voter1", 1, VoteOption::Yes).unwrap_err();
+        assert_eq!(err, ContractError::AlreadyVoted {});
+
+        let finalize_msg = ExecuteMsg::FinalizeVoting { claim_id: 0 };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), finalize_msg).unwrap_err();
+        assert_eq!(err, ContractError::VotingNotEnded {});
+
+        env.block.time = env.block.time.plus_seconds(86400);
+        let err = reveal_vote(deps.as_mut(), &env, "voter2", 1, VoteOption::No).unwrap_err();
+        assert_eq!(err, ContractError::RevealEnded {});
+        execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+        let org_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("voter2"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org_res.reputation_score, Uint128::new(1));
+
+        // voter2 never revealed: the claim is decided by voter1 alone and voter2 is penalised
+        execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::FinalizeVoting { claim_id: 1 }).unwrap();
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: 1 }).unwrap()).unwrap();
+        assert_eq!(claim_res.status, ClaimStatus::Approved);
+        assert_eq!(claim_res.yes_votes, Uint128::new(1));
+        assert_eq!(claim_res.no_votes, Uint128::zero());
+        let org_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("voter2"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org_res.reputation_score, Uint128::zero());
+
+        let err = execute(deps.as_mut(), env, info, ExecuteMsg::FinalizeVoting { claim_id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::ClaimNotActive {});
+    }
+    #[test]
+    fn upheld_challenge_claws_back_credits() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3", "voter4"]);
+
+        for claim in 0..2 {
+            let create_claim_msg = ExecuteMsg::CreateClaim {
+                longitudes: square_longitudes(claim),
+                latitudes: square_latitudes(),
+                time_started: 1000 + claim,
+                time_ended: 2000,
+                demanded_tokens: Uint128::new(100),
+                ipfs_hashes: vec!["QmHash1".to_string()],
+                methodology: "VM0047".to_string(),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
+            commit_vote(deps.as_mut(), &env, "voter1", claim, VoteOption::Yes).unwrap();
+            commit_vote(deps.as_mut(), &env, "voter2", claim, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for claim in 0..2 {
+            reveal_vote(deps.as_mut(), &env, "voter1", claim, VoteOption::Yes).unwrap();
+            reveal_vote(deps.as_mut(), &env, "voter2", claim, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        for claim_id in 0..2 {
+            execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::FinalizeVoting { claim_id }).unwrap();
+        }
+
+        // The creator spends 150 of its 200 credits before claim 0 is challenged
+        let request_msg = ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("creator"),
+            amount: Uint128::new(150),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request_msg).unwrap();
+        let lend_msg = ExecuteMsg::LendTokens {
+            lend_request_id: 0,
+            response: LendResponse::Accept,
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), lend_msg).unwrap();
+
+        let challenge_msg = ExecuteMsg::ChallengeClaim { claim_id: 0, evidence: "QmEvidence".to_string() };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), challenge_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::NotVerifier {});
+        execute(deps.as_mut(), env.clone(), mock_info("voter3", &[]), challenge_msg.clone()).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("voter4", &[]), challenge_msg).unwrap_err();
+        assert_eq!(err, ContractError::AlreadyChallenged {});
+
+        let vote_msg = ExecuteMsg::VoteOnChallenge { challenge_id: 0, vote: VoteOption::Yes };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("voter3", &[]), vote_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::OwnClaim {});
+        execute(deps.as_mut(), env.clone(), mock_info("voter4", &[]), vote_msg).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::ResolveChallenge { challenge_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::VotingNotEnded {});
+
+        env.block.time = env.block.time.plus_seconds(86401);
+        let challenge_msg = ExecuteMsg::ChallengeClaim { claim_id: 1, evidence: "QmLate".to_string() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("voter3", &[]), challenge_msg).unwrap_err();
+        assert_eq!(err, ContractError::ChallengePeriodEnded {});
+        execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::ResolveChallenge { challenge_id: 0 }).unwrap();
+
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: 0 }).unwrap()).unwrap();
+        assert_eq!(claim_res.status, ClaimStatus::Revoked);
+        assert_eq!(claim_res.challenge_id, Some(0));
+        let creator_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("creator"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(creator_res.carbon_credits, Uint128::zero());
+        assert_eq!(creator_res.debt, Uint128::new(50));
+        let total_res: TotalCarbonCreditsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetTotalCarbonCredits {}).unwrap()).unwrap();
+        assert_eq!(total_res.total, Uint128::new(150));
+        // Both yes voters earned 2 reputation and lose 1
+        for voter in ["voter1", "voter2"] {
+            let voter_res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked(voter), at_height: None }).unwrap()).unwrap();
+            assert_eq!(voter_res.reputation_score, Uint128::new(1));
+        }
+        let challenges_res: ChallengesResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetChallenges { start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(challenges_res.challenges.len(), 1);
+        assert_eq!(challenges_res.challenges[0].status, ChallengeStatus::Upheld);
+        assert_eq!(challenges_res.challenges[0].evidence, "QmEvidence");
+        // The revoked claim's batch went to the borrower with the loan and stays there, while the
+        // creator's remaining serials of claim 1 go with the credits clawed back
+        let batches_res: BatchesResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatchesByClaim { claim_id: 0, start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(batches_res.batches.iter().map(|b| (b.owner.as_str(), b.amount())).collect::<Vec<_>>(), vec![("borrower", Uint128::new(100))]);
+        let batches_res: BatchesResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatchesByClaim { claim_id: 1, start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(batches_res.batches.iter().map(|b| (b.owner.as_str(), b.amount())).collect::<Vec<_>>(), vec![("borrower", Uint128::new(50))]);
+        let count: NumTokensResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::NumTokens {}).unwrap()).unwrap();
+        assert_eq!(count.count, 2);
+
+        let err = execute(deps.as_mut(), env, info, ExecuteMsg::ResolveChallenge { challenge_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::ChallengeNotOpen {});
+    }
+
+    #[test]
+    fn dismissed_challenge_keeps_claim_approved() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("creator", &[]);
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        let create_claim_msg = ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), create_claim_msg).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+
+        let challenge_msg = ExecuteMsg::ChallengeClaim { claim_id: 0, evidence: "QmEvidence".to_string() };
+        execute(deps.as_mut(), env.clone(), mock_info("voter2", &[]), challenge_msg).unwrap();
+        let vote_msg = ExecuteMsg::VoteOnChallenge { challenge_id: 0, vote: VoteOption::No };
+        execute(deps.as_mut(), env.clone(), mock_info("voter3", &[]), vote_msg).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        execute(deps.as_mut(), env.clone(), info, ExecuteMsg::ResolveChallenge { challenge_id: 0 }).unwrap();
+
+        let claim_res: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: 0 }).unwrap()).unwrap();
+        assert_eq!(claim_res.status, ClaimStatus::Approved);
+        let challenge: Challenge = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetChallenge { id: 0 }).unwrap()).unwrap();
+        assert_eq!(challenge.status, ChallengeStatus::Dismissed);
+        let total_res: TotalCarbonCreditsResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetTotalCarbonCredits {}).unwrap()).unwrap();
+        assert_eq!(total_res.total, Uint128::new(100));
+    }
+
+    fn create_plot(deps: DepsMut, env: &Env, sender: &str, longitudes: &[&str], latitudes: &[&str], period: (u64, u64)) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::CreateClaim {
+            longitudes: longitudes.iter().map(|s| s.to_string()).collect(),
+            latitudes: latitudes.iter().map(|s| s.to_string()).collect(),
+            time_started: period.0,
+            time_ended: period.1,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
+        };
+        execute(deps, env.clone(), mock_info(sender, &[]), msg)
+    }
+
+    #[test]
+    fn claim_polygons_are_validated() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        let lats = ["10.0", "10.0", "10.5", "10.5", "10.0"];
+
+        let err = create_plot(deps.as_mut(), &env, "org", &["0.0", "0.5", "0.5", "0.0"], &lats, (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::MismatchedCoordinates {});
+        let err = create_plot(deps.as_mut(), &env, "org", &["0.0", "0.5", "0.5", "0.0", "0.1"], &lats, (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::PolygonNotClosed {});
+        let err = create_plot(deps.as_mut(), &env, "org", &["0.0", "180.5", "180.5", "0.0", "0.0"], &lats, (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCoordinate { value: "180.5".to_string() });
+        let err = create_plot(deps.as_mut(), &env, "org", &["0.0", "0.5", "0.5", "0.0", "0.0"], &["10.0", "10.0", "-90.1", "10.5", "10.0"], (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCoordinate { value: "-90.1".to_string() });
+        let err = create_plot(deps.as_mut(), &env, "org", &["0.0", "abc", "0.5", "0.0", "0.0"], &lats, (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCoordinate { value: "abc".to_string() });
+        let err = create_plot(deps.as_mut(), &env, "org", &["0.0", "0.5", "1.0", "0.0"], &["10.0", "10.0", "10.0", "10.0"], (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPolygon { reason: "zero area".to_string() });
+        // Bow-tie: edges 0-1 and 2-3 cross
+        let err = create_plot(deps.as_mut(), &env, "org", &["0.0", "0.5", "0.0", "0.3", "0.0"], &lats, (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::SelfIntersectingPolygon {});
+        let err = create_plot(deps.as_mut(), &env, "org", &square_longitudes(0).iter().map(|s| s.as_str()).collect::<Vec<_>>(), &lats, (2000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidClaimPeriod {});
+
+        // Half-degree square: 500_000^2 square microdegrees
+        create_plot(deps.as_mut(), &env, "org", &["-0.25", "0.25", "0.25", "-0.25", "-0.25"], &lats, (1000, 2000)).unwrap();
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: 0 }).unwrap();
+        let claim: ClaimResponse = from_json(res).unwrap();
+        assert_eq!(claim.area, Uint128::new(250_000_000_000));
+    }
+
+    #[test]
+    fn overlapping_claims_are_rejected() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        let lons = ["0.0", "1.0", "1.0", "0.0", "0.0"];
+        let lats = ["10.0", "10.0", "11.0", "11.0", "10.0"];
+        create_plot(deps.as_mut(), &env, "org1", &lons, &lats, (1000, 2000)).unwrap();
+
+        // Same plot, a plot inside it, and a plot crossing its edge all overlap
+        let err = create_plot(deps.as_mut(), &env, "org2", &lons, &lats, (1500, 2500)).unwrap_err();
+        assert_eq!(err, ContractError::OverlappingClaim { claim_id: 0 });
+        let err = create_plot(deps.as_mut(), &env, "org2", &["0.2", "0.4", "0.4", "0.2", "0.2"], &["10.2", "10.2", "10.4", "10.4", "10.2"], (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::OverlappingClaim { claim_id: 0 });
+        let err = create_plot(deps.as_mut(), &env, "org2", &["0.5", "1.5", "1.5", "0.5", "0.5"], &["10.5", "10.5", "11.5", "11.5", "10.5"], (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::OverlappingClaim { claim_id: 0 });
+        // Triangle with every vertex on the plot's boundary
+        let err = create_plot(deps.as_mut(), &env, "org2", &["0.0", "1.0", "0.0", "0.0"], &["10.0", "10.0", "11.0", "10.0"], (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::OverlappingClaim { claim_id: 0 });
+
+        // Neighbouring plots sharing an edge, or the same plot in a later period, are fine
+        create_plot(deps.as_mut(), &env, "org2", &["1.0", "2.0", "2.0", "1.0", "1.0"], &lats, (1000, 2000)).unwrap();
+        create_plot(deps.as_mut(), &env, "org3", &lons, &lats, (2000, 3000)).unwrap();
+
+        // Once claim 0 is rejected its area can be claimed again
+        for voter in ["voter1", "voter2", "voter3"] {
+            commit_vote(deps.as_mut(), &env, voter, 0, VoteOption::No).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for voter in ["voter1", "voter2", "voter3"] {
+            reveal_vote(deps.as_mut(), &env, voter, 0, VoteOption::No).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+        create_plot(deps.as_mut(), &env, "org2", &lons, &lats, (1500, 1800)).unwrap();
+
+        // Claims are indexed by grid cell, which bounds both their size and how small they may be
+        let err = create_plot(deps.as_mut(), &env, "org4", &["20.0", "23.0", "23.0", "20.0", "20.0"], &lats, (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPolygon { reason: "spans more than 64 grid cells".to_string() });
+        let err = create_plot(deps.as_mut(), &env, "org4", &["30.0", "30.001", "30.001", "30.0", "30.0"], &["10.0", "10.0", "10.01", "10.01", "10.0"], (1000, 2000)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPolygon { reason: "covers less than 61027344 square microdegrees".to_string() });
+
+        // A cell full of claims from earlier periods still takes new ones
+        let strip = |i: usize| -> (Vec<String>, Vec<String>) {
+            let (west, east) = (format!("30.{:03}", i), format!("30.{:03}", i + 1));
+            (vec![west.clone(), east.clone(), east, west.clone(), west], vec!["10.0".into(), "10.0".into(), "10.1".into(), "10.1".into(), "10.0".into()])
+        };
+        for i in 0..40 {
+            let (lons, lats) = strip(i);
+            let (lons, lats): (Vec<&str>, Vec<&str>) = (lons.iter().map(String::as_str).collect(), lats.iter().map(String::as_str).collect());
+            let start = 1000 * i as u64;
+            create_plot(deps.as_mut(), &env, "org4", &lons, &lats, (start, start + 1000)).unwrap();
+        }
+        let (lons, lats) = strip(40);
+        let (lons, lats): (Vec<&str>, Vec<&str>) = (lons.iter().map(String::as_str).collect(), lats.iter().map(String::as_str).collect());
+        create_plot(deps.as_mut(), &env, "org4", &lons, &lats, (40_000, 41_000)).unwrap();
+        let err = create_plot(deps.as_mut(), &env, "org4", &["30.0", "30.1", "30.1", "30.0", "30.0"], &["10.0", "10.0", "10.1", "10.1", "10.0"], (39_500, 40_500)).unwrap_err();
+        assert_eq!(err, ContractError::OverlappingClaim { claim_id: 43 });
+    }
+
+    /// Answers the credit token's balance queries, and its allowance queries for allowances to the contract
+    fn mock_credit_token(deps: &mut cosmwasm_std::OwnedDeps<cosmwasm_std::MemoryStorage, cosmwasm_std::testing::MockApi, cosmwasm_std::testing::MockQuerier>, balances: &[(&str, u128)], allowances: &[(&str, u128)]) {
+        let owned = |entries: &[(&str, u128)]| -> Vec<(String, Uint128)> {
+            entries.iter().map(|(address, amount)| (address.to_string(), Uint128::new(*amount))).collect()
+        };
+        let (balances, allowances) = (owned(balances), owned(allowances));
+        deps.querier.update_wasm(move |query| {
+            let msg = match query {
+                WasmQuery::Smart { contract_addr, msg } if contract_addr == "credit_token" => msg,
+                _ => return SystemResult::Err(SystemError::UnsupportedRequest { kind: "wasm".to_string() }),
+            };
+            let amount_of = |entries: &[(String, Uint128)], address: &str| {
+                entries.iter().find(|(entry, _)| entry == address).map(|(_, amount)| *amount).unwrap_or_default()
+            };
+            let res = match from_json(msg).unwrap() {
+                Cw20QueryMsg::Balance { address } => to_json_binary(&BalanceResponse { balance: amount_of(&balances, &address) }),
+                Cw20QueryMsg::Allowance { owner, .. } => to_json_binary(&AllowanceResponse { allowance: amount_of(&allowances, &owner), expires: Expiration::Never {} }),
+                other => panic!("unexpected query {:?}", other),
+            };
+            SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+        });
+    }
+
+    fn cw20_transfer(msg: &SubMsg) -> Cw20ExecuteMsg {
+        match &msg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) => {
+                assert_eq!(contract_addr, "credit_token");
+                from_json(msg).unwrap()
+            },
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn credits_issued_as_cw20_token() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            credit_token: Some(CreditTokenSource::Existing { address: Addr::unchecked("credit_token") }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        mock_credit_token(&mut deps, &[], &[]);
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        create_plot(deps.as_mut(), &env, "org1", &["0.0", "1.0", "1.0", "0.0", "0.0"], &["10.0", "10.0", "11.0", "11.0", "10.0"], (1000, 2000)).unwrap();
+        for voter in ["voter1", "voter2", "voter3"] {
+            commit_vote(deps.as_mut(), &env, voter, 0, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for voter in ["voter1", "voter2", "voter3"] {
+            reveal_vote(deps.as_mut(), &env, voter, 0, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+
+        // Minted on the token rather than the internal ledger, into escrow until the claim can no
+        // longer be challenged
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(cw20_transfer(&res.messages[0]), Cw20ExecuteMsg::Mint { recipient: "cosmos2contract".to_string(), amount: Uint128::new(100) });
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("org1"), at_height: None }).unwrap();
+        let org: OrganizationResponse = from_json(res).unwrap();
+        assert_eq!(org.carbon_credits, Uint128::zero());
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::ReleaseCredits { claim_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::ChallengePeriodNotEnded {});
+        env.block.time = env.block.time.plus_seconds(86401);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::ReleaseCredits { claim_id: 0 }).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0]), Cw20ExecuteMsg::Transfer { recipient: "org1".to_string(), amount: Uint128::new(100) });
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::ReleaseCredits { claim_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::NoEscrowedCredits {});
+        // The contract keeps the serials of tokens it can't follow
+        let owner: OwnerOfResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::OwnerOf { token_id: "0".to_string(), include_expired: None }).unwrap()).unwrap();
+        assert_eq!(owner.owner, Addr::unchecked("cosmos2contract"));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("cosmos2contract", &[]), ExecuteMsg::TransferNft { recipient: Addr::unchecked("org1"), token_id: "0".to_string() }).unwrap_err();
+        assert_eq!(err, ContractError::LedgerDisabled {});
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_json(res).unwrap();
+        assert_eq!(config.total_carbon_credits, Uint128::new(100));
+        assert_eq!(config.credit_token, Some(Addr::unchecked("credit_token")));
+
+        let request_msg = ExecuteMsg::CreateLendToken { lender: Addr::unchecked("org1"), amount: Uint128::new(40), interest_rate: Decimal::zero(), term: 86400 * 30, proof: None };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request_msg).unwrap();
+        let lend_msg = ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Accept };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), lend_msg).unwrap_err();
+        assert_eq!(err, ContractError::TokenPaymentRequired {});
+
+        let receive = |sender: &str, amount: u128, msg: &ReceiveMsg| ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_json_binary(msg).unwrap(),
+        });
+        let lend_hook = ReceiveMsg::LendTokens { lend_request_id: 0 };
+        // Only the credit token may call the hook
+        let err = execute(deps.as_mut(), env.clone(), mock_info("fake_token", &[]), receive("org1", 40, &lend_hook)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("credit_token", &[]), receive("org1", 30, &lend_hook)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPayment { sent: Uint128::new(30), expected: Uint128::new(40) });
+        let err = execute(deps.as_mut(), env.clone(), mock_info("credit_token", &[]), receive("stranger", 40, &lend_hook)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), env.clone(), mock_info("credit_token", &[]), receive("org1", 40, &lend_hook)).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0]), Cw20ExecuteMsg::Transfer { recipient: "borrower".to_string(), amount: Uint128::new(40) });
+
+        let repay_msg = ExecuteMsg::RepayTokens { loan_id: 0, amount: Uint128::new(40) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay_msg).unwrap_err();
+        assert_eq!(err, ContractError::TokenPaymentRequired {});
+        let repay_hook = ReceiveMsg::RepayTokens { loan_id: 0 };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("credit_token", &[]), receive("borrower", 25, &repay_hook)).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0]), Cw20ExecuteMsg::Transfer { recipient: "org1".to_string(), amount: Uint128::new(25) });
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("borrower"), at_height: None }).unwrap();
+        let borrower: OrganizationResponse = from_json(res).unwrap();
+        assert_eq!(borrower.debt, Uint128::new(15));
+        assert_eq!(borrower.total_returned, Uint128::new(25));
+        // Can't repay more than is owed
+        let err = execute(deps.as_mut(), env.clone(), mock_info("credit_token", &[]), receive("borrower", 20, &repay_hook)).unwrap_err();
+        assert_eq!(err, ContractError::RepaymentExceedsLoan { remaining: Uint128::new(15) });
+
+        // Lend offers escrow the sent tokens
+        let offer_msg = ExecuteMsg::CreateLendOffer { max_amount: Uint128::new(30), min_eligibility_score: Uint128::zero(), rate: Decimal::zero(), term: 86400 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), offer_msg).unwrap_err();
+        assert_eq!(err, ContractError::TokenPaymentRequired {});
+        let offer_hook = ReceiveMsg::CreateLendOffer { min_eligibility_score: Uint128::zero(), rate: Decimal::zero(), term: 86400 };
+        execute(deps.as_mut(), env.clone(), mock_info("credit_token", &[]), receive("org1", 30, &offer_hook)).unwrap();
+        let accept_msg = ExecuteMsg::AcceptLendOffer { offer_id: 0, amount: Uint128::new(30), proof: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_msg).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0]), Cw20ExecuteMsg::Transfer { recipient: "borrower".to_string(), amount: Uint128::new(30) });
+
+        let retire_msg = ExecuteMsg::RetireCredits { amount: Uint128::new(10), beneficiary: Addr::unchecked("org1"), period: 2024, reason: "2024 offsets".to_string() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire_msg).unwrap_err();
+        assert_eq!(err, ContractError::TokenPaymentRequired {});
+        let retire_hook = ReceiveMsg::RetireCredits { beneficiary: Addr::unchecked("org1"), period: 2024, reason: "2024 offsets".to_string() };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("credit_token", &[]), receive("org1", 10, &retire_hook)).unwrap();
+        assert_eq!(cw20_transfer(&res.messages[0]), Cw20ExecuteMsg::Burn { amount: Uint128::new(10) });
+        let certificate: RetirementCertificate = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetRetirement { id: 0 }).unwrap()).unwrap();
+        assert_eq!(certificate.serials, vec![RetiredSerials { batch_id: 0, claim_id: 0, serial_start: Uint128::new(1), serial_end: Uint128::new(10) }]);
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_json(res).unwrap();
+        assert_eq!(config.total_carbon_credits, Uint128::new(90));
+    }
+
+    #[test]
+    fn upheld_challenge_burns_credit_tokens() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            credit_token: Some(CreditTokenSource::Existing { address: Addr::unchecked("credit_token") }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        create_plot(deps.as_mut(), &env, "org1", &["0.0", "1.0", "1.0", "0.0", "0.0"], &["10.0", "10.0", "11.0", "11.0", "10.0"], (1000, 2000)).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+
+        // The minted tokens are in escrow, so they neither count for org1 nor can be released yet
+        mock_credit_token(&mut deps, &[("cosmos2contract", 100)], &[]);
+        env.block.height += 1;
+        let query_msg = QueryMsg::SimulateEligibility { borrower: Addr::unchecked("org1"), lender: Addr::unchecked("org2"), amount: Uint128::new(10) };
+        let res: SimulateEligibilityResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.factors.credits, Uint128::zero());
+
+        let challenge_msg = ExecuteMsg::ChallengeClaim { claim_id: 0, evidence: "QmEvidence".to_string() };
+        execute(deps.as_mut(), env.clone(), mock_info("voter2", &[]), challenge_msg).unwrap();
+        let vote_msg = ExecuteMsg::VoteOnChallenge { challenge_id: 0, vote: VoteOption::Yes };
+        execute(deps.as_mut(), env.clone(), mock_info("voter3", &[]), vote_msg).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        // The challenge period is over, but the claim is still under challenge
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::ReleaseCredits { claim_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::ClaimNotApproved {});
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::ResolveChallenge { challenge_id: 0 }).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(cw20_transfer(&res.messages[0]), Cw20ExecuteMsg::Burn { amount: Uint128::new(100) });
+
+        let query_msg = QueryMsg::GetOrganization { address: Addr::unchecked("org1"), at_height: None };
+        let org: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(org.debt, Uint128::zero());
+        let total_res: TotalCarbonCreditsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetTotalCarbonCredits {}).unwrap()).unwrap();
+        assert_eq!(total_res.total, Uint128::zero());
+        // as are the serials the contract kept for them
+        let count: NumTokensResponse = from_json(query(deps.as_ref(), env, QueryMsg::NumTokens {}).unwrap()).unwrap();
+        assert_eq!(count.count, 0);
+    }
+
+    #[test]
+    fn credit_token_instantiated_through_reply() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            credit_token: Some(CreditTokenSource::Instantiate {
+                code_id: 7,
+                name: "Carbon Credit".to_string(),
+                symbol: "CARBON".to_string(),
+                decimals: 0,
+                label: "carbon credits".to_string(),
+            }),
+            ..Default::default()
+        };
+        let res = instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Success);
+        let reply_id = res.messages[0].id;
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, msg, .. }) => {
+                assert_eq!(*code_id, 7);
+                let token_msg: Cw20InstantiateMsg = from_json(msg).unwrap();
+                assert_eq!(token_msg.mint.unwrap().minter, env.contract.address.to_string());
+            },
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        // MsgInstantiateContractResponse { contract_address: "credit_token" }
+        let address = b"credit_token";
+        let mut data = vec![0x0a, address.len() as u8];
+        data.extend_from_slice(address);
+        let token_reply = Reply {
+            id: reply_id,
+            result: SubMsgResult::Ok(SubMsgResponse { events: vec![], data: Some(Binary::from(data)) }),
+        };
+        reply(deps.as_mut(), env.clone(), token_reply).unwrap();
+        let res = query(deps.as_ref(), env, QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_json(res).unwrap();
+        assert_eq!(config.credit_token, Some(Addr::unchecked("credit_token")));
+    }
+
+    #[test]
+    fn credit_batches_split_and_transfer() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        let lats = ["10.0", "10.0", "11.0", "11.0", "10.0"];
+        create_plot(deps.as_mut(), &env, "org1", &["0.0", "1.0", "1.0", "0.0", "0.0"], &lats, (1000, 2000)).unwrap();
+        create_plot(deps.as_mut(), &env, "org2", &["1.0", "2.0", "2.0", "1.0", "1.0"], &lats, (1000, 2000)).unwrap();
+        for claim_id in 0..2 {
+            for voter in ["voter1", "voter2", "voter3"] {
+                commit_vote(deps.as_mut(), &env, voter, claim_id, VoteOption::Yes).unwrap();
+            }
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for claim_id in 0..2 {
+            for voter in ["voter1", "voter2", "voter3"] {
+                reveal_vote(deps.as_mut(), &env, voter, claim_id, VoteOption::Yes).unwrap();
+            }
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        for claim_id in 0..2 {
+            execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id }).unwrap();
+        }
+
+        // Serial ranges continue across claims
+        let batch: CreditBatch = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 0 }).unwrap()).unwrap();
+        assert_eq!(batch.claim_id, 0);
+        assert_eq!(batch.owner, Addr::unchecked("org1"));
+        assert_eq!((batch.serial_start, batch.serial_end), (Uint128::new(1), Uint128::new(100)));
+        assert_eq!((batch.vintage_start, batch.vintage_end), (1000, 2000));
+        assert_eq!(batch.methodology, "VM0047");
+        let lons: Vec<String> = ["0.0", "1.0", "1.0", "0.0", "0.0"].iter().map(|s| s.to_string()).collect();
+        let lat_strings: Vec<String> = lats.iter().map(|s| s.to_string()).collect();
+        assert_eq!(batch.polygon_hash, polygon_hash(&lons, &lat_strings));
+        let batch: CreditBatch = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 1 }).unwrap()).unwrap();
+        assert_eq!((batch.serial_start, batch.serial_end), (Uint128::new(101), Uint128::new(200)));
+
+        let split_msg = |amount: u128| ExecuteMsg::SplitBatch { token_id: "0".to_string(), amount: Uint128::new(amount) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), split_msg(30)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), split_msg(100)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSplit {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), split_msg(30)).unwrap();
+        let batch: CreditBatch = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 0 }).unwrap()).unwrap();
+        assert_eq!((batch.serial_start, batch.serial_end), (Uint128::new(1), Uint128::new(70)));
+        let split: CreditBatch = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 2 }).unwrap()).unwrap();
+        assert_eq!((split.serial_start, split.serial_end), (Uint128::new(71), Uint128::new(100)));
+        assert_eq!(split.parent_id, Some(0));
+        assert_eq!(split.amount(), Uint128::new(30));
+
+        let transfer_msg = ExecuteMsg::TransferNft { recipient: Addr::unchecked("buyer"), token_id: "2".to_string() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), transfer_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer_msg).unwrap();
+        let owner: OwnerOfResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::OwnerOf { token_id: "2".to_string(), include_expired: None }).unwrap()).unwrap();
+        assert_eq!(owner.owner, Addr::unchecked("buyer"));
+        // The credits go with the batch
+        let balance = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.carbon_credits
+        };
+        assert_eq!(balance(&deps, "org1"), Uint128::new(70));
+        assert_eq!(balance(&deps, "buyer"), Uint128::new(30));
+        let info: NftInfoResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::NftInfo { token_id: "2".to_string() }).unwrap()).unwrap();
+        assert_eq!(info.extension.claim_id, 0);
+
+        let tokens: TokensResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::Tokens { owner: Addr::unchecked("org1"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(tokens.tokens, vec!["0".to_string()]);
+        let by_claim: BatchesResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatchesByClaim { claim_id: 0, start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(by_claim.batches.iter().map(|b| b.id).collect::<Vec<_>>(), vec![0, 2]);
+        let by_owner: BatchesResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatchesByOwner { owner: Addr::unchecked("buyer"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(by_owner.batches.len(), 1);
+        let count: NumTokensResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::NumTokens {}).unwrap()).unwrap();
+        assert_eq!(count.count, 3);
+        let all: TokensResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::AllTokens { start_after: Some("0".to_string()), limit: Some(1) }).unwrap()).unwrap();
+        assert_eq!(all.tokens, vec!["1".to_string()]);
+        let contract_info: ContractInfoResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::ContractInfo {}).unwrap()).unwrap();
+        assert_eq!(contract_info.symbol, "CCB");
+
+        // Approved spenders and operators may transfer on the owner's behalf, until it changes hands
+        let approve_msg = ExecuteMsg::Approve { spender: Addr::unchecked("broker"), token_id: "2".to_string(), expires: None };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), approve_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), approve_msg).unwrap();
+        let approval: ApprovalResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::Approval { token_id: "2".to_string(), spender: Addr::unchecked("broker"), include_expired: None }).unwrap()).unwrap();
+        assert_eq!(approval.approval.expires, Expiration::Never {});
+        execute(deps.as_mut(), env.clone(), mock_info("broker", &[]), ExecuteMsg::TransferNft { recipient: Addr::unchecked("org2"), token_id: "2".to_string() }).unwrap();
+        assert_eq!((balance(&deps, "buyer"), balance(&deps, "org2")), (Uint128::zero(), Uint128::new(130)));
+        let owner: OwnerOfResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::OwnerOf { token_id: "2".to_string(), include_expired: None }).unwrap()).unwrap();
+        assert_eq!((owner.owner, owner.approvals), (Addr::unchecked("org2"), vec![]));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("broker", &[]), ExecuteMsg::TransferNft { recipient: Addr::unchecked("broker"), token_id: "2".to_string() }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let approve_all = ExecuteMsg::ApproveAll { operator: Addr::unchecked("broker"), expires: Some(Expiration::AtHeight(env.block.height + 1)) };
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), approve_all).unwrap();
+        let operators: OperatorsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::AllOperators { owner: Addr::unchecked("org2"), include_expired: None, start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(operators.operators.len(), 1);
+        let mut later = env.clone();
+        later.block.height += 1;
+        let err = execute(deps.as_mut(), later, mock_info("broker", &[]), ExecuteMsg::TransferNft { recipient: Addr::unchecked("buyer"), token_id: "2".to_string() }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("broker", &[]), ExecuteMsg::TransferNft { recipient: Addr::unchecked("buyer"), token_id: "2".to_string() }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::RevokeAll { operator: Addr::unchecked("broker") }).unwrap();
+        let operators: OperatorsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::AllOperators { owner: Addr::unchecked("org2"), include_expired: None, start_after: None, limit: None }).unwrap()).unwrap();
+        assert!(operators.operators.is_empty());
+
+        // and the serials with the credits, split off the sender's oldest batch
+        let transfer_msg = ExecuteMsg::TransferCredits { recipient: Addr::unchecked("buyer"), amount: Uint128::new(50) };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer_msg).unwrap();
+        let batch: CreditBatch = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 0 }).unwrap()).unwrap();
+        assert_eq!((batch.serial_start, batch.serial_end), (Uint128::new(1), Uint128::new(20)));
+        let split: CreditBatch = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 3 }).unwrap()).unwrap();
+        assert_eq!((split.owner, split.serial_start, split.serial_end, split.parent_id), (Addr::unchecked("buyer"), Uint128::new(21), Uint128::new(70), Some(0)));
+        // Listed credits are escrowed with their serials
+        let list_msg = ExecuteMsg::CreateListing { amount: Uint128::new(20), price: coin(5, "uatom") };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), list_msg).unwrap();
+        let owner: OwnerOfResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::OwnerOf { token_id: "0".to_string(), include_expired: None }).unwrap()).unwrap();
+        assert_eq!(owner.owner, Addr::unchecked("cosmos2contract"));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::TransferNft { recipient: Addr::unchecked("buyer"), token_id: "0".to_string() }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::CancelListing { listing_id: 0 }).unwrap();
+        let owner: OwnerOfResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::OwnerOf { token_id: "0".to_string(), include_expired: None }).unwrap()).unwrap();
+        assert_eq!(owner.owner, Addr::unchecked("org1"));
+
+        // Sending hands the batch to the contract and notifies it
+        let send_msg = ExecuteMsg::SendNft { contract: Addr::unchecked("market"), token_id: "1".to_string(), msg: Binary::from(b"{}".to_vec()) };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), send_msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) => {
+                assert_eq!(contract_addr, "market");
+                let Cw721ReceiverMsg::ReceiveNft(receive) = from_json(msg).unwrap();
+                assert_eq!(receive.sender, "org2");
+                assert_eq!(receive.token_id, "1");
+            },
+            other => panic!("unexpected message {:?}", other),
+        }
+        let owner: OwnerOfResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::OwnerOf { token_id: "1".to_string(), include_expired: None }).unwrap()).unwrap();
+        assert_eq!(owner.owner, Addr::unchecked("market"));
+        let err = execute(deps.as_mut(), env, mock_info("org1", &[]), split_msg(0)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidSplit {});
+    }
+
+    #[test]
+    fn retire_credits_issues_certificates() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        create_plot(deps.as_mut(), &env, "org1", &["0.0", "1.0", "1.0", "0.0", "0.0"], &["10.0", "10.0", "11.0", "11.0", "10.0"], (1000, 2000)).unwrap();
+        for voter in ["voter1", "voter2", "voter3"] {
+            commit_vote(deps.as_mut(), &env, voter, 0, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for voter in ["voter1", "voter2", "voter3"] {
+            reveal_vote(deps.as_mut(), &env, voter, 0, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::AddOrganizationEmission { emissions: "80".to_string() }).unwrap();
+
+        let retire = |amount: u128, beneficiary: &str| ExecuteMsg::RetireCredits {
+            amount: Uint128::new(amount),
+            beneficiary: Addr::unchecked(beneficiary),
+            period: 2024,
+            reason: "Scope 1 offsets".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire(0, "org2")).unwrap_err();
+        assert_eq!(err, ContractError::InvalidAmount {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire(101, "org2")).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughCredits {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire(30, "org2")).unwrap();
+
+        let certificate: RetirementCertificate = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetRetirement { id: 0 }).unwrap()).unwrap();
+        assert_eq!(certificate.retiree, Addr::unchecked("org1"));
+        assert_eq!(certificate.beneficiary, Addr::unchecked("org2"));
+        assert_eq!(certificate.amount, Uint128::new(30));
+        assert_eq!(certificate.reason, "Scope 1 offsets");
+        assert_eq!(certificate.time, env.block.time.seconds());
+        assert_eq!(certificate.serials, vec![RetiredSerials { batch_id: 0, claim_id: 0, serial_start: Uint128::new(1), serial_end: Uint128::new(30) }]);
+        let org1: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("org1"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org1.carbon_credits, Uint128::new(70));
+        // Offsets only count against the period they were retired for
+        let org2: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("org2"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org2.emissions, Uint128::new(80));
+        let unallocated: EmissionsReport = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEmissionsReport { organization: Addr::unchecked("org2"), period: 0, version: None }).unwrap()).unwrap();
+        assert_eq!((unallocated.version, unallocated.gross), (1, Uint128::new(80)));
+        let total: TotalCarbonCreditsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetTotalCarbonCredits {}).unwrap()).unwrap();
+        assert_eq!(total.total, Uint128::new(70));
+        let batch: CreditBatch = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 0 }).unwrap()).unwrap();
+        assert_eq!(batch.serial_start, Uint128::new(31));
+
+        // Retiring the rest consumes the batch
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire(70, "org1")).unwrap();
+        let certificate: RetirementCertificate = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetRetirement { id: 1 }).unwrap()).unwrap();
+        assert_eq!(certificate.serials[0].serial_start, Uint128::new(31));
+        assert_eq!(certificate.serials[0].serial_end, Uint128::new(100));
+        assert!(query(deps.as_ref(), env.clone(), QueryMsg::GetBatch { id: 0 }).is_err());
+
+        let all: RetirementsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetRetirements { start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(all.retirements.len(), 2);
+        let page: RetirementsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetRetirements { start_after: Some(0), limit: Some(1) }).unwrap()).unwrap();
+        assert_eq!(page.retirements[0].id, 1);
+        let for_org2: RetirementsResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetRetirementsByBeneficiary { beneficiary: Addr::unchecked("org2"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(for_org2.retirements.len(), 1);
+        assert_eq!(for_org2.retirements[0].id, 0);
+    }
+
+    #[test]
+    fn emissions_reports_and_net_position() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        create_plot(deps.as_mut(), &env, "org1", &["0.0", "1.0", "1.0", "0.0", "0.0"], &["10.0", "10.0", "11.0", "11.0", "10.0"], (1000, 2000)).unwrap();
+        for voter in ["voter1", "voter2", "voter3"] {
+            commit_vote(deps.as_mut(), &env, voter, 0, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for voter in ["voter1", "voter2", "voter3"] {
+            reveal_vote(deps.as_mut(), &env, voter, 0, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+
+        let entry = |scope: EmissionScope, category: &str, amount: u128| EmissionEntry { scope, category: category.to_string(), amount: Uint128::new(amount) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::ReportEmissions { period: 2024, entries: vec![] }).unwrap_err();
+        assert_eq!(err, ContractError::EmptyEmissionsReport {});
+        let report_msg = ExecuteMsg::ReportEmissions {
+            period: 2024,
+            entries: vec![
+                entry(EmissionScope::Scope1, "stationary combustion", 40),
+                entry(EmissionScope::Scope2, "purchased electricity", 20),
+                entry(EmissionScope::Scope3, "business travel", 10),
+            ],
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), report_msg).unwrap();
+        // A correction supersedes the first report
+        let correction_msg = ExecuteMsg::ReportEmissions {
+            period: 2024,
+            entries: vec![
+                entry(EmissionScope::Scope1, "stationary combustion", 50),
+                entry(EmissionScope::Scope1, "fleet", 10),
+                entry(EmissionScope::Scope2, "purchased electricity", 20),
+            ],
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), correction_msg).unwrap();
+
+        let report: EmissionsReport = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEmissionsReport { organization: Addr::unchecked("org2"), period: 2024, version: None }).unwrap()).unwrap();
+        assert_eq!(report.version, 2);
+        assert_eq!((report.scope_1, report.scope_2, report.scope_3), (Uint128::new(60), Uint128::new(20), Uint128::zero()));
+        assert_eq!(report.gross, Uint128::new(80));
+        let first: EmissionsReport = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEmissionsReport { organization: Addr::unchecked("org2"), period: 2024, version: Some(1) }).unwrap()).unwrap();
+        assert_eq!(first.gross, Uint128::new(70));
+        let history: EmissionsReportsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEmissionsReportHistory { organization: Addr::unchecked("org2"), period: 2024 }).unwrap()).unwrap();
+        assert_eq!(history.reports.len(), 2);
+        assert!(query(deps.as_ref(), env.clone(), QueryMsg::GetEmissionsReport { organization: Addr::unchecked("org2"), period: 2023, version: None }).is_err());
+        // The lifetime total only counts the latest report
+        let org2: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("org2"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org2.emissions, Uint128::new(80));
+
+        let retire = |amount: u128, period: u32| ExecuteMsg::RetireCredits {
+            amount: Uint128::new(amount),
+            beneficiary: Addr::unchecked("org2"),
+            period,
+            reason: "offsets".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire(60, 2024)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire(10, 2025)).unwrap();
+        let position: NetPositionResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetNetPosition { organization: Addr::unchecked("org2"), period: 2024 }).unwrap()).unwrap();
+        assert_eq!(position.gross_emissions, Uint128::new(80));
+        assert_eq!(position.retired_offsets, Uint128::new(60));
+        assert_eq!(position.net_emissions, Uint128::new(20));
+        assert!(!position.net_zero);
+        // The 2025 surplus isn't carried over to 2024
+        let org2: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("org2"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org2.emissions, Uint128::new(20));
+
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), retire(25, 2024)).unwrap();
+        let position: NetPositionResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetNetPosition { organization: Addr::unchecked("org2"), period: 2024 }).unwrap()).unwrap();
+        assert_eq!(position.net_emissions, Uint128::zero());
+        assert_eq!(position.surplus_offsets, Uint128::new(5));
+        assert!(position.net_zero);
+        // A later correction is netted against the offsets already retired
+        let correction_msg = ExecuteMsg::ReportEmissions { period: 2024, entries: vec![entry(EmissionScope::Scope1, "stationary combustion", 100)] };
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), correction_msg).unwrap();
+        let org2: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("org2"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org2.emissions, Uint128::new(15));
+        // and emissions added without a period are reported outside one
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::AddOrganizationEmission { emissions: "5".to_string() }).unwrap();
+        let unallocated: EmissionsReport = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEmissionsReport { organization: Addr::unchecked("org2"), period: 0, version: None }).unwrap()).unwrap();
+        assert_eq!(unallocated.gross, Uint128::new(5));
+        let org2: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("org2"), at_height: None }).unwrap()).unwrap();
+        assert_eq!(org2.emissions, Uint128::new(20));
+
+        let positions: NetPositionsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetNetPositions { organization: Addr::unchecked("org2"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(positions.positions.iter().map(|p| p.period).collect::<Vec<_>>(), vec![0, 2024, 2025]);
+        // Offsets alone don't make an unreported period net-zero
+        assert!(!positions.positions[2].reported);
+        assert!(!positions.positions[2].net_zero);
+        let positions: NetPositionsResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetNetPositions { organization: Addr::unchecked("org2"), start_after: Some(2024), limit: None }).unwrap()).unwrap();
+        assert_eq!(positions.positions.len(), 1);
+        assert_eq!(positions.positions[0].retired_offsets, Uint128::new(10));
+    }
+
+    // Approves a 100 credit claim for `organization` through voter1..voter3
+    fn issue_credits(mut deps: DepsMut, env: &mut Env, organization: &str, offset: u64) -> u64 {
+        let res = execute(deps.branch(), env.clone(), mock_info(organization, &[]), ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(offset),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash1".to_string()],
+            methodology: "VM0047".to_string(),
+        }).unwrap();
+        // The attribute holds the incremented counter
+        let claim_id = res.attributes.iter().find(|a| a.key == "claim_id").unwrap().value.parse::<u64>().unwrap() - 1;
+        for voter in ["voter1", "voter2", "voter3"] {
+            commit_vote(deps.branch(), env, voter, claim_id, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86401);
+        for voter in ["voter1", "voter2", "voter3"] {
+            reveal_vote(deps.branch(), env, voter, claim_id, VoteOption::Yes).unwrap();
+        }
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps, env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id }).unwrap();
+        env.block.height += 1;
+        claim_id
+    }
+
+    #[test]
+    fn transfers_and_allowances() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let balance = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.carbon_credits
+        };
+
+        let transfer = |amount: u128| ExecuteMsg::TransferCredits { recipient: Addr::unchecked("org2"), amount: Uint128::new(amount) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer(0)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidAmount {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer(101)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughCredits {});
+        let res = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer(30)).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "to" && a.value == "org2"));
+        assert_eq!(balance(&deps, "org1"), Uint128::new(70));
+        assert_eq!(balance(&deps, "org2"), Uint128::new(30));
+
+        let increase = |amount: u128, expires: Option<Expiration>| ExecuteMsg::IncreaseAllowance { spender: Addr::unchecked("broker"), amount: Uint128::new(amount), expires };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("broker", &[]), increase(10, None)).unwrap_err();
+        assert_eq!(err, ContractError::CannotSetOwnAccount {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), increase(10, Some(Expiration::AtHeight(env.block.height)))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidExpiration {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), increase(40, None)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), increase(10, Some(Expiration::AtHeight(env.block.height + 10)))).unwrap();
+        let decrease_msg = ExecuteMsg::DecreaseAllowance { spender: Addr::unchecked("broker"), amount: Uint128::new(5), expires: None };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), decrease_msg).unwrap();
+        let allowance: AllowanceResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetAllowance { owner: Addr::unchecked("org1"), spender: Addr::unchecked("broker") }).unwrap()).unwrap();
+        assert_eq!(allowance.allowance, Uint128::new(45));
+        assert_eq!(allowance.expires, Expiration::AtHeight(env.block.height + 10));
+
+        let transfer_from = |amount: u128| ExecuteMsg::TransferFrom { owner: Addr::unchecked("org1"), recipient: Addr::unchecked("buyer"), amount: Uint128::new(amount) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("stranger", &[]), transfer_from(10)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientAllowance {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("broker", &[]), transfer_from(50)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientAllowance {});
+        execute(deps.as_mut(), env.clone(), mock_info("broker", &[]), transfer_from(20)).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::new(50));
+        assert_eq!(balance(&deps, "buyer"), Uint128::new(20));
+
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::IncreaseAllowance { spender: Addr::unchecked("broker"), amount: Uint128::new(5), expires: None }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::IncreaseAllowance { spender: Addr::unchecked("auditor"), amount: Uint128::new(5), expires: None }).unwrap();
+        let all: AllAllowancesResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetAllAllowances { owner: Addr::unchecked("org1"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(all.allowances.iter().map(|a| a.spender.as_str()).collect::<Vec<_>>(), vec!["auditor", "broker"]);
+        assert_eq!(all.allowances[1].allowance, Uint128::new(25));
+
+        // Expired allowances can't be spent
+        env.block.height += 10;
+        let err = execute(deps.as_mut(), env.clone(), mock_info("broker", &[]), transfer_from(5)).unwrap_err();
+        assert_eq!(err, ContractError::AllowanceExpired {});
+        let decrease_msg = ExecuteMsg::DecreaseAllowance { spender: Addr::unchecked("auditor"), amount: Uint128::new(5), expires: None };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), decrease_msg).unwrap();
+        let all: AllAllowancesResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetAllAllowances { owner: Addr::unchecked("org1"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(all.allowances.len(), 1);
+    }
+
+    #[test]
+    fn ledger_messages_disabled_with_credit_token() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            credit_token: Some(CreditTokenSource::Existing { address: Addr::unchecked("credit_token") }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::TransferCredits { recipient: Addr::unchecked("org2"), amount: Uint128::new(1) }).unwrap_err();
+        assert_eq!(err, ContractError::LedgerDisabled {});
+        let err = execute(deps.as_mut(), env, mock_info("org1", &[]), ExecuteMsg::IncreaseAllowance { spender: Addr::unchecked("org2"), amount: Uint128::new(1), expires: None }).unwrap_err();
+        assert_eq!(err, ContractError::LedgerDisabled {});
+    }
+
+    #[test]
+    fn marketplace_listings() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let balance = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.carbon_credits
+        };
+
+        let list = |amount: u128, price: u128, denom: &str| ExecuteMsg::CreateListing { amount: Uint128::new(amount), price: coin(price, denom) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), list(200, 5, "uusd")).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughCredits {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), list(10, 0, "uusd")).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPrice {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), list(40, 5, "uusd")).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), list(30, 3, "uusd")).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), list(10, 2, "ujuno")).unwrap();
+        // Listed credits are held in escrow
+        assert_eq!(balance(&deps, "org1"), Uint128::new(20));
+
+        let book: ListingsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrderBook { denom: "uusd".to_string(), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(book.listings.iter().map(|l| l.id).collect::<Vec<_>>(), vec![1, 0]);
+        let page: ListingsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrderBook { denom: "uusd".to_string(), start_after: Some((Uint128::new(3), 1)), limit: Some(1) }).unwrap()).unwrap();
+        assert_eq!(page.listings.iter().map(|l| l.id).collect::<Vec<_>>(), vec![0]);
+
+        let fill = |amount: u128| ExecuteMsg::FillListing { listing_id: 0, amount: Uint128::new(amount) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("buyer", &coins(40, "uusd")), fill(10)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPayment { sent: Uint128::new(40), expected: Uint128::new(50) });
+        let err = execute(deps.as_mut(), env.clone(), mock_info("buyer", &coins(50, "ujuno")), fill(10)).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MissingDenom("uusd".to_string())));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), fill(10)).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("buyer", &coins(205, "uusd")), fill(41)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughCredits {});
+
+        // Partial fill pays the seller and leaves the rest listed
+        let res = execute(deps.as_mut(), env.clone(), mock_info("buyer", &coins(50, "uusd")), fill(10)).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send { to_address: "org1".to_string(), amount: coins(50, "uusd") }));
+        assert_eq!(balance(&deps, "buyer"), Uint128::new(10));
+        let listing: Listing = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetListing { id: 0 }).unwrap()).unwrap();
+        assert_eq!(listing.amount, Uint128::new(30));
+        execute(deps.as_mut(), env.clone(), mock_info("buyer", &coins(150, "uusd")), fill(30)).unwrap();
+        assert!(query(deps.as_ref(), env.clone(), QueryMsg::GetListing { id: 0 }).is_err());
+        assert_eq!(balance(&deps, "buyer"), Uint128::new(40));
+
+        let cancel_msg = ExecuteMsg::CancelListing { listing_id: 1 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("buyer", &[]), cancel_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), cancel_msg).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::new(50));
+        let by_seller: ListingsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetListingsBySeller { seller: Addr::unchecked("org1"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(by_seller.listings.iter().map(|l| l.id).collect::<Vec<_>>(), vec![2]);
+        // Filled and cancelled listings leave the order book index
+        let book: ListingsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrderBook { denom: "uusd".to_string(), start_after: None, limit: None }).unwrap()).unwrap();
+        assert!(book.listings.is_empty());
+        let book: ListingsResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetOrderBook { denom: "ujuno".to_string(), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(book.listings.iter().map(|l| l.id).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn repayments_settle_specific_loans() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        issue_credits(deps.as_mut(), &mut env, "org2", 2);
+        let balance = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.carbon_credits
+        };
+
+        for (lender, amount) in [("org1", 30), ("org2", 20)] {
+            let request_msg = ExecuteMsg::CreateLendToken { lender: Addr::unchecked(lender), amount: Uint128::new(amount), interest_rate: Decimal::zero(), term: 86400 * 30, proof: None };
+            execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request_msg).unwrap();
+        }
+        for (lend_request_id, lender) in [(0, "org1"), (1, "org2")] {
+            let lend_msg = ExecuteMsg::LendTokens { lend_request_id, response: LendResponse::Accept };
+            let res = execute(deps.as_mut(), env.clone(), mock_info(lender, &[]), lend_msg).unwrap();
+            assert!(res.attributes.iter().any(|a| a.key == "loan_id" && a.value == lend_request_id.to_string()));
+        }
+        let open: LoansResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOpenLoansByBorrower { borrower: Addr::unchecked("borrower"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(open.loans.iter().map(|l| (l.id, l.principal)).collect::<Vec<_>>(), vec![(0, Uint128::new(30)), (1, Uint128::new(20))]);
+        let open: LoansResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOpenLoansByBorrower { borrower: Addr::unchecked("borrower"), start_after: Some(0), limit: Some(1) }).unwrap()).unwrap();
+        assert_eq!(open.loans.iter().map(|l| l.id).collect::<Vec<_>>(), vec![1]);
+
+        let repay = |loan_id: u64, amount: u128| ExecuteMsg::RepayTokens { loan_id, amount: Uint128::new(amount) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), repay(1, 5)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay(1, 25)).unwrap_err();
+        assert_eq!(err, ContractError::RepaymentExceedsLoan { remaining: Uint128::new(20) });
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay(7, 5)).unwrap_err();
+        assert_eq!(err, ContractError::LoanNotFound {});
+
+        // Repaying the second loan pays its lender, not the first one
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay(1, 20)).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::new(70));
+        assert_eq!(balance(&deps, "org2"), Uint128::new(100));
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 1 }).unwrap()).unwrap();
+        assert_eq!(loan.status, LoanStatus::Repaid);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay(1, 1)).unwrap_err();
+        assert_eq!(err, ContractError::LoanNotOpen {});
+
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay(0, 10)).unwrap();
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.repaid, loan.remaining(), loan.status), (Uint128::new(10), Uint128::new(20), LoanStatus::Open));
+        let open: LoansResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOpenLoansByBorrower { borrower: Addr::unchecked("borrower"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(open.loans.iter().map(|l| l.id).collect::<Vec<_>>(), vec![0]);
+        let open: LoansResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOpenLoansByLender { lender: Addr::unchecked("org2"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert!(open.loans.is_empty());
+        let res = query(deps.as_ref(), env, QueryMsg::GetOrganization { address: Addr::unchecked("borrower"), at_height: None }).unwrap();
+        let borrower: OrganizationResponse = from_json(res).unwrap();
+        assert_eq!(borrower.debt, Uint128::new(20));
+    }
+
+    #[test]
+    fn loans_accrue_interest_and_default() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            default_penalty: Some(Uint128::new(5)),
+            reborrow_reputation: Some(Uint128::new(1)),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let organization = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> OrganizationResponse {
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap()
+        };
+        // voter1 earned one reputation point for voting with the majority
+        assert_eq!(organization(&deps, "voter1").reputation_score, Uint128::new(1));
+
+        let request = |term: u64| ExecuteMsg::CreateLendToken { lender: Addr::unchecked("org1"), amount: Uint128::new(50), interest_rate: Decimal::percent(10), term, proof: None };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), request(0)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidLoanTerm {});
+        execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), request(SECONDS_PER_YEAR)).unwrap();
+        let lend_msg = ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Accept };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), lend_msg).unwrap();
+
+        // Half a year at 10% on 50 credits, rounded down
+        env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR / 2);
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.interest, loan.remaining()), (Uint128::new(2), Uint128::new(52)));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::MarkDefaulted { loan_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::LoanNotOverdue {});
+
+        env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR / 2 + 1);
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::MarkDefaulted { loan_id: 0 }).unwrap();
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.remaining(), loan.status), (Uint128::new(55), LoanStatus::Defaulted));
+        let requests: UserLendRequestsResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::UserLendRequests { user: "voter1".to_string(), start_after: None, limit: None }).unwrap()).unwrap();
+        assert_eq!(requests.lend_requests[0].status, LentStatus::Defaulted);
+        let borrower = organization(&deps, "voter1");
+        assert_eq!((borrower.reputation_score, borrower.defaults, borrower.debt), (Uint128::zero(), 1, Uint128::new(55)));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::MarkDefaulted { loan_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::LoanNotOpen {});
+
+        // Defaulted borrowers can't borrow again until their reputation recovers
+        let err = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), request(SECONDS_PER_YEAR)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughReputation {});
+
+        // A defaulted loan can still be repaid
+        let repay = |amount: u128| ExecuteMsg::RepayTokens { loan_id: 0, amount: Uint128::new(amount) };
+        execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), repay(50)).unwrap();
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.remaining(), loan.status), (Uint128::new(5), LoanStatus::Defaulted));
+        assert_eq!(organization(&deps, "org1").carbon_credits, Uint128::new(100));
+    }
+
+    #[test]
+    fn collateralised_loans() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            collateral: Some(CollateralParams { denom: "uusd".to_string(), ratio: Decimal::percent(200) }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let health = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, env: &Env, id: u64| -> LoanHealthResponse {
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoanHealth { id }).unwrap()).unwrap()
+        };
+
+        let request = ExecuteMsg::CreateLendToken { lender: Addr::unchecked("org1"), amount: Uint128::new(30), interest_rate: Decimal::percent(10), term: SECONDS_PER_YEAR, proof: None };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &coins(59, "uusd")), request.clone()).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientCollateral { required: Uint128::new(60) });
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &coins(60, "ujuno")), request.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Payment(PaymentError::MissingDenom("uusd".to_string())));
+        // Request 0 is denied and its collateral refunded; request 1 is accepted; request 2 is unsecured
+        for _ in 0..2 {
+            execute(deps.as_mut(), env.clone(), mock_info("borrower", &coins(60, "uusd")), request.clone()).unwrap();
+        }
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request).unwrap();
+        let deny_msg = ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Deny };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), deny_msg).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send { to_address: "borrower".to_string(), amount: coins(60, "uusd") }));
+        for lend_request_id in [1, 2] {
+            let lend_msg = ExecuteMsg::LendTokens { lend_request_id, response: LendResponse::Accept };
+            execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), lend_msg).unwrap();
+        }
+        assert_eq!(health(&deps, &env, 0).health, Some(Decimal::one()));
+        assert_eq!(health(&deps, &env, 1).health, None);
+
+        // Interest lowers the health of the secured loan; repaying it in full returns the collateral
+        env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR / 2);
+        let loan_health = health(&deps, &env, 0);
+        assert_eq!((loan_health.remaining, loan_health.required_collateral), (Uint128::new(31), Uint128::new(62)));
+        assert!(loan_health.health < Some(Decimal::one()));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::ClaimCollateral { loan_id: 0 }).unwrap_err();
+        assert_eq!(err, ContractError::LoanNotDefaulted {});
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), ExecuteMsg::RepayTokens { loan_id: 0, amount: Uint128::new(31) }).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send { to_address: "borrower".to_string(), amount: coins(60, "uusd") }));
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.collateral, loan.status), (None, LoanStatus::Repaid));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::ClaimCollateral { loan_id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::LoanNotDefaulted {});
+
+        // After default the lender of the unsecured loan has nothing to claim
+        env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR);
+        assert!(health(&deps, &env, 1).overdue);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), ExecuteMsg::ClaimCollateral { loan_id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::ClaimCollateral { loan_id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::NoCollateral {});
+    }
+
+    #[test]
+    fn lender_claims_collateral_after_default() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            collateral: Some(CollateralParams { denom: "uusd".to_string(), ratio: Decimal::one() }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+
+        let request = ExecuteMsg::CreateLendToken { lender: Addr::unchecked("org1"), amount: Uint128::new(40), interest_rate: Decimal::zero(), term: 86400, proof: None };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &coins(50, "uusd")), request).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Accept }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), ExecuteMsg::RepayTokens { loan_id: 0, amount: Uint128::new(10) }).unwrap();
+
+        // Claiming an overdue loan defaults it first
+        env.block.time = env.block.time.plus_seconds(86401);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::ClaimCollateral { loan_id: 0 }).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send { to_address: "org1".to_string(), amount: coins(50, "uusd") }));
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.collateral, loan.status), (None, LoanStatus::Liquidated));
+        let borrower: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("borrower"), at_height: None }).unwrap()).unwrap();
+        assert_eq!((borrower.debt, borrower.defaults), (Uint128::zero(), 1));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), ExecuteMsg::RepayTokens { loan_id: 0, amount: Uint128::new(10) }).unwrap_err();
+        assert_eq!(err, ContractError::LoanNotOpen {});
+        let open: LoansResponse = from_json(query(deps.as_ref(), env, QueryMsg::GetOpenLoansByLender { lender: Addr::unchecked("org1"), start_after: None, limit: None }).unwrap()).unwrap();
+        assert!(open.loans.is_empty());
+    }
+
+    #[test]
+    fn standing_lend_offers() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let balance = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.carbon_credits
+        };
+        // Holding 20 credits lifts the borrower's score from the base 50 to 70
+        let transfer_msg = ExecuteMsg::TransferCredits { recipient: Addr::unchecked("borrower"), amount: Uint128::new(20) };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer_msg).unwrap();
+        // Eligibility reads the state at the start of the block
+        env.block.height += 1;
+
+        let offer = |max_amount: u128, min_eligibility_score: u128| ExecuteMsg::CreateLendOffer {
+            max_amount: Uint128::new(max_amount),
+            min_eligibility_score: Uint128::new(min_eligibility_score),
+            rate: Decimal::percent(5),
+            term: 86400 * 30,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), offer(100, 60)).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughCredits {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), offer(60, 60)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), offer(20, 90)).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::zero());
+
+        let offers = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, eligibility_score: Option<u128>| -> Vec<u64> {
+            let query_msg = QueryMsg::GetLendOffers { eligibility_score: eligibility_score.map(Uint128::new), start_after: None, limit: None };
+            let res: LendOffersResponse = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+            res.offers.iter().map(|offer| offer.id).collect()
+        };
+        assert_eq!(offers(&deps, Some(70)), vec![0]);
+        assert_eq!(offers(&deps, Some(50)), Vec::<u64>::new());
+        assert_eq!(offers(&deps, None), vec![0, 1]);
+
+        let accept = |offer_id: u64, amount: u128| ExecuteMsg::AcceptLendOffer { offer_id, amount: Uint128::new(amount), proof: None };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("newcomer", &[]), accept(0, 10)).unwrap_err();
+        assert_eq!(err, ContractError::BorrowerNotEligible {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept(1, 10)).unwrap_err();
+        assert_eq!(err, ContractError::BorrowerNotEligible {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), accept(0, 10)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept(0, 70)).unwrap_err();
+        assert_eq!(err, ContractError::ExceedsOffer { available: Uint128::new(60) });
+
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept(0, 40)).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "eligibility_score" && a.value == "70"));
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.offer_id, loan.lender, loan.interest_rate), (Some(0), Addr::unchecked("org1"), Decimal::percent(5)));
+        let remaining: LendOffer = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLendOffer { id: 0 }).unwrap()).unwrap();
+        assert_eq!(remaining.max_amount, Uint128::new(20));
+        // Draining an offer closes it
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept(0, 20)).unwrap();
+        assert_eq!(offers(&deps, None), vec![1]);
+        assert_eq!(balance(&deps, "borrower"), Uint128::new(80));
+
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), ExecuteMsg::CancelLendOffer { offer_id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::CancelLendOffer { offer_id: 1 }).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::new(20));
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), ExecuteMsg::RepayTokens { loan_id: 0, amount: Uint128::new(40) }).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::new(60));
+
+        // Serials followed the credits through escrow, lending and repayment
+        let serials = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, owner: &str| -> Uint128 {
+            let query_msg = QueryMsg::GetBatchesByOwner { owner: Addr::unchecked(owner), start_after: None, limit: None };
+            let res: BatchesResponse = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+            res.batches.iter().map(|batch| batch.amount()).sum()
+        };
+        assert_eq!(serials(&deps, "org1"), Uint128::new(60));
+        assert_eq!(serials(&deps, "borrower"), Uint128::new(40));
+        assert_eq!(serials(&deps, "cosmos2contract"), Uint128::zero());
+        // so borrowed credits retire with serials too
+        let retire_msg = ExecuteMsg::RetireCredits { amount: Uint128::new(40), beneficiary: Addr::unchecked("borrower"), period: 2024, reason: "2024 offsets".to_string() };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), retire_msg).unwrap();
+        let certificate: RetirementCertificate = from_json(query(deps.as_ref(), env, QueryMsg::GetRetirement { id: 0 }).unwrap()).unwrap();
+        assert_eq!(certificate.serials.iter().map(|serials| serials.serial_end + Uint128::new(1) - serials.serial_start).sum::<Uint128>(), Uint128::new(40));
+    }
+
+    #[test]
+    fn pooled_lending() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            pool: Some(PoolParams { base_rate: Decimal::zero(), utilisation_rate: Decimal::one(), min_eligibility_score: Uint128::new(60) }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        issue_credits(deps.as_mut(), &mut env, "org2", 2);
+        let balance = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.carbon_credits
+        };
+        let position = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, env: &Env, address: &str| -> PoolPositionResponse {
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetPoolPosition { address: Addr::unchecked(address) }).unwrap()).unwrap()
+        };
+        let transfer_msg = ExecuteMsg::TransferCredits { recipient: Addr::unchecked("borrower"), amount: Uint128::new(20) };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer_msg).unwrap();
+        env.block.height += 1;
+
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::PoolDeposit { amount: Uint128::new(60) }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::PoolDeposit { amount: Uint128::new(40) }).unwrap();
+        assert_eq!(position(&deps, &env, "org1").shares, Uint128::new(60));
+
+        let borrow = |amount: u128| ExecuteMsg::PoolBorrow { amount: Uint128::new(amount), proof: None };
+        // The base score of 50 is below the pool's minimum
+        let err = execute(deps.as_mut(), env.clone(), mock_info("newcomer", &[]), borrow(10)).unwrap_err();
+        assert_eq!(err, ContractError::BorrowerNotEligible {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), borrow(120)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientLiquidity { available: Uint128::new(100) });
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), borrow(50)).unwrap();
+        let pool: PoolResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetPool {}).unwrap()).unwrap();
+        assert_eq!((pool.available, pool.borrowed), (Uint128::new(50), Uint128::new(50)));
+        assert_eq!((pool.utilisation, pool.borrow_rate), (Decimal::percent(50), Decimal::percent(50)));
+
+        // Withdrawals are limited to what is not lent out
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::PoolWithdraw { shares: Uint128::new(60) }).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientLiquidity { available: Uint128::new(50) });
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::PoolWithdraw { shares: Uint128::new(41) }).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientShares {});
+
+        // Half a year at 50% grows the debt by a quarter, shared by depositors pro rata
+        env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR / 2);
+        assert_eq!(position(&deps, &env, "borrower").debt, Uint128::new(62));
+        assert_eq!(position(&deps, &env, "org1").value, Uint128::new(67));
+        assert_eq!(position(&deps, &env, "org2").value, Uint128::new(44));
+
+        let repay = |amount: u128| ExecuteMsg::PoolRepay { amount: Uint128::new(amount) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay(63)).unwrap_err();
+        assert_eq!(err, ContractError::RepaymentExceedsLoan { remaining: Uint128::new(62) });
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), repay(62)).unwrap();
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked("borrower"), at_height: None }).unwrap();
+        let borrower: OrganizationResponse = from_json(res).unwrap();
+        assert_eq!((borrower.debt, borrower.total_returned, borrower.carbon_credits), (Uint128::zero(), Uint128::new(62), Uint128::new(8)));
+
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::PoolWithdraw { shares: Uint128::new(60) }).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::new(87));
+        let pool: PoolResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetPool {}).unwrap()).unwrap();
+        assert_eq!((pool.total_shares, pool.available, pool.borrowed), (Uint128::new(40), Uint128::new(45), Uint128::zero()));
+        assert_eq!(position(&deps, &env, "org2").value, Uint128::new(45));
+    }
+
+    #[test]
+    fn lend_request_cancellation_expiry_and_counter_offers() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            collateral: Some(CollateralParams { denom: "uusd".to_string(), ratio: Decimal::one() }),
+            lend_request_expiry: Some(86400),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let balance = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.carbon_credits
+        };
+        let statuses = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, env: &Env| -> Vec<LentStatus> {
+            let query_msg = QueryMsg::UserLendRequests { user: "borrower".to_string(), start_after: None, limit: None };
+            let res: UserLendRequestsResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+            res.lend_requests.into_iter().map(|request| request.status).collect()
+        };
+        let request = |amount: u128| ExecuteMsg::CreateLendToken { lender: Addr::unchecked("org1"), amount: Uint128::new(amount), interest_rate: Decimal::zero(), term: 86400 * 30, proof: None };
+        let respond = |lend_request_id: u64, response: LendResponse| ExecuteMsg::LendTokens { lend_request_id, response };
+        let cancel = |lend_request_id: u64| ExecuteMsg::CancelLendRequest { lend_request_id };
+
+        // Cancelling refunds the collateral and closes the request
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &coins(30, "uusd")), request(30)).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), cancel(0)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), cancel(0)).unwrap();
+        assert_eq!(res.messages[0].msg, CosmosMsg::Bank(BankMsg::Send { to_address: "borrower".to_string(), amount: coins(30, "uusd") }));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), respond(0, LendResponse::Accept)).unwrap_err();
+        assert_eq!(err, ContractError::RequestNotActive {});
+
+        // The lender counters with more than asked, escrowing it
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &coins(30, "uusd")), request(30)).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), respond(1, LendResponse::Counter { amount: Uint128::new(200) })).unwrap_err();
+        assert_eq!(err, ContractError::NotEnoughCredits {});
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), respond(1, LendResponse::Counter { amount: Uint128::new(40) })).unwrap();
+        assert_eq!(balance(&deps, "org1"), Uint128::new(60));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), respond(1, LendResponse::Accept)).unwrap_err();
+        assert_eq!(err, ContractError::RequestNotActive {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request(20)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request(20)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), respond(3, LendResponse::Counter { amount: Uint128::new(10) })).unwrap();
+        let accept_counter = ExecuteMsg::AcceptCounterOffer { lend_request_id: 1 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_counter.clone()).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientCollateral { required: Uint128::new(40) });
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &coins(10, "uusd")), accept_counter).unwrap();
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.principal, loan.collateral), (Uint128::new(40), Some(coin(40, "uusd"))));
+        assert_eq!(balance(&deps, "borrower"), Uint128::new(40));
+
+        // Requests left unanswered expire; a countered one returns the lender's escrow when reclaimed
+        assert_eq!(balance(&deps, "org1"), Uint128::new(50));
+        env.block.time = env.block.time.plus_seconds(86401);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), respond(2, LendResponse::Accept)).unwrap_err();
+        assert_eq!(err, ContractError::RequestExpired {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), ExecuteMsg::AcceptCounterOffer { lend_request_id: 3 }).unwrap_err();
+        assert_eq!(err, ContractError::RequestExpired {});
+        assert_eq!(statuses(&deps, &env), vec![LentStatus::Cancelled, LentStatus::Approved, LentStatus::Expired, LentStatus::Expired]);
+        // The lender settles the expired counter-offer the borrower never answered
+        let res = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), cancel(3)).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "borrower" && a.value == "borrower"));
+        assert_eq!(balance(&deps, "org1"), Uint128::new(60));
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), cancel(3)).unwrap_err();
+        assert_eq!(err, ContractError::RequestNotActive {});
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), cancel(2)).unwrap();
+    }
+
+    #[test]
+    fn reputation_rewards_slashing_and_decay() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            reputation: Some(ReputationParams {
+                correct_vote_reward: Uint128::new(10),
+                incorrect_vote_slash: Uint128::new(3),
+                absent_penalty: Uint128::new(2),
+                decay_rate: Decimal::percent(50),
+                decay_period: 30 * 86400,
+            }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let reputation = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, env: &Env, address: &str| -> Uint128 {
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height: None }).unwrap()).unwrap();
+            res.reputation_score
+        };
+        let events = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str| -> Vec<(ReputationReason, Option<u64>, Uint128)> {
+            let query_msg = QueryMsg::GetReputationEvents { address: Addr::unchecked(address), start_after: None, limit: None };
+            let res: ReputationEventsResponse = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+            res.events.into_iter().map(|event| (event.reason, event.reference, event.current)).collect()
+        };
+
+        // An evenly split vote is rejected: the yes voter is slashed and the verifier that stayed away penalised
+        add_verifiers(deps.as_mut(), &env, &["voter4"]);
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(1),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash2".to_string()],
+            methodology: "VM0047".to_string(),
+        }).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter1", 1, VoteOption::Yes).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter2", 1, VoteOption::No).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "voter1", 1, VoteOption::Yes).unwrap();
+        reveal_vote(deps.as_mut(), &env, "voter2", 1, VoteOption::No).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id: 1 }).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "absent" && a.value == "2"));
+        // Like unrevealed voters, absentees without an organization have nothing to lose
+        assert!(ORGANIZATIONS.may_load(deps.as_ref().storage, &Addr::unchecked("voter4")).unwrap().is_none());
+        assert_eq!(reputation(&deps, &env, "voter1"), Uint128::new(7));
+        assert_eq!(reputation(&deps, &env, "voter2"), Uint128::new(20));
+        assert_eq!(reputation(&deps, &env, "voter3"), Uint128::new(8));
+        assert_eq!(events(&deps, "voter1"), vec![
+            (ReputationReason::CorrectVote, Some(0), Uint128::new(10)),
+            (ReputationReason::IncorrectVote, Some(1), Uint128::new(7)),
+        ]);
+        assert_eq!(events(&deps, "voter3"), vec![
+            (ReputationReason::CorrectVote, Some(0), Uint128::new(10)),
+            (ReputationReason::Absent, Some(1), Uint128::new(8)),
+        ]);
+
+        // Reputation halves every period; it is reported straight away and recorded on the next change
+        env.block.time = env.block.time.plus_seconds(30 * 86400);
+        assert_eq!(reputation(&deps, &env, "voter2"), Uint128::new(10));
+        assert_eq!(events(&deps, "voter2").len(), 2);
+        let msg = ExecuteMsg::VerifyEligibility { borrower: Addr::unchecked("voter2"), amount: Uint128::new(10), lender: Addr::unchecked("org1"), proof: None };
+        execute(deps.as_mut(), env.clone(), mock_info("voter2", &[]), msg).unwrap();
+        assert_eq!(events(&deps, "voter2")[2], (ReputationReason::Decay, None, Uint128::new(10)));
+        env.block.time = env.block.time.plus_seconds(60 * 86400);
+        assert_eq!(reputation(&deps, &env, "voter2"), Uint128::new(2));
+
+        // The verifier set is capped so finalization sweeps stay bounded
+        let verifiers: Vec<String> = (4..MAX_VERIFIERS).map(|i| format!("extra{}", i)).collect();
+        add_verifiers(deps.as_mut(), &env, &verifiers.iter().map(String::as_str).collect::<Vec<_>>());
+        let msg = ExecuteMsg::AddVerifier { address: Addr::unchecked("extra"), name: "extra".to_string(), metadata: "".to_string(), accredited_until: env.block.time.seconds() + 86400 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::TooManyVerifiers { max: MAX_VERIFIERS });
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), ExecuteMsg::RemoveVerifier { address: Addr::unchecked("voter4") }).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+    }
+
+    #[test]
+    fn votes_and_eligibility_use_snapshots() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            vote_weights: Some(VoteWeights {
+                base_weight: Uint128::new(1),
+                reputation_weight: Uint128::zero(),
+                credit_weight: Uint128::new(1),
+            }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3", "whale"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let credits_at = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, address: &str, at_height: Option<u64>| -> Uint128 {
+            let query_msg = QueryMsg::GetOrganization { address: Addr::unchecked(address), at_height };
+            let res: OrganizationResponse = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+            res.carbon_credits
+        };
+
+        // Credits received once the claim is open carry no weight on it
+        let claim_height = env.block.height;
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::CreateClaim {
+            longitudes: square_longitudes(1),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash2".to_string()],
+            methodology: "VM0047".to_string(),
+        }).unwrap();
+        let transfer_msg = ExecuteMsg::TransferCredits { recipient: Addr::unchecked("whale"), amount: Uint128::new(80) };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), transfer_msg).unwrap();
+        env.block.height += 1;
+        let res = commit_vote(deps.as_mut(), &env, "whale", 1, VoteOption::Yes).unwrap();
+        assert!(res.attributes.iter().any(|attr| attr.key == "weight" && attr.value == "1"));
+
+        assert_eq!(credits_at(&deps, "whale", Some(claim_height)), Uint128::zero());
+        assert_eq!(credits_at(&deps, "whale", Some(claim_height + 1)), Uint128::new(80));
+        assert_eq!(credits_at(&deps, "whale", None), Uint128::new(80));
+        assert_eq!(credits_at(&deps, "org1", Some(claim_height)), Uint128::new(100));
+
+        // Credits borrowed within the block don't lift the eligibility score
+        let transfer_msg = ExecuteMsg::TransferCredits { recipient: Addr::unchecked("borrower"), amount: Uint128::new(20) };
+        execute(deps.as_mut(), env.clone(), mock_info("whale", &[]), transfer_msg).unwrap();
+        let verify = ExecuteMsg::VerifyEligibility { borrower: Addr::unchecked("borrower"), amount: Uint128::new(10), lender: Addr::unchecked("org1"), proof: None };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify.clone()).unwrap();
+        assert!(res.attributes.iter().any(|attr| attr.key == "eligibility_score" && attr.value == "50"));
+        env.block.height += 1;
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify).unwrap();
+        assert!(res.attributes.iter().any(|attr| attr.key == "eligibility_score" && attr.value == "70"));
+    }
+
+    #[test]
+    fn token_votes_use_tracked_snapshots() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            credit_token: Some(CreditTokenSource::Existing { address: Addr::unchecked("credit_token") }),
+            vote_weights: Some(VoteWeights {
+                base_weight: Uint128::new(1),
+                reputation_weight: Uint128::zero(),
+                credit_weight: Uint128::new(1),
+            }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        // The whale bought its tokens elsewhere, so the contract never saw them arrive
+        mock_credit_token(&mut deps, &[("org1", 100), ("whale", 500)], &[]);
+        add_verifiers(deps.as_mut(), &env, &["voter1", "org1", "whale"]);
+        let lats = ["10.0", "10.0", "11.0", "11.0", "10.0"];
+        create_plot(deps.as_mut(), &env, "org1", &["0.0", "1.0", "1.0", "0.0", "0.0"], &lats, (1000, 2000)).unwrap();
+        commit_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        reveal_vote(deps.as_mut(), &env, "voter1", 0, VoteOption::Yes).unwrap();
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("voter1", &[]), ExecuteMsg::FinalizeVoting { claim_id: 0 }).unwrap();
+        env.block.time = env.block.time.plus_seconds(86401);
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::ReleaseCredits { claim_id: 0 }).unwrap();
+
+        // Tokens released in the claim's creation block carry no weight on it
+        create_plot(deps.as_mut(), &env, "org2", &["2.0", "3.0", "3.0", "2.0", "2.0"], &lats, (1000, 2000)).unwrap();
+        let weight = |res: Response| res.attributes.into_iter().find(|attr| attr.key == "weight").unwrap().value;
+        assert_eq!(weight(commit_vote(deps.as_mut(), &env, "org1", 1, VoteOption::Yes).unwrap()), "1");
+        env.block.height += 1;
+        create_plot(deps.as_mut(), &env, "org2", &["4.0", "5.0", "5.0", "4.0", "4.0"], &lats, (1000, 2000)).unwrap();
+        assert_eq!(weight(commit_vote(deps.as_mut(), &env, "org1", 2, VoteOption::Yes).unwrap()), "101");
+        assert_eq!(weight(commit_vote(deps.as_mut(), &env, "whale", 2, VoteOption::Yes).unwrap()), "1");
+    }
+
+    /// Proof of `threshold` for the score `factors` add up to, for borrowing up to `amount`
+    fn eligibility_proof(proving_key: &ProvingKey<Bn254>, borrower: &str, amount: u128, threshold: u128, factors: &EligibilityFactors) -> EligibilityProof {
+        let circuit = EligibilityCircuit { threshold: Uint128::new(threshold), borrower: Addr::unchecked(borrower), amount: Uint128::new(amount), factors: factors.clone() };
+        let proof = Groth16::<Bn254>::create_random_proof_with_reduction(circuit, proving_key, &mut ark_std::test_rng()).unwrap();
+        let mut bytes = vec![];
+        proof.serialize_compressed(&mut bytes).unwrap();
+        EligibilityProof { proof: Binary::from(bytes), threshold: Uint128::new(threshold) }
+    }
+
+    #[test]
+    fn zero_knowledge_eligibility_proofs() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let setup = EligibilityCircuit { threshold: Uint128::zero(), borrower: Addr::unchecked(""), amount: Uint128::zero(), factors: Default::default() };
+        let proving_key = Groth16::<Bn254>::generate_random_parameters_with_reduction(setup, &mut ark_std::test_rng()).unwrap();
+        let mut verifying_key = vec![];
+        proving_key.vk.serialize_compressed(&mut verifying_key).unwrap();
+        // A borrower with no history scores the base of 70
+        let factors = EligibilityFactors { base: Uint128::new(70), ..Default::default() };
+        // The circuit computes the score from the factors, so 70 can't be proven to reach 100,
+        // nor a score pushed below zero by debt to reach anything
+        let satisfied = |threshold: u128, factors: &EligibilityFactors| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let circuit = EligibilityCircuit { threshold: Uint128::new(threshold), borrower: Addr::unchecked("borrower"), amount: Uint128::new(10), factors: factors.clone() };
+            circuit.generate_constraints(cs.clone()).unwrap();
+            cs.is_satisfied().unwrap()
+        };
+        assert!(satisfied(70, &factors));
+        assert!(!satisfied(100, &factors));
+        assert!(!satisfied(0, &EligibilityFactors { debt: Uint128::new(71), ..factors.clone() }));
+
+        // Without a key, proofs can't be checked
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            eligibility_policy: Some(EligibilityPolicy { base_score: Uint128::new(70), min_score: Uint128::new(20), ..Default::default() }),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        let verify = |proof: Option<EligibilityProof>| ExecuteMsg::VerifyEligibility {
+            borrower: Addr::unchecked("borrower"),
+            amount: Uint128::new(10),
+            lender: Addr::unchecked("org1"),
+            proof,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(Some(eligibility_proof(&proving_key, "borrower", 10, 60, &factors)))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+
+        let set_key = |verifying_key: Vec<u8>| ExecuteMsg::SetEligibilityKey { verifying_key: Binary::from(verifying_key) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), set_key(verifying_key.clone())).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), set_key(vec![1, 2, 3])).unwrap_err();
+        assert_eq!(err, ContractError::InvalidVerifyingKey {});
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), set_key(verifying_key)).unwrap();
+        let query_msg = QueryMsg::SimulateEligibility { borrower: Addr::unchecked("borrower"), lender: Addr::unchecked("org1"), amount: Uint128::new(10) };
+        let res: SimulateEligibilityResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!((res.proof_required, res.eligible), (true, false));
+
+        // Once it is set a proof is required, and it only holds for the threshold and borrower it was made for
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(None)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        let proof = eligibility_proof(&proving_key, "borrower", 10, 60, &factors);
+        let raised = EligibilityProof { threshold: Uint128::new(70), ..proof.clone() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(Some(raised))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        // or a larger amount
+        let larger = ExecuteMsg::VerifyEligibility { borrower: Addr::unchecked("borrower"), amount: Uint128::new(20), lender: Addr::unchecked("org1"), proof: Some(proof.clone()) };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), larger).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        // Nor can it claim credits the borrower doesn't hold, a threshold above its score or one below
+        // the policy minimum
+        let made_up = EligibilityFactors { credits: Uint128::new(30), ..factors.clone() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(Some(eligibility_proof(&proving_key, "borrower", 10, 60, &made_up)))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(Some(eligibility_proof(&proving_key, "borrower", 10, 90, &made_up)))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(Some(eligibility_proof(&proving_key, "borrower", 10, 10, &factors)))).unwrap_err();
+        assert_eq!(err, ContractError::BorrowerNotEligible {});
+        let garbled = EligibilityProof { proof: Binary::from(vec![0; 8]), ..proof.clone() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(Some(garbled))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(Some(proof.clone()))).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "eligibility_score" && a.value == "60"));
+
+        let request = |proof: Option<EligibilityProof>| ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("org1"),
+            amount: Uint128::new(10),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("other", &[]), request(Some(proof.clone()))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request(Some(proof.clone()))).unwrap();
+        let query_msg = QueryMsg::UserLendRequests { user: "org1".to_string(), start_after: None, limit: None };
+        let res: UserLendRequestsResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.lend_requests.len(), 1);
+        assert_eq!((res.lend_requests[0].borrower.as_str(), res.lend_requests[0].eligibility_score), ("borrower", Uint128::new(60)));
+
+        // Borrowing from an offer takes the same proof
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let offer = ExecuteMsg::CreateLendOffer { max_amount: Uint128::new(30), min_eligibility_score: Uint128::new(50), rate: Decimal::zero(), term: 86400 };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), offer).unwrap();
+        let accept_offer = |amount: u128, proof: Option<EligibilityProof>| ExecuteMsg::AcceptLendOffer { offer_id: 0, amount: Uint128::new(amount), proof };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_offer(10, None)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_offer(20, Some(proof.clone()))).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        let res = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_offer(10, Some(proof.clone()))).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "eligibility_score" && a.value == "60"));
+
+        // and so does borrowing from the pool
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::PoolDeposit { amount: Uint128::new(20) }).unwrap();
+        let borrow = |proof: Option<EligibilityProof>| ExecuteMsg::PoolBorrow { amount: Uint128::new(10), proof };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), borrow(None)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProof {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), borrow(Some(proof))).unwrap();
+    }
+
+    #[test]
+    fn eligibility_policy_scoring() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), InstantiateMsg { voting_period: 86400, ..Default::default() }).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let request = |amount: u128| ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("org1"),
+            amount: Uint128::new(amount),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof: None,
+        };
+        let eligibility_score = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, id: usize| -> Uint128 {
+            let query_msg = QueryMsg::UserLendRequests { user: "borrower".to_string(), start_after: None, limit: None };
+            let res: UserLendRequestsResponse = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+            res.lend_requests[id].eligibility_score
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request(40)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Accept }).unwrap();
+        env.block.height += 1;
+
+        // Debt outweighing reputation and returns no longer underflows: 50 + 40 credits - 40 / 2
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request(10)).unwrap();
+        assert_eq!(eligibility_score(&deps, 1), Uint128::new(70));
+
+        let policy = EligibilityPolicy {
+            base_score: Uint128::zero(),
+            debt_weight: Decimal::percent(200),
+            min_score: Uint128::new(30),
+            ..Default::default()
+        };
+        let update = |policy: EligibilityPolicy| ExecuteMsg::UpdateEligibilityPolicy { policy };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), update(policy.clone())).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let inverted = EligibilityPolicy { min_score: Uint128::new(101), ..policy.clone() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), update(inverted)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidEligibilityPolicy {});
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), update(policy.clone())).unwrap();
+        let stored: EligibilityPolicy = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEligibilityPolicy {}).unwrap()).unwrap();
+        assert_eq!(stored, policy);
+
+        // 40 credits - 2 * 40 debt clamps to 0, below the new minimum; the lender's 60 credits clear it
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request(10)).unwrap_err();
+        assert_eq!(err, ContractError::BorrowerNotEligible {});
+        let verify = ExecuteMsg::VerifyEligibility { borrower: Addr::unchecked("org1"), amount: Uint128::new(10), lender: Addr::unchecked("borrower"), proof: None };
+        let res = execute(deps.as_mut(), env, mock_info("org1", &[]), verify).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "eligibility_score" && a.value == "60"));
+    }
+
+    #[test]
+    fn simulate_eligibility_breakdown() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), InstantiateMsg { voting_period: 86400, ..Default::default() }).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let request = ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("org1"),
+            amount: Uint128::new(40),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Accept }).unwrap();
+        env.block.height += 1;
+
+        let simulate = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, env: &Env, amount: u128| -> SimulateEligibilityResponse {
+            let query_msg = QueryMsg::SimulateEligibility {
+                borrower: Addr::unchecked("borrower"),
+                lender: Addr::unchecked("org1"),
+                amount: Uint128::new(amount),
+            };
+            from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap()
+        };
+        let res = simulate(&deps, &env, 10);
+        assert_eq!(res.factors, EligibilityFactors {
+            base: Uint128::new(50),
+            credits: Uint128::new(40),
+            debt: Uint128::new(20),
+            ..Default::default()
+        });
+        assert_eq!((res.score, res.policy_version, res.eligible), (Uint128::new(70), 1, true));
+        // org1 has 60 credits left to lend
+        assert_eq!((simulate(&deps, &env, 60).eligible, simulate(&deps, &env, 61).eligible), (true, false));
+        assert!(!simulate(&deps, &env, 0).eligible);
+
+        // The simulated score matches what a real request records
+        let request = ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("org1"),
+            amount: Uint128::new(10),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request).unwrap();
+        let query_msg = QueryMsg::UserLendRequests { user: "borrower".to_string(), start_after: None, limit: None };
+        let requests: UserLendRequestsResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(requests.lend_requests[1].eligibility_score, res.score);
+
+        // Once proofs are required, only a current record for the amount makes the borrower eligible
+        let update_msg = ExecuteMsg::UpdateConfig {
+            voting_period: None,
+            reveal_period: None,
+            unrevealed_penalty: None,
+            challenge_period: None,
+            challenge_slash: None,
+            vote_weights: None,
+            tally_params: None,
+            governance: None,
+            default_penalty: None,
+            reborrow_reputation: None,
+            collateral: None,
+            pool: None,
+            lend_request_expiry: None,
+            reputation: None,
+            proof_validity: None,
+            require_eligibility_proof: Some(true),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), update_msg).unwrap();
+        let res = simulate(&deps, &env, 10);
+        assert_eq!((res.proof_required, res.eligible), (true, false));
+        let verify = ExecuteMsg::VerifyEligibility {
+            borrower: Addr::unchecked("borrower"),
+            lender: Addr::unchecked("org1"),
+            amount: Uint128::new(10),
+            proof: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify).unwrap();
+        let res = simulate(&deps, &env, 10);
+        assert_eq!((res.proof_required, res.eligible), (false, true));
+        assert!(simulate(&deps, &env, 20).proof_required);
+
+        let policy = EligibilityPolicy { debt_weight: Decimal::percent(200), min_score: Uint128::new(30), ..Default::default() };
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), ExecuteMsg::UpdateEligibilityPolicy { policy }).unwrap();
+        let res = simulate(&deps, &env, 10);
+        assert_eq!(res.factors.debt, Uint128::new(80));
+        assert_eq!((res.score, res.policy_version, res.eligible), (Uint128::new(10), 2, false));
+    }
+
+    #[test]
+    fn eligibility_proof_records() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            voting_period: 86400,
+            proof_validity: Some(3600),
+            require_eligibility_proof: Some(true),
+            ..Default::default()
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        add_verifiers(deps.as_mut(), &env, &["voter1", "voter2", "voter3"]);
+        issue_credits(deps.as_mut(), &mut env, "org1", 0);
+        let request = ExecuteMsg::CreateLendToken {
+            lender: Addr::unchecked("org1"),
+            amount: Uint128::new(10),
+            interest_rate: Decimal::zero(),
+            term: 86400 * 30,
+            proof: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request.clone()).unwrap();
+        let accept = ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Accept };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), accept.clone()).unwrap_err();
+        assert_eq!(err, ContractError::EligibilityProofRequired {});
+
+        let verify = |lender: &str, amount: u128| ExecuteMsg::VerifyEligibility {
+            borrower: Addr::unchecked("borrower"),
+            lender: Addr::unchecked(lender),
+            amount: Uint128::new(amount),
+            proof: None,
+        };
+        // Only the borrower can record its eligibility, so nobody can replace its records
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), verify("org1", 0)).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify("org2", 5)).unwrap();
+        // A proof for a smaller amount does not cover the request
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify("org1", 5)).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), accept.clone()).unwrap_err();
+        assert_eq!(err, ContractError::EligibilityProofRequired {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify("org1", 10)).unwrap();
+
+        let query_msg = QueryMsg::GetEligibilityProof { borrower: Addr::unchecked("borrower"), lender: Addr::unchecked("org1") };
+        let record: EligibilityRecord = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!((record.amount, record.score, record.policy_version, record.zero_knowledge), (Uint128::new(10), Uint128::new(50), 1, false));
+        assert_eq!(record.expires_at, record.created_at + 3600);
+        let query_msg = QueryMsg::GetEligibilityProofs { borrower: Addr::unchecked("borrower"), start_after: None, limit: None };
+        let res: EligibilityProofsResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.proofs.iter().map(|p| p.lender.as_str()).collect::<Vec<_>>(), vec!["org1", "org2"]);
+        let query_msg = QueryMsg::GetEligibilityProofs { borrower: Addr::unchecked("borrower"), start_after: Some(Addr::unchecked("org1")), limit: None };
+        let res: EligibilityProofsResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.proofs.len(), 1);
+
+        // Proofs go stale once they expire or the policy changes
+        env.block.time = env.block.time.plus_seconds(3601);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), accept.clone()).unwrap_err();
+        assert_eq!(err, ContractError::EligibilityProofRequired {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify("org1", 10)).unwrap();
+        let policy = ExecuteMsg::UpdateEligibilityPolicy { policy: EligibilityPolicy { base_score: Uint128::new(60), ..Default::default() } };
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), policy).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), accept.clone()).unwrap_err();
+        assert_eq!(err, ContractError::EligibilityProofRequired {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify("org1", 10)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), accept).unwrap();
+
+        // A counter-offer above the proven amount needs a new proof before the borrower can take it
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), request).unwrap();
+        let counter = ExecuteMsg::LendTokens { lend_request_id: 1, response: LendResponse::Counter { amount: Uint128::new(20) } };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), counter).unwrap();
+        let accept_counter = ExecuteMsg::AcceptCounterOffer { lend_request_id: 1 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_counter.clone()).unwrap_err();
+        assert_eq!(err, ContractError::EligibilityProofRequired {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify("org1", 20)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_counter).unwrap();
+
+        // Offers need a record for the offering lender just the same
+        let offer = ExecuteMsg::CreateLendOffer { max_amount: Uint128::new(30), min_eligibility_score: Uint128::zero(), rate: Decimal::zero(), term: 86400 };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), offer).unwrap();
+        let accept_offer = ExecuteMsg::AcceptLendOffer { offer_id: 0, amount: Uint128::new(30), proof: None };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_offer.clone()).unwrap_err();
+        assert_eq!(err, ContractError::EligibilityProofRequired {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify("org1", 30)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), accept_offer).unwrap();
+
+        // The pool lends as the contract itself
+        issue_credits(deps.as_mut(), &mut env, "org2", 1);
+        execute(deps.as_mut(), env.clone(), mock_info("org2", &[]), ExecuteMsg::PoolDeposit { amount: Uint128::new(20) }).unwrap();
+        let borrow = ExecuteMsg::PoolBorrow { amount: Uint128::new(10), proof: None };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), borrow.clone()).unwrap_err();
+        assert_eq!(err, ContractError::EligibilityProofRequired {});
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), verify(env.contract.address.as_str(), 10)).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("borrower", &[]), borrow).unwrap();
+    }
+
+    #[test]
+    fn migrate_from_baseline_layout() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let storage = deps.as_mut().storage;
+        baseline::CONFIG.save(storage, &baseline::Config {
+            owner: Addr::unchecked("creator"),
+            voting_period: 86400,
+            total_carbon_credits: Uint128::new(100),
+        }).unwrap();
+        baseline::CLAIMS.save(storage, 0, &baseline::Claim {
+            id: 0,
+            organization: Addr::unchecked("org1"),
+            longitudes: square_longitudes(0),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash1".to_string()],
+            status: ClaimStatus::Approved,
+            voting_end_time: env.block.time.seconds(),
+            yes_votes: Uint128::new(1),
+            no_votes: Uint128::zero(),
+        }).unwrap();
+        baseline::CLAIMS.save(storage, 1, &baseline::Claim {
+            id: 1,
+            organization: Addr::unchecked("org1"),
+            longitudes: square_longitudes(5),
+            latitudes: square_latitudes(),
+            time_started: 1000,
+            time_ended: 2000,
+            demanded_tokens: Uint128::new(100),
+            ipfs_hashes: vec!["QmHash2".to_string()],
+            status: ClaimStatus::Active,
+            voting_end_time: env.block.time.seconds() + 100,
+            yes_votes: Uint128::zero(),
+            no_votes: Uint128::zero(),
+        }).unwrap();
+        baseline::VOTES.save(storage, (0, &Addr::unchecked("voter1")), &VoteOption::Yes).unwrap();
+        baseline::LEND_REQUESTS.save(storage, 0, &baseline::LendRequest {
+            id: 0,
+            borrower: Addr::unchecked("borrower"),
+            lender: Addr::unchecked("org1"),
+            amount: Uint128::new(10),
+            eligibility_score: Uint128::new(50),
+            proof_data: "00".to_string(),
+            status: LentStatus::Active,
+            time: env.block.time.seconds(),
+        }).unwrap();
+        baseline::ORGANIZATIONS.save(storage, &Addr::unchecked("org1"), &baseline::OrganizationInfo {
+            reputation_score: Uint128::new(5),
+            carbon_credits: Uint128::new(100),
+            debt: Uint128::zero(),
+            times_borrowed: 0,
+            total_borrowed: Uint128::zero(),
+            total_returned: Uint128::zero(),
+            name: "Org One".to_string(),
+            emissions: Uint128::new(40),
+        }).unwrap();
+        // borrower2 has since repaid 30 of the 50 it borrowed
+        for (id, amount) in [(1u64, 30u128), (2, 20)] {
+            baseline::LEND_REQUESTS.save(storage, id, &baseline::LendRequest {
+                id,
+                borrower: Addr::unchecked("borrower2"),
+                lender: Addr::unchecked("org1"),
+                amount: Uint128::new(amount),
+                eligibility_score: Uint128::new(50),
+                proof_data: "00".to_string(),
+                status: LentStatus::Approved,
+                time: env.block.time.seconds() - 1000,
+            }).unwrap();
+        }
+        baseline::ORGANIZATIONS.save(storage, &Addr::unchecked("borrower2"), &baseline::OrganizationInfo {
+            reputation_score: Uint128::zero(),
+            carbon_credits: Uint128::new(20),
+            debt: Uint128::new(20),
+            times_borrowed: 2,
+            total_borrowed: Uint128::new(50),
+            total_returned: Uint128::new(30),
+            name: "".to_string(),
+            emissions: Uint128::zero(),
+        }).unwrap();
+        baseline::PROOFS.save(storage, (&Addr::unchecked("borrower"), &Addr::unchecked("org1")), &vec![0]).unwrap();
+
+        let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "previous_version" && a.value == "baseline"));
+        let version = get_contract_version(deps.as_ref().storage).unwrap();
+        assert_eq!((version.contract.as_str(), version.version.as_str()), (CONTRACT_NAME, CONTRACT_VERSION));
+
+        let config: ConfigResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetConfig {}).unwrap()).unwrap();
+        assert_eq!((config.owner.as_str(), config.reveal_period, config.total_carbon_credits), ("creator", 86400, Uint128::new(100)));
+        let claim: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: 0 }).unwrap()).unwrap();
+        assert_eq!((claim.status, claim.area.is_zero()), (ClaimStatus::Approved, false));
+        let voting_end_time = env.block.time.seconds() + 100;
+        let claim: ClaimResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetClaim { id: 1 }).unwrap()).unwrap();
+        assert_eq!((claim.reveal_end_time, claim.challenge_end_time), (voting_end_time + 86400, 0));
+        let err = create_plot(deps.as_mut(), &env, "org2", &["0.1", "0.2", "0.2", "0.1", "0.1"], &["10.1", "10.1", "10.2", "10.2", "10.1"], (1500, 2500)).unwrap_err();
+        assert_eq!(err, ContractError::OverlappingClaim { claim_id: 0 });
+        assert!(baseline::PROOFS.may_load(deps.as_ref().storage, (&Addr::unchecked("borrower"), &Addr::unchecked("org1"))).unwrap().is_none());
+
+        // Migrated organizations are snapshotted from the next block, and the open request can be accepted
+        env.block.height += 1;
+        let query_msg = QueryMsg::GetOrganization { address: Addr::unchecked("org1"), at_height: Some(env.block.height) };
+        let org: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!((org.name.as_str(), org.carbon_credits, org.defaults, org.emissions), ("Org One", Uint128::new(100), 0, Uint128::new(40)));
+        // The lifetime total is kept as a report outside any period
+        let report: EmissionsReport = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEmissionsReport { organization: Addr::unchecked("org1"), period: 0, version: None }).unwrap()).unwrap();
+        assert_eq!((report.version, report.gross), (1, Uint128::new(40)));
+        let accept = ExecuteMsg::LendTokens { lend_request_id: 0, response: LendResponse::Accept };
+        execute(deps.as_mut(), env.clone(), mock_info("org1", &[]), accept).unwrap();
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 2 }).unwrap()).unwrap();
+        assert_eq!((loan.principal, loan.due_at), (Uint128::new(10), env.block.time.seconds() + SECONDS_PER_YEAR));
+
+        // Approved requests become loans, with past repayments settling the oldest first
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 0 }).unwrap()).unwrap();
+        assert_eq!((loan.lend_request_id, loan.repaid, loan.status), (Some(1), Uint128::new(30), LoanStatus::Repaid));
+        let loan: Loan = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetLoan { id: 1 }).unwrap()).unwrap();
+        assert_eq!((loan.remaining(), loan.status, loan.created_at), (Uint128::new(20), LoanStatus::Open, env.block.time.seconds() - 1000));
+        execute(deps.as_mut(), env.clone(), mock_info("borrower2", &[]), ExecuteMsg::RepayTokens { loan_id: 1, amount: Uint128::new(20) }).unwrap();
+        let query_msg = QueryMsg::GetOrganization { address: Addr::unchecked("borrower2"), at_height: None };
+        let org: OrganizationResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(org.debt, Uint128::zero());
+
+        // The active claim still has its reveal phase ahead before it can be finalized
+        env.block.time = env.block.time.plus_seconds(101);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::FinalizeVoting { claim_id: 1 }).unwrap_err();
+        assert_eq!(err, ContractError::VotingNotEnded {});
+        env.block.time = env.block.time.plus_seconds(86400);
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::FinalizeVoting { claim_id: 1 }).unwrap();
+
+        // Later migrations find the version and leave the layout alone
+        let res = migrate(deps.as_mut(), env, MigrateMsg {}).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "previous_version" && a.value == CONTRACT_VERSION));
+    }
+
+    #[test]
+    fn migrate_refuses_downgrades() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), InstantiateMsg { voting_period: 86400, ..Default::default() }).unwrap();
+        let version = get_contract_version(deps.as_ref().storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap_err();
+        assert_eq!(err, ContractError::CannotDowngrade { previous_version: "99.0.0".to_string(), version: CONTRACT_VERSION.to_string() });
+        set_contract_version(deps.as_mut().storage, "crates.io:other", "0.0.1").unwrap();
+        let err = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap_err();
+        assert_eq!(err, ContractError::CannotMigrate { previous_contract: "crates.io:other".to_string() });
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.0.1").unwrap();
+        migrate(deps.as_mut(), env, MigrateMsg {}).unwrap();
+    }
+}
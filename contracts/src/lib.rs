@@ -1,8 +1,11 @@
 pub mod contract;
 mod error;
+pub mod geometry;
 pub mod helpers;
 pub mod integration_tests;
+pub mod migrations;
 pub mod msg;
 pub mod state;
+pub mod zk;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,256 @@
+//! Conversion of older storage layouts to the current one, run by `contract::migrate`.
+//!
+//! The baseline release never recorded a cw2 version, so its storage is recognised by the
+//! version being absent. Items it did not have are initialised as `instantiate` would.
+
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, Env, Order, StdResult, Storage, Uint128};
+
+use crate::contract::{new_config, save_initial_state};
+use crate::error::ContractError;
+use crate::geometry::{grid_cells, parse_polygon, polygon_area};
+use crate::msg::InstantiateMsg;
+use crate::state::{Claim, ClaimStatus, EmissionEntry, EmissionScope, EmissionsReport, LendRequest, LentStatus, Loan, LoanStatus, OrganizationInfo, Vote, CLAIMS, CLAIM_CELLS, CONFIG, ELIGIBILITY_POLICY, ELIGIBILITY_POLICY_VERSION, EMISSION_REPORTS, LEND_REQUESTS, loans, LOAN_COUNTER, ORGANIZATIONS, SECONDS_PER_YEAR, UNALLOCATED_EMISSIONS_PERIOD, VOTES};
+
+/// Types and storage keys of the baseline release
+pub mod baseline {
+    use cosmwasm_std::{Addr, Uint128};
+    use cw_storage_plus::{Item, Map};
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+
+    use crate::state::{ClaimStatus, LentStatus, VoteOption};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct Config {
+        pub owner: Addr,
+        pub voting_period: u64,
+        pub total_carbon_credits: Uint128,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct Claim {
+        pub id: u64,
+        pub organization: Addr,
+        pub longitudes: Vec<String>,
+        pub latitudes: Vec<String>,
+        pub time_started: u64,
+        pub time_ended: u64,
+        pub demanded_tokens: Uint128,
+        pub ipfs_hashes: Vec<String>,
+        pub status: ClaimStatus,
+        pub voting_end_time: u64,
+        pub yes_votes: Uint128,
+        pub no_votes: Uint128,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct LendRequest {
+        pub id: u64,
+        pub borrower: Addr,
+        pub lender: Addr,
+        pub amount: Uint128,
+        pub eligibility_score: Uint128,
+        pub proof_data: String,
+        pub status: LentStatus,
+        pub time: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    pub struct OrganizationInfo {
+        pub reputation_score: Uint128,
+        pub carbon_credits: Uint128,
+        pub debt: Uint128,
+        pub times_borrowed: u32,
+        pub total_borrowed: Uint128,
+        pub total_returned: Uint128,
+        pub name: String,
+        pub emissions: Uint128,
+    }
+
+    pub const CONFIG: Item<Config> = Item::new("config");
+    pub const CLAIMS: Map<u64, Claim> = Map::new("claims");
+    pub const LEND_REQUESTS: Map<u64, LendRequest> = Map::new("lend_requests");
+    pub const VOTES: Map<(u64, &Addr), VoteOption> = Map::new("votes");
+    pub const ORGANIZATIONS: Map<&Addr, OrganizationInfo> = Map::new("organizations");
+    pub const PROOFS: Map<(&Addr, &Addr), Vec<u8>> = Map::new("proofs"); // raw mock proofs, without scores
+}
+
+pub fn migrate_from_baseline(storage: &mut dyn Storage, env: &Env) -> Result<(), ContractError> {
+    let now = env.block.time.seconds();
+    let legacy_config = baseline::CONFIG.load(storage)?;
+    let mut config = new_config(legacy_config.owner, &InstantiateMsg {
+        voting_period: legacy_config.voting_period,
+        ..Default::default()
+    });
+    config.total_carbon_credits = legacy_config.total_carbon_credits;
+    CONFIG.save(storage, &config)?;
+    save_initial_state(storage, env)?;
+    ELIGIBILITY_POLICY.save(storage, &Default::default())?;
+    ELIGIBILITY_POLICY_VERSION.save(storage, &1u64)?;
+
+    let claims = baseline::CLAIMS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (id, claim) in claims {
+        // Coordinates were not validated before, so an unparseable polygon keeps a zero area and
+        // stays out of the overlap index
+        let polygon = parse_polygon(&claim.longitudes, &claim.latitudes).ok();
+        let area = polygon.as_ref().map(|polygon| polygon_area(polygon)).unwrap_or_default();
+        let cells = match (&polygon, &claim.status) {
+            (Some(polygon), ClaimStatus::Active | ClaimStatus::Approved) => grid_cells(polygon).unwrap_or_default(),
+            _ => vec![],
+        };
+        for (column, row) in cells {
+            CLAIM_CELLS.save(storage, ((column, row), claim.time_ended, id), &claim.time_started)?;
+        }
+        // Claims still in voting get a reveal phase and, once approved, a challenge window as
+        // create_claim sets them up; decided claims are not open to either
+        let (reveal_end_time, challenge_end_time) = match claim.status {
+            ClaimStatus::Active => (claim.voting_end_time + config.reveal_period, 0),
+            _ => (claim.voting_end_time, claim.voting_end_time),
+        };
+        CLAIMS.save(storage, id, &Claim {
+            id: claim.id,
+            organization: claim.organization,
+            longitudes: claim.longitudes,
+            latitudes: claim.latitudes,
+            area: Uint128::from(area),
+            time_started: claim.time_started,
+            time_ended: claim.time_ended,
+            demanded_tokens: claim.demanded_tokens,
+            ipfs_hashes: claim.ipfs_hashes,
+            methodology: String::new(),
+            status: claim.status,
+            voting_end_time: claim.voting_end_time,
+            reveal_end_time,
+            yes_votes: claim.yes_votes,
+            no_votes: claim.no_votes,
+            challenge_end_time,
+            challenge_id: None,
+            created_height: env.block.height + 1, // first block with migrated organizations
+        })?;
+    }
+
+    // Every vote counted once
+    let votes = baseline::VOTES
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for ((claim_id, voter), vote) in votes {
+        VOTES.save(storage, (claim_id, &voter), &Vote {
+            voter: voter.clone(),
+            vote,
+            weight: Uint128::new(1),
+            timestamp: now,
+        })?;
+    }
+
+    let lend_requests = baseline::LEND_REQUESTS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    // Repayments were only tracked as a drop in the borrower's debt; they settle the oldest loans first
+    let mut repaid: BTreeMap<Addr, Uint128> = BTreeMap::new();
+    for (_, request) in lend_requests.iter().filter(|(_, request)| request.status == LentStatus::Approved) {
+        *repaid.entry(request.borrower.clone()).or_default() += request.amount;
+    }
+    for (borrower, lent) in repaid.iter_mut() {
+        let debt = baseline::ORGANIZATIONS.may_load(storage, borrower)?.map(|info| info.debt).unwrap_or_default();
+        *lent = lent.saturating_sub(debt);
+    }
+    let mut loan_id = 0u64;
+    for (id, request) in lend_requests {
+        if request.status == LentStatus::Approved {
+            let available = repaid.entry(request.borrower.clone()).or_default();
+            let loan_repaid = request.amount.min(*available);
+            *available -= loan_repaid;
+            // As lend_on_request would have opened it, a year's term at zero interest
+            loans().save(storage, loan_id, &Loan {
+                id: loan_id,
+                lend_request_id: Some(id),
+                offer_id: None,
+                borrower: request.borrower.clone(),
+                lender: request.lender.clone(),
+                principal: request.amount,
+                interest_rate: Default::default(),
+                interest: Uint128::zero(),
+                repaid: loan_repaid,
+                status: if loan_repaid == request.amount { LoanStatus::Repaid } else { LoanStatus::Open },
+                created_at: request.time,
+                due_at: request.time + SECONDS_PER_YEAR,
+                last_accrued: request.time,
+                collateral: None,
+            })?;
+            loan_id += 1;
+        }
+        // Open requests get the usual time to answer from now on
+        let expires_at = match request.status {
+            LentStatus::Active => now + config.lend_request_expiry,
+            _ => request.time,
+        };
+        LEND_REQUESTS.save(storage, id, &LendRequest {
+            id: request.id,
+            borrower: request.borrower,
+            lender: request.lender,
+            amount: request.amount,
+            eligibility_score: request.eligibility_score,
+            proof_data: request.proof_data,
+            status: request.status,
+            time: request.time,
+            interest_rate: Default::default(),
+            term: SECONDS_PER_YEAR, // requests had no term, loans on them are due a year after acceptance
+            collateral: None,
+            expires_at,
+            counter_amount: None,
+        })?;
+    }
+    LOAN_COUNTER.save(storage, &loan_id)?;
+
+    let organizations = baseline::ORGANIZATIONS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (address, info) in organizations {
+        // Snapshotting reads the stored value, which has to be in the new layout; history starts here
+        baseline::ORGANIZATIONS.remove(storage, &address);
+        ORGANIZATIONS.save(storage, &address, &OrganizationInfo {
+            reputation_score: info.reputation_score,
+            carbon_credits: info.carbon_credits,
+            debt: info.debt,
+            times_borrowed: info.times_borrowed,
+            total_borrowed: info.total_borrowed,
+            total_returned: info.total_returned,
+            name: info.name,
+            emissions: info.emissions,
+            defaults: 0,
+            reputation_decayed_at: 0,
+        }, env.block.height)?;
+        // Lifetime totals had no period; as AddOrganizationEmission now does, they are reported
+        // outside one so the net figure above stays derivable
+        if !info.emissions.is_zero() {
+            EMISSION_REPORTS.save(storage, (&address, UNALLOCATED_EMISSIONS_PERIOD, 1), &EmissionsReport {
+                organization: address.clone(),
+                period: UNALLOCATED_EMISSIONS_PERIOD,
+                version: 1,
+                entries: vec![EmissionEntry {
+                    scope: EmissionScope::Scope1,
+                    category: "unallocated".to_string(),
+                    amount: info.emissions,
+                }],
+                scope_1: info.emissions,
+                scope_2: Uint128::zero(),
+                scope_3: Uint128::zero(),
+                gross: info.emissions,
+                reported_at: now,
+            })?;
+        }
+    }
+
+    // Old proofs carry no score or expiry to build an eligibility record from
+    let proofs = baseline::PROOFS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (borrower, lender) in proofs {
+        baseline::PROOFS.remove(storage, (&borrower, &lender));
+    }
+    Ok(())
+}
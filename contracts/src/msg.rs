@@ -1,18 +1,105 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128};
+use cw20::{Cw20Coin, Cw20ReceiveMsg, Expiration, MinterResponse};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::{ClaimStatus, OrganizationInfo, VoteOption};
+use crate::state::{Challenge, ClaimStatus, CollateralParams, PoolParams, CreditBatch, ReputationEvent, ReputationParams, EligibilityFactors, EligibilityPolicy, EligibilityRecord, EmissionEntry, EmissionsReport, LendOffer, Listing, Loan, RetirementCertificate, TallyParams, Verifier, VoteOption, VoteWeights};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub voting_period: u64, // in seconds
+    pub reveal_period: Option<u64>, // defaults to voting_period
+    pub unrevealed_penalty: Option<Uint128>,
+    pub challenge_period: Option<u64>, // defaults to voting_period
+    pub challenge_slash: Option<Uint128>,
+    pub vote_weights: Option<VoteWeights>, // defaults to one address, one vote
+    pub tally_params: Option<TallyParams>,
+    pub governance: Option<Addr>,
+    pub credit_token: Option<CreditTokenSource>, // without one, credits stay in the internal ledger
+    pub default_penalty: Option<Uint128>,
+    pub reborrow_reputation: Option<Uint128>,
+    pub collateral: Option<CollateralParams>, // loans are unsecured without one
+    pub pool: Option<PoolParams>,
+    pub lend_request_expiry: Option<u64>, // defaults to a week
+    pub reputation: Option<ReputationParams>,
+    pub eligibility_policy: Option<EligibilityPolicy>,
+    pub eligibility_key: Option<Binary>, // Groth16 verifying key; borrowers must then prove their eligibility
+    pub proof_validity: Option<u64>, // defaults to a day
+    pub require_eligibility_proof: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
+/// Groth16 proof that the borrower's eligibility score is at least `threshold`, see `zk`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EligibilityProof {
+    pub proof: Binary,
+    pub threshold: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+pub enum CreditTokenSource {
+    /// Existing CW20 contract that has this contract as its minter
+    Existing { address: Addr },
+    /// Instantiate a cw20-base contract with this contract as its minter
+    Instantiate {
+        code_id: u64,
+        name: String,
+        symbol: String,
+        decimals: u8,
+        label: String,
+    },
+}
+
+/// Instantiate message of cw20-base
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20InstantiateMsg {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub initial_balances: Vec<Cw20Coin>,
+    pub mint: Option<MinterResponse>,
+}
+
+/// Hooks called through Send on the credit token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Accepts the lend request, forwarding the sent tokens to the borrower
+    LendTokens { lend_request_id: u64 },
+    /// Repays debt, forwarding the sent tokens to the lender
+    RepayTokens { loan_id: u64 },
+    /// Burns the sent tokens as a retirement
+    RetireCredits { beneficiary: Addr, period: u32, reason: String },
+    /// Counters the lend request with the sent amount
+    CounterOffer { lend_request_id: u64 },
+    /// Escrows the sent tokens as a standing lend offer
+    CreateLendOffer { min_eligibility_score: Uint128, rate: Decimal, term: u64 },
+    /// Deposits the sent tokens into the lending pool
+    PoolDeposit {},
+    /// Repays pool debt with the sent tokens
+    PoolRepay {},
+}
+
+/// Lender's answer to a lend request
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LendResponse {
+    Accept,
+    Deny,
+    /// Offers `amount` instead, escrowed until the borrower accepts or cancels
+    Counter { amount: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     UpdateOrganizationName { name: String },
+    /// Adds to the sender's emissions outside any reporting period, as a correction of its
+    /// `UNALLOCATED_EMISSIONS_PERIOD` report
     AddOrganizationEmission { emissions: String },
     CreateClaim {
         longitudes: Vec<String>,
@@ -21,32 +108,236 @@ pub enum ExecuteMsg {
         time_ended: u64,
         demanded_tokens: Uint128,
         ipfs_hashes: Vec<String>,
+        methodology: String,
     },
+    /// Coins sent along are escrowed as collateral
     CreateLendToken {
         lender: Addr,
         amount: Uint128,
+        interest_rate: Decimal, // annual
+        term: u64, // in seconds
+        proof: Option<EligibilityProof>, // required once an eligibility key is set
     },
     
-    CastVote {
+    CommitVote {
+        claim_id: u64,
+        hash: String,
+    },
+    RevealVote {
         claim_id: u64,
         vote: VoteOption,
+        salt: String,
     },
     FinalizeVoting {
         claim_id: u64,
     },
+    ChallengeClaim {
+        claim_id: u64,
+        evidence: String, // IPFS hash
+    },
+    VoteOnChallenge {
+        challenge_id: u64,
+        vote: VoteOption, // Yes upholds the challenge
+    },
+    ResolveChallenge {
+        challenge_id: u64,
+    },
+    /// Pays out the credit tokens held in escrow for an approved claim once its challenge period is over
+    ReleaseCredits {
+        claim_id: u64,
+    },
     LendTokens {
         lend_request_id: u64,
-        response: String,
+        response: LendResponse,
+    },
+    /// Withdraws an unanswered or countered request, refunding any escrow.
+    /// Anyone may settle a request once it has expired.
+    CancelLendRequest {
+        lend_request_id: u64,
+    },
+    /// Borrows the amount the lender countered with; coins sent along top up the collateral
+    AcceptCounterOffer {
+        lend_request_id: u64,
     },
     RepayTokens {
-        lender: Addr,
+        loan_id: u64,
+        amount: Uint128,
+    },
+    /// Defaults a loan past its due date, penalising the borrower
+    MarkDefaulted {
+        loan_id: u64,
+    },
+    /// Lender takes the collateral of a defaulted loan in place of repayment
+    ClaimCollateral {
+        loan_id: u64,
+    },
+    /// Escrows `max_amount` credits for borrowers to draw on
+    CreateLendOffer {
+        max_amount: Uint128,
+        min_eligibility_score: Uint128,
+        rate: Decimal, // annual
+        term: u64, // in seconds
+    },
+    CancelLendOffer {
+        offer_id: u64,
+    },
+    /// Borrows from an offer; coins sent along are escrowed as collateral
+    AcceptLendOffer {
+        offer_id: u64,
         amount: Uint128,
+        proof: Option<EligibilityProof>, // required once an eligibility key is set
     },
+    /// Deposits credits into the lending pool in exchange for pool shares
+    PoolDeposit {
+        amount: Uint128,
+    },
+    /// Redeems pool shares for credits, limited by what is not lent out
+    PoolWithdraw {
+        shares: Uint128,
+    },
+    /// Eligibility records for the pool name this contract as the lender
+    PoolBorrow {
+        amount: Uint128,
+        proof: Option<EligibilityProof>, // required once an eligibility key is set
+    },
+    PoolRepay {
+        amount: Uint128,
+    },
+    /// Records the sender's eligibility to borrow up to `amount` from `lender`; `borrower` must be the sender
     VerifyEligibility {
         borrower: Addr,
         amount: Uint128,
-        lender: Addr, // ZK proof
+        lender: Addr,
+        proof: Option<EligibilityProof>, // required once an eligibility key is set
+    },
+    UpdateConfig {
+        voting_period: Option<u64>,
+        reveal_period: Option<u64>,
+        unrevealed_penalty: Option<Uint128>,
+        challenge_period: Option<u64>,
+        challenge_slash: Option<Uint128>,
+        vote_weights: Option<VoteWeights>,
+        tally_params: Option<TallyParams>,
+        governance: Option<Addr>,
+        default_penalty: Option<Uint128>,
+        reborrow_reputation: Option<Uint128>,
+        collateral: Option<CollateralParams>,
+        pool: Option<PoolParams>,
+        lend_request_expiry: Option<u64>,
+        reputation: Option<ReputationParams>,
+        proof_validity: Option<u64>,
+        require_eligibility_proof: Option<bool>,
+    },
+    /// Replaces the eligibility scoring policy, owner or governance only
+    UpdateEligibilityPolicy {
+        policy: EligibilityPolicy,
+    },
+    /// Groth16 verifying key of `zk::EligibilityCircuit`
+    SetEligibilityKey {
+        verifying_key: Binary,
+    },
+    AddVerifier {
+        address: Addr,
+        name: String,
+        metadata: String,
+        accredited_until: u64,
+    },
+    RemoveVerifier {
+        address: Addr,
+    },
+    Receive(Cw20ReceiveMsg),
+    /// CW721 transfer of a credit batch, together with the credits it backs
+    TransferNft {
+        recipient: Addr,
+        token_id: String,
+    },
+    /// CW721 send of a credit batch to a contract implementing `ReceiveNft`
+    SendNft {
+        contract: Addr,
+        token_id: String,
+        msg: Binary,
+    },
+    /// CW721 approval for `spender` to transfer or send one batch, until the batch changes hands
+    Approve {
+        spender: Addr,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    Revoke {
+        spender: Addr,
+        token_id: String,
+    },
+    /// CW721 approval for `operator` to transfer or send any of the sender's batches
+    ApproveAll {
+        operator: Addr,
+        expires: Option<Expiration>,
+    },
+    RevokeAll {
+        operator: Addr,
+    },
+    /// Moves `amount` tonnes off the end of a batch into a new batch with the same owner
+    SplitBatch {
+        token_id: String,
+        amount: Uint128,
     },
+    RetireCredits {
+        amount: Uint128,
+        beneficiary: Addr, // whose emissions are offset
+        period: u32, // reporting period the offset counts towards
+        reason: String,
+    },
+    /// Reports the sender's emissions for a period, superseding any earlier report for it
+    ReportEmissions {
+        period: u32,
+        entries: Vec<EmissionEntry>,
+    },
+    /// Moves credits in the internal ledger, as CW20 Transfer
+    TransferCredits {
+        recipient: Addr,
+        amount: Uint128,
+    },
+    IncreaseAllowance {
+        spender: Addr,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    DecreaseAllowance {
+        spender: Addr,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    TransferFrom {
+        owner: Addr,
+        recipient: Addr,
+        amount: Uint128,
+    },
+    /// Escrows `amount` credits for sale at `price` per credit
+    CreateListing {
+        amount: Uint128,
+        price: Coin,
+    },
+    CancelListing {
+        listing_id: u64,
+    },
+    /// Buys `amount` credits from a listing; exactly amount * price must be attached
+    FillListing {
+        listing_id: u64,
+        amount: Uint128,
+    },
+}
+
+/// Sent to the receiving contract by SendNft, as in CW721
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw721ReceiveMsg {
+    pub sender: String,
+    pub token_id: String,
+    pub msg: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721ReceiverMsg {
+    ReceiveNft(Cw721ReceiveMsg),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -64,6 +355,7 @@ pub enum QueryMsg {
     GetAllOrganizations { start_after: Option<Addr>, limit: Option<u32> },
     GetOrganization {
         address: Addr,
+        at_height: Option<u64>, // state as of the start of this block, current state if unset
     },
     GetTotalCarbonCredits {},
     GetClaims {
@@ -75,13 +367,202 @@ pub enum QueryMsg {
         start_after: Option<u64>,
         limit: Option<u32>,
     },
+    GetChallenge {
+        id: u64,
+    },
+    GetChallenges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetVerifier {
+        address: Addr,
+    },
+    GetVerifiers {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    GetBatch {
+        id: u64,
+    },
+    GetBatchesByOwner {
+        owner: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetBatchesByClaim {
+        claim_id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// CW721 queries over credit batches
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    Approval {
+        token_id: String,
+        spender: Addr,
+        include_expired: Option<bool>,
+    },
+    Approvals {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    AllOperators {
+        owner: Addr,
+        include_expired: Option<bool>,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    NumTokens {},
+    ContractInfo {},
+    NftInfo {
+        token_id: String,
+    },
+    AllNftInfo {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    Tokens {
+        owner: Addr,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    AllTokens {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    GetRetirement {
+        id: u64,
+    },
+    GetRetirements {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetRetirementsByBeneficiary {
+        beneficiary: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Latest version unless one is given
+    GetEmissionsReport {
+        organization: Addr,
+        period: u32,
+        version: Option<u32>,
+    },
+    GetEmissionsReportHistory {
+        organization: Addr,
+        period: u32,
+    },
+    GetNetPosition {
+        organization: Addr,
+        period: u32,
+    },
+    GetNetPositions {
+        organization: Addr,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    /// Returns cw20::AllowanceResponse
+    GetAllowance {
+        owner: Addr,
+        spender: Addr,
+    },
+    /// Returns cw20::AllAllowancesResponse
+    GetAllAllowances {
+        owner: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    GetListing {
+        id: u64,
+    },
+    GetListingsBySeller {
+        seller: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Listings in `denom`, cheapest first
+    GetOrderBook {
+        denom: String,
+        start_after: Option<(Uint128, u64)>, // (price, listing id) of the last listing on the previous page
+        limit: Option<u32>,
+    },
+    /// Interest is shown accrued up to the current block
+    GetLoan {
+        id: u64,
+    },
+    GetLoanHealth {
+        id: u64,
+    },
+    GetPool {},
+    GetPoolPosition {
+        address: Addr,
+    },
+    GetLendOffer {
+        id: u64,
+    },
+    /// Offers open to a borrower with `eligibility_score`, or all offers without one
+    GetLendOffers {
+        eligibility_score: Option<Uint128>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetOpenLoansByBorrower {
+        borrower: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetOpenLoansByLender {
+        lender: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetEligibilityPolicy {},
+    /// Score a `CreateLendToken` from `borrower` would get now, and whether `lender` could accept it
+    SimulateEligibility {
+        borrower: Addr,
+        lender: Addr,
+        amount: Uint128,
+    },
+    GetEligibilityProof {
+        borrower: Addr,
+        lender: Addr,
+    },
+    /// Proofs recorded for `borrower`, ordered by lender
+    GetEligibilityProofs {
+        borrower: Addr,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    GetReputationEvents {
+        address: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     pub owner: Addr,
     pub voting_period: u64,
+    pub reveal_period: u64,
+    pub unrevealed_penalty: Uint128,
+    pub challenge_period: u64,
+    pub challenge_slash: Uint128,
     pub total_carbon_credits: Uint128,
+    pub vote_weights: VoteWeights,
+    pub tally_params: TallyParams,
+    pub governance: Option<Addr>,
+    pub credit_token: Option<Addr>,
+    pub default_penalty: Uint128,
+    pub reborrow_reputation: Uint128,
+    pub collateral: Option<CollateralParams>,
+    pub pool: PoolParams,
+    pub lend_request_expiry: u64,
+    pub reputation: ReputationParams,
+    pub proof_validity: u64,
+    pub require_eligibility_proof: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -90,14 +571,19 @@ pub struct ClaimResponse {
     pub organization: Addr,
     pub longitudes: Vec<String>,
     pub latitudes: Vec<String>,
+    pub area: Uint128,
     pub time_started: u64,
     pub time_ended: u64,
     pub demanded_tokens: Uint128,
     pub ipfs_hashes: Vec<String>,
+    pub methodology: String,
     pub status: ClaimStatus,
     pub voting_end_time: u64,
+    pub reveal_end_time: u64,
     pub yes_votes: Uint128,
     pub no_votes: Uint128,
+    pub challenge_end_time: u64,
+    pub challenge_id: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -111,6 +597,23 @@ pub struct OrganizationResponse {
     pub total_returned: Uint128,
     pub name: String,
     pub emissions: Uint128,
+    pub defaults: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ChallengesResponse {
+    pub challenges: Vec<Challenge>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VerifierResponse {
+    pub verifier: Verifier,
+    pub accredited: bool, // accreditation has not expired yet
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VerifiersResponse {
+    pub verifiers: Vec<VerifierResponse>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -134,4 +637,158 @@ pub struct OrganizationListItem {
     pub name: String,
     pub reputation_score: Uint128,
     pub carbon_credits: Uint128,
-}
\ No newline at end of file
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BatchesResponse {
+    pub batches: Vec<CreditBatch>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftApproval {
+    pub spender: Addr,
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerOfResponse {
+    pub owner: Addr,
+    pub approvals: Vec<NftApproval>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ApprovalResponse {
+    pub approval: NftApproval,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ApprovalsResponse {
+    pub approvals: Vec<NftApproval>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OperatorsResponse {
+    pub operators: Vec<NftApproval>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractInfoResponse {
+    pub name: String,
+    pub symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NftInfoResponse {
+    pub token_uri: Option<String>,
+    pub extension: CreditBatch,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllNftInfoResponse {
+    pub access: OwnerOfResponse,
+    pub info: NftInfoResponse,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokensResponse {
+    pub tokens: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NumTokensResponse {
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RetirementsResponse {
+    pub retirements: Vec<RetirementCertificate>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionsReportsResponse {
+    pub reports: Vec<EmissionsReport>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NetPositionResponse {
+    pub organization: Addr,
+    pub period: u32,
+    pub reported: bool, // a report exists for the period
+    pub gross_emissions: Uint128,
+    pub retired_offsets: Uint128,
+    pub net_emissions: Uint128, // gross emissions not yet offset
+    pub surplus_offsets: Uint128, // offsets beyond gross emissions
+    pub net_zero: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NetPositionsResponse {
+    pub positions: Vec<NetPositionResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListingsResponse {
+    pub listings: Vec<Listing>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LoansResponse {
+    pub loans: Vec<Loan>,
+}
+
+/// Pool state with interest accrued up to the current block
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolResponse {
+    pub total_shares: Uint128,
+    pub available: Uint128,
+    pub borrowed: Uint128,
+    pub total_assets: Uint128,
+    pub utilisation: Decimal,
+    pub borrow_rate: Decimal, // annual
+    pub borrow_index: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolPositionResponse {
+    pub address: Addr,
+    pub shares: Uint128,
+    pub value: Uint128, // credits the shares are worth
+    pub debt: Uint128, // owed to the pool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LendOffersResponse {
+    pub offers: Vec<LendOffer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LoanHealthResponse {
+    pub loan_id: u64,
+    pub remaining: Uint128,
+    pub collateral: Option<Coin>,
+    pub required_collateral: Uint128, // for the remaining balance at the configured ratio
+    pub health: Option<Decimal>, // collateral / required_collateral, below 1 when undercollateralised
+    pub overdue: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReputationEventsResponse {
+    pub events: Vec<ReputationEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateEligibilityResponse {
+    pub borrower: Addr,
+    pub lender: Addr,
+    pub amount: Uint128,
+    pub score: Uint128,
+    pub factors: EligibilityFactors,
+    pub policy_version: u64,
+    pub min_score: Uint128,
+    pub proof_required: bool, // a zero-knowledge proof or a current eligibility record is still missing
+    pub eligible: bool, // scores at least min_score, is not barred by past defaults, the lender holds `amount` and no proof is missing
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EligibilityProofsResponse {
+    pub proofs: Vec<EligibilityRecord>,
+}
@@ -1,5 +1,6 @@
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, StdError, StdResult, Uint128};
+use cw20::{AllowanceResponse, Expiration};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotMap, Strategy};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -7,7 +8,185 @@ use serde::{Deserialize, Serialize};
 pub struct Config {
     pub owner: Addr,
     pub voting_period: u64, // in seconds
+    pub reveal_period: u64, // in seconds, follows the voting (commit) period
+    pub unrevealed_penalty: Uint128, // reputation lost for a commit that is never revealed
+    pub challenge_period: u64, // in seconds, after approval
+    pub challenge_slash: Uint128, // reputation lost by the claimant and its yes voters when a challenge is upheld
     pub total_carbon_credits: Uint128,
+    pub vote_weights: VoteWeights,
+    pub tally_params: TallyParams,
+    pub governance: Option<Addr>, // may manage verifiers alongside the owner
+    pub credit_token: Option<Addr>, // CW20 contract credits are minted on; None keeps them in the internal ledger
+    pub default_penalty: Uint128, // reputation lost by a borrower when a loan defaults
+    pub reborrow_reputation: Uint128, // reputation a borrower with past defaults needs before borrowing again
+    pub collateral: Option<CollateralParams>, // None refuses collateral
+    pub pool: PoolParams,
+    pub lend_request_expiry: u64, // in seconds, after which lenders can no longer respond
+    pub reputation: ReputationParams,
+    pub proof_validity: u64, // in seconds, after which an eligibility proof is stale
+    pub require_eligibility_proof: bool, // lenders may only accept requests backed by a current proof
+}
+
+/// Native coin accepted as loan collateral
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CollateralParams {
+    pub denom: String,
+    /// Collateral required per credit owed
+    pub ratio: Decimal,
+}
+
+/// Weight of a vote = base_weight + reputation_score * reputation_weight + carbon_credits * credit_weight
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteWeights {
+    pub base_weight: Uint128,
+    pub reputation_weight: Uint128,
+    pub credit_weight: Uint128,
+}
+
+impl Default for VoteWeights {
+    fn default() -> Self {
+        // One address, one vote
+        VoteWeights {
+            base_weight: Uint128::new(1),
+            reputation_weight: Uint128::zero(),
+            credit_weight: Uint128::zero(),
+        }
+    }
+}
+
+/// Rules applied by FinalizeVoting, all measured in vote weight
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TallyParams {
+    /// Minimum total weight cast for the result to count
+    pub quorum: Uint128,
+    /// Share of yes votes that must be exceeded for approval
+    pub threshold: Decimal,
+    /// Share of no votes above which the claim is rejected regardless of the threshold
+    pub veto_threshold: Decimal,
+}
+
+impl Default for TallyParams {
+    fn default() -> Self {
+        TallyParams {
+            quorum: Uint128::new(1),
+            threshold: Decimal::percent(50),
+            veto_threshold: Decimal::permille(334),
+        }
+    }
+}
+
+/// How verifiers earn and lose reputation on claim votes, and how it fades over time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReputationParams {
+    pub correct_vote_reward: Uint128,
+    /// Lost for voting against the final outcome
+    pub incorrect_vote_slash: Uint128,
+    /// Lost by accredited verifiers that neither committed nor revealed a vote on a claim
+    pub absent_penalty: Uint128,
+    /// Share of reputation lost every `decay_period`
+    pub decay_rate: Decimal,
+    pub decay_period: u64, // in seconds
+}
+
+impl Default for ReputationParams {
+    fn default() -> Self {
+        ReputationParams {
+            correct_vote_reward: Uint128::new(1),
+            incorrect_vote_slash: Uint128::new(1),
+            absent_penalty: Uint128::zero(),
+            decay_rate: Decimal::zero(),
+            decay_period: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// Eligibility score = base_score + reputation * reputation_weight + total_returned * returned_weight
+///     + carbon_credits * credit_weight - debt * debt_weight - emissions * emissions_weight,
+/// clamped between 0 and max_score. Borrowers scoring below min_score are not eligible.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EligibilityPolicy {
+    pub base_score: Uint128,
+    pub reputation_weight: Decimal,
+    pub returned_weight: Decimal,
+    pub credit_weight: Decimal,
+    pub debt_weight: Decimal,
+    pub emissions_weight: Decimal,
+    pub max_score: Uint128,
+    pub min_score: Uint128,
+}
+
+impl Default for EligibilityPolicy {
+    fn default() -> Self {
+        EligibilityPolicy {
+            base_score: Uint128::new(50),
+            reputation_weight: Decimal::one(),
+            returned_weight: Decimal::one(),
+            credit_weight: Decimal::one(),
+            debt_weight: Decimal::percent(50),
+            emissions_weight: Decimal::percent(10),
+            max_score: Uint128::new(100),
+            min_score: Uint128::new(1),
+        }
+    }
+}
+
+/// What each factor contributes to an eligibility score; debt and emissions are deducted
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct EligibilityFactors {
+    pub base: Uint128,
+    pub reputation: Uint128,
+    pub returns: Uint128,
+    pub credits: Uint128,
+    pub debt: Uint128,
+    pub emissions: Uint128,
+}
+
+impl EligibilityPolicy {
+    pub fn factors(&self, org_info: &OrganizationInfo) -> StdResult<EligibilityFactors> {
+        let weighted = |value: Uint128, weight: Decimal| {
+            value
+                .checked_multiply_ratio(weight.atomics(), Decimal::one().atomics())
+                .map_err(|_| StdError::generic_err("Eligibility score overflow"))
+        };
+        Ok(EligibilityFactors {
+            base: self.base_score,
+            reputation: weighted(org_info.reputation_score, self.reputation_weight)?,
+            returns: weighted(org_info.total_returned, self.returned_weight)?,
+            credits: weighted(org_info.carbon_credits, self.credit_weight)?,
+            debt: weighted(org_info.debt, self.debt_weight)?,
+            emissions: weighted(org_info.emissions, self.emissions_weight)?,
+        })
+    }
+
+    /// Gains and losses are summed separately with checked arithmetic, so a negative total clamps to 0
+    pub fn score(&self, org_info: &OrganizationInfo) -> StdResult<Uint128> {
+        let factors = self.factors(org_info)?;
+        let gains = factors.base
+            .checked_add(factors.reputation)?
+            .checked_add(factors.returns)?
+            .checked_add(factors.credits)?;
+        let losses = factors.debt.checked_add(factors.emissions)?;
+        Ok(gains.saturating_sub(losses).min(self.max_score))
+    }
+}
+
+/// Lending pool borrow rate = base_rate + utilisation_rate * utilisation, both annual
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolParams {
+    pub base_rate: Decimal,
+    pub utilisation_rate: Decimal,
+    /// Eligibility score needed to borrow from the pool
+    pub min_eligibility_score: Uint128,
+}
+
+impl Default for PoolParams {
+    fn default() -> Self {
+        PoolParams {
+            base_rate: Decimal::percent(2),
+            utilisation_rate: Decimal::percent(20),
+            min_eligibility_score: Uint128::new(50),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -20,6 +199,15 @@ pub enum VoteOption {
 pub struct Vote {
     pub voter: Addr,
     pub vote: VoteOption,
+    pub weight: Uint128,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoteCommit {
+    pub voter: Addr,
+    pub hash: String, // hex sha256, see contract::vote_commitment
+    pub weight: Uint128,
     pub timestamp: u64,
 }
 
@@ -28,6 +216,30 @@ pub enum ClaimStatus {
     Active,
     Approved,
     Rejected,
+    Expired, // voting ended without quorum
+    Challenged, // approved, with an open challenge
+    Revoked, // approved, then overturned by a challenge
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub enum ChallengeStatus {
+    Open,
+    Upheld,
+    Dismissed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Challenge {
+    pub id: u64,
+    pub claim_id: u64,
+    pub challenger: Addr,
+    pub evidence: String, // IPFS hash
+    pub status: ChallengeStatus,
+    pub created_at: u64,
+    pub created_height: u64, // votes are weighted by organization state as of this block
+    pub voting_end_time: u64,
+    pub uphold_votes: Uint128,
+    pub dismiss_votes: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -35,6 +247,10 @@ pub enum LentStatus {
     Active, 
     Approved,
     Rejected,
+    Defaulted, // approved, then not repaid by the due date
+    Countered, // the lender offered a different amount, awaiting the borrower
+    Cancelled,
+    Expired,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -43,14 +259,331 @@ pub struct Claim {
     pub organization: Addr,
     pub longitudes: Vec<String>,
     pub latitudes: Vec<String>,
+    pub area: Uint128, // square microdegrees
     pub time_started: u64,
     pub time_ended: u64,
     pub demanded_tokens: Uint128,
     pub ipfs_hashes: Vec<String>,
+    pub methodology: String, // e.g. "VM0047"
     pub status: ClaimStatus,
     pub voting_end_time: u64,
+    pub reveal_end_time: u64,
     pub yes_votes: Uint128,
     pub no_votes: Uint128,
+    pub challenge_end_time: u64, // set on approval
+    pub challenge_id: Option<u64>,
+    pub created_height: u64, // votes are weighted by organization state as of this block
+}
+
+/// Serialised tonnes issued for an approved claim. Each batch is a CW721 token whose
+/// token id is the batch id; splitting carves the top of the serial range into a new batch.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CreditBatch {
+    pub id: u64,
+    pub claim_id: u64,
+    pub owner: Addr,
+    pub polygon_hash: String, // hex sha256 of the claim's coordinates
+    pub vintage_start: u64, // claim time_started
+    pub vintage_end: u64, // claim time_ended
+    pub serial_start: Uint128,
+    pub serial_end: Uint128, // inclusive
+    pub methodology: String,
+    pub parent_id: Option<u64>, // batch this one was split from
+}
+
+impl CreditBatch {
+    pub fn amount(&self) -> Uint128 {
+        self.serial_end - self.serial_start + Uint128::new(1)
+    }
+}
+
+pub struct CreditBatchIndexes<'a> {
+    pub owner: MultiIndex<'a, Addr, CreditBatch, u64>,
+    pub claim: MultiIndex<'a, u64, CreditBatch, u64>,
+}
+
+impl<'a> IndexList<CreditBatch> for CreditBatchIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CreditBatch>> + '_> {
+        let v: Vec<&dyn Index<CreditBatch>> = vec![&self.owner, &self.claim];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Batches are the serials behind the credit balances: in the internal ledger every holder's
+/// batches add up to at most its balance, with credit tokens the contract keeps them as the
+/// register of serials still outstanding
+pub fn credit_batches<'a>() -> IndexedMap<'a, u64, CreditBatch, CreditBatchIndexes<'a>> {
+    let indexes = CreditBatchIndexes {
+        owner: MultiIndex::new(|batch| batch.owner.clone(), "credit_batches", "credit_batches__owner"),
+        claim: MultiIndex::new(|batch| batch.claim_id, "credit_batches", "credit_batches__claim"),
+    };
+    IndexedMap::new("credit_batches", indexes)
+}
+
+/// Permanent record of credits used to offset the beneficiary's emissions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RetirementCertificate {
+    pub id: u64,
+    pub retiree: Addr,
+    pub beneficiary: Addr,
+    pub amount: Uint128,
+    pub reason: String,
+    pub serials: Vec<RetiredSerials>, // provenance of the tonnes that had a batch
+    pub period: u32, // reporting period of the beneficiary's emissions being offset
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RetiredSerials {
+    pub batch_id: u64,
+    pub claim_id: u64,
+    pub serial_start: Uint128,
+    pub serial_end: Uint128, // inclusive
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmissionScope {
+    Scope1, // direct
+    Scope2, // purchased energy
+    Scope3, // value chain
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionEntry {
+    pub scope: EmissionScope,
+    pub category: String, // e.g. "stationary combustion"
+    pub amount: Uint128, // tonnes CO2e
+}
+
+/// An organization's emissions for one reporting period. Each correction is stored as a new
+/// version; the highest version supersedes the earlier ones.
+///
+/// Reports hold the only gross figures; `OrganizationInfo::emissions` is derived from them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionsReport {
+    pub organization: Addr,
+    pub period: u32, // e.g. 2024
+    pub version: u32,
+    pub entries: Vec<EmissionEntry>,
+    pub scope_1: Uint128,
+    pub scope_2: Uint128,
+    pub scope_3: Uint128,
+    pub gross: Uint128,
+    pub reported_at: u64,
+}
+
+/// Credits offered for sale, escrowed by the contract until filled or cancelled
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Listing {
+    pub id: u64,
+    pub seller: Addr,
+    pub amount: Uint128, // credits still for sale
+    pub price: Uint128, // per credit
+    pub denom: String,
+    pub created_at: u64,
+}
+
+pub struct ListingIndexes<'a> {
+    pub seller: MultiIndex<'a, Addr, Listing, u64>,
+    /// (denom, price), so each denom's order book is ranged cheapest first
+    pub price: MultiIndex<'a, (String, u128), Listing, u64>,
+}
+
+impl<'a> IndexList<Listing> for ListingIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Listing>> + '_> {
+        let v: Vec<&dyn Index<Listing>> = vec![&self.seller, &self.price];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn listings<'a>() -> IndexedMap<'a, u64, Listing, ListingIndexes<'a>> {
+    let indexes = ListingIndexes {
+        seller: MultiIndex::new(|listing| listing.seller.clone(), "listings", "listings__seller"),
+        price: MultiIndex::new(|listing| (listing.denom.clone(), listing.price.u128()), "listings", "listings__price"),
+    };
+    IndexedMap::new("listings", indexes)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LoanStatus {
+    Open,
+    Repaid,
+    Defaulted, // still owed, past the due date
+    Liquidated, // the lender took the collateral after default
+}
+
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Credits owed by a borrower to one lender, opened when a lend request or a lend offer is accepted
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Loan {
+    pub id: u64,
+    pub lend_request_id: Option<u64>,
+    pub offer_id: Option<u64>,
+    pub borrower: Addr,
+    pub lender: Addr,
+    pub principal: Uint128,
+    pub interest_rate: Decimal, // annual, simple interest on the outstanding balance
+    pub interest: Uint128, // accrued up to `last_accrued`
+    pub repaid: Uint128,
+    pub status: LoanStatus,
+    pub created_at: u64,
+    pub due_at: u64,
+    pub last_accrued: u64,
+    pub collateral: Option<Coin>, // held in escrow until repaid or claimed by the lender
+}
+
+impl Loan {
+    pub fn remaining(&self) -> Uint128 {
+        self.principal + self.interest - self.repaid
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self.status, LoanStatus::Repaid | LoanStatus::Liquidated)
+    }
+
+    /// 1 while the loan is open, 0 once closed, for the loan indexes
+    pub fn open_key(&self) -> u8 {
+        u8::from(!self.is_closed())
+    }
+
+    /// Accrues interest up to `now` and returns the amount added
+    pub fn accrue(&mut self, now: u64) -> Uint128 {
+        if self.is_closed() || now <= self.last_accrued {
+            return Uint128::zero();
+        }
+        let elapsed = Decimal::from_ratio(now - self.last_accrued, SECONDS_PER_YEAR);
+        let accrued = self.remaining() * (self.interest_rate * elapsed);
+        // Keep the clock running until a whole credit has accrued, so frequent calls can't round interest away
+        if !accrued.is_zero() {
+            self.interest += accrued;
+            self.last_accrued = now;
+        }
+        accrued
+    }
+
+    pub fn is_overdue(&self, now: u64) -> bool {
+        self.status == LoanStatus::Open && now > self.due_at
+    }
+}
+
+pub struct LoanIndexes<'a> {
+    /// (borrower, Loan::open_key), so a borrower's open loans are ranged without its closed ones
+    pub borrower: MultiIndex<'a, (Addr, u8), Loan, u64>,
+    /// (lender, Loan::open_key)
+    pub lender: MultiIndex<'a, (Addr, u8), Loan, u64>,
+}
+
+impl<'a> IndexList<Loan> for LoanIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Loan>> + '_> {
+        let v: Vec<&dyn Index<Loan>> = vec![&self.borrower, &self.lender];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn loans<'a>() -> IndexedMap<'a, u64, Loan, LoanIndexes<'a>> {
+    let indexes = LoanIndexes {
+        borrower: MultiIndex::new(|loan| (loan.borrower.clone(), loan.open_key()), "loans", "loans__borrower"),
+        lender: MultiIndex::new(|loan| (loan.lender.clone(), loan.open_key()), "loans", "loans__lender"),
+    };
+    IndexedMap::new("loans", indexes)
+}
+
+/// Credits a lender has set aside for any borrower scoring at least `min_eligibility_score`,
+/// escrowed by the contract until borrowed or cancelled
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LendOffer {
+    pub id: u64,
+    pub lender: Addr,
+    pub max_amount: Uint128, // still available
+    pub min_eligibility_score: Uint128,
+    pub rate: Decimal, // annual interest rate
+    pub term: u64, // in seconds
+    pub created_at: u64,
+}
+
+/// Credits deposited for pooled lending. Depositors own the pool pro rata through shares, and
+/// borrowers owe `debt_shares * borrow_index`, so interest accrues by growing the index.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LendingPool {
+    pub total_shares: Uint128,
+    pub available: Uint128, // credits held by the pool, not lent out
+    pub total_debt_shares: Uint128,
+    pub borrow_index: Decimal,
+    pub last_accrued: u64,
+}
+
+impl LendingPool {
+    pub fn borrowed(&self) -> Uint128 {
+        self.debt_value(self.total_debt_shares)
+    }
+
+    /// Credits the depositors own, lent out or not
+    pub fn total_assets(&self) -> Uint128 {
+        self.available + self.borrowed()
+    }
+
+    pub fn utilisation(&self) -> Decimal {
+        let total = self.total_assets();
+        if total.is_zero() {
+            return Decimal::zero();
+        }
+        Decimal::from_ratio(self.borrowed(), total)
+    }
+
+    pub fn borrow_rate(&self, params: &PoolParams) -> Decimal {
+        params.base_rate + params.utilisation_rate * self.utilisation()
+    }
+
+    pub fn accrue(&mut self, params: &PoolParams, now: u64) {
+        if now <= self.last_accrued {
+            return;
+        }
+        let growth = self.borrow_rate(params) * Decimal::from_ratio(now - self.last_accrued, SECONDS_PER_YEAR);
+        self.borrow_index *= Decimal::one() + growth;
+        self.last_accrued = now;
+    }
+
+    pub fn debt_value(&self, debt_shares: Uint128) -> Uint128 {
+        debt_shares * self.borrow_index
+    }
+
+    /// Debt shares worth `amount`, rounded up when `round_up` so borrowers never owe less than they took
+    pub fn debt_shares(&self, amount: Uint128, round_up: bool) -> Uint128 {
+        let shares = amount.multiply_ratio(Decimal::one().atomics(), self.borrow_index.atomics());
+        if round_up && self.debt_value(shares) < amount {
+            shares + Uint128::one()
+        } else {
+            shares
+        }
+    }
+
+    pub fn share_value(&self, shares: Uint128) -> Uint128 {
+        if self.total_shares.is_zero() {
+            return Uint128::zero();
+        }
+        shares.multiply_ratio(self.total_assets(), self.total_shares)
+    }
+}
+
+/// A borrower's pool debt. `accounted` is the part already added to `OrganizationInfo::debt`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PoolDebt {
+    pub debt_shares: Uint128,
+    pub accounted: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Verifier {
+    pub address: Addr,
+    pub name: String,
+    pub metadata: String, // accreditation body, certificate URI, etc.
+    pub accredited_until: u64, // in seconds
+    pub added_at: u64,
+    pub votes_cast: u64, // on claims that reached a decision
+    pub correct_votes: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -63,10 +596,44 @@ pub struct LendRequest {
     pub proof_data: String, 
     pub status: LentStatus,
     pub time: u64,
+    pub interest_rate: Decimal, // annual
+    pub term: u64, // in seconds, counted from acceptance
+    pub collateral: Option<Coin>, // escrowed with the request, refunded if it is denied or cancelled
+    pub expires_at: u64,
+    pub counter_amount: Option<Uint128>, // escrowed from the lender until the borrower accepts or cancels
 
 }
 
+/// Latest eligibility verification of a borrower towards a lender
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EligibilityRecord {
+    pub borrower: Addr,
+    pub lender: Addr,
+    pub amount: Uint128,
+    /// Proven threshold for zero-knowledge proofs
+    pub score: Uint128,
+    pub policy_version: u64,
+    pub proof: Binary, // empty unless zero_knowledge
+    pub zero_knowledge: bool,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+impl EligibilityRecord {
+    /// Whether the record can still back a loan of `amount` under `policy_version`
+    pub fn is_current(&self, amount: Uint128, policy_version: u64, now: u64) -> bool {
+        now <= self.expires_at && self.policy_version == policy_version && amount <= self.amount
+    }
+}
+
+impl LendRequest {
+    /// Whether a request still awaiting an answer has run out of time
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.status, LentStatus::Active | LentStatus::Countered) && now > self.expires_at
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 pub struct OrganizationInfo {
     pub reputation_score: Uint128,
     pub carbon_credits: Uint128,
@@ -75,7 +642,65 @@ pub struct OrganizationInfo {
     pub total_borrowed: Uint128,
     pub total_returned: Uint128,
     pub name: String,
-    pub emissions: Uint128,
+    pub emissions: Uint128, // net lifetime emissions: each period's latest report less its offsets
+    pub defaults: u32, // loans not repaid by their due date
+    pub reputation_decayed_at: u64, // decay is applied in whole periods from here, 0 until reputation first changes
+}
+
+impl OrganizationInfo {
+    /// Reputation after the decay due up to `now`
+    pub fn reputation_at(&self, params: &ReputationParams, now: u64) -> Uint128 {
+        let mut reputation = self.reputation_score;
+        if params.decay_period == 0 || params.decay_rate.is_zero() || self.reputation_decayed_at == 0 {
+            return reputation;
+        }
+        let periods = now.saturating_sub(self.reputation_decayed_at) / params.decay_period;
+        let retained = Decimal::one() - params.decay_rate.min(Decimal::one());
+        for _ in 0..periods {
+            if reputation.is_zero() {
+                break;
+            }
+            reputation = reputation * retained;
+        }
+        reputation
+    }
+
+    /// Applies the decay due up to `now`, returning the reputation lost
+    pub fn decay_reputation(&mut self, params: &ReputationParams, now: u64) -> Uint128 {
+        if self.reputation_decayed_at == 0 || params.decay_period == 0 {
+            self.reputation_decayed_at = now;
+            return Uint128::zero();
+        }
+        let reputation = self.reputation_at(params, now);
+        let lost = self.reputation_score - reputation;
+        self.reputation_score = reputation;
+        self.reputation_decayed_at += now.saturating_sub(self.reputation_decayed_at) / params.decay_period * params.decay_period;
+        lost
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReputationReason {
+    CorrectVote,
+    IncorrectVote,
+    Unrevealed,
+    Absent,
+    ChallengeUpheld,
+    LoanDefault,
+    Decay,
+}
+
+/// A change to an organization's reputation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReputationEvent {
+    pub id: u64,
+    pub organization: Addr,
+    pub reason: ReputationReason,
+    pub reference: Option<u64>, // claim id for votes and challenges, loan id for defaults
+    pub previous: Uint128,
+    pub current: Uint128,
+    pub timestamp: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -88,6 +713,11 @@ pub struct LendRequestResponse {
     pub proof_data: String,
     pub time: u64,
     pub amount: Uint128,
+    pub interest_rate: Decimal,
+    pub term: u64,
+    pub collateral: Option<Coin>,
+    pub expires_at: u64,
+    pub counter_amount: Option<Uint128>,
     pub role: String,  // "borrower" or "lender"
 }
 
@@ -98,8 +728,62 @@ pub struct UserLendRequestsResponse {
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const CLAIMS: Map<u64, Claim> = Map::new("claims");
+/// ((column, row), end of the claimed period, claim id) of each grid cell a live claim touches,
+/// see geometry::grid_cells, to the start of its claimed period. Keying by the end lets the overlap
+/// check skip claims whose period is already over.
+pub const CLAIM_CELLS: Map<((i32, i32), u64, u64), u64> = Map::new("claim_cells");
 pub const LEND_REQUESTS : Map<u64, LendRequest> = Map::new("lend_requests");
-pub const VOTES: Map<(u64, &Addr), VoteOption> = Map::new("votes");
+pub const VOTE_COMMITS: Map<(u64, &Addr), VoteCommit> = Map::new("vote_commits");
+pub const VOTES: Map<(u64, &Addr), Vote> = Map::new("votes"); // revealed votes
 pub const CLAIM_COUNTER: Item<u64> = Item::new("claim_counter");
-pub const ORGANIZATIONS: Map<&Addr, OrganizationInfo> = Map::new("organizations");
-pub const LEND_REQUEST_COUNTER: Item<u64> = Item::new("lend_request_counter");
\ No newline at end of file
+/// Snapshotted every block so vote weights and eligibility can be read as of a past height
+pub const ORGANIZATIONS: SnapshotMap<&Addr, OrganizationInfo> = SnapshotMap::new(
+    "organizations",
+    "organizations__checkpoints",
+    "organizations__changelog",
+    Strategy::EveryBlock,
+);
+/// Credit tokens of each holder as far as this contract moved them: minted, paid out or received
+/// through a hook. Transfers made directly on the token are not seen.
+pub const CREDIT_TOKEN_BALANCES: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "credit_token_balances",
+    "credit_token_balances__checkpoints",
+    "credit_token_balances__changelog",
+    Strategy::EveryBlock,
+);
+pub const VERIFIERS: Map<&Addr, Verifier> = Map::new("verifiers");
+pub const VERIFIER_COUNT: Item<u32> = Item::new("verifier_count");
+/// Bounds the per-claim sweeps over verifiers at finalization
+pub const MAX_VERIFIERS: u32 = 100;
+pub const LEND_REQUEST_COUNTER: Item<u64> = Item::new("lend_request_counter");
+pub const CHALLENGES: Map<u64, Challenge> = Map::new("challenges");
+pub const CHALLENGE_COUNTER: Item<u64> = Item::new("challenge_counter");
+pub const CHALLENGE_VOTES: Map<(u64, &Addr), Vote> = Map::new("challenge_votes"); // Yes upholds the challenge
+/// Credit tokens minted to this contract for an approved claim, held until its challenge period ends
+pub const ESCROWED_CREDITS: Map<u64, Uint128> = Map::new("escrowed_credits");
+pub const BATCH_COUNTER: Item<u64> = Item::new("batch_counter");
+/// Batches outstanding, which BATCH_COUNTER overcounts once batches are retired or voided
+pub const BATCH_COUNT: Item<u64> = Item::new("batch_count");
+pub const NFT_APPROVALS: Map<(u64, &Addr), Expiration> = Map::new("nft_approvals"); // (batch id, spender), cleared when the batch changes hands
+pub const NFT_OPERATORS: Map<(&Addr, &Addr), Expiration> = Map::new("nft_operators"); // (owner, operator) over all the owner's batches
+pub const NEXT_SERIAL: Item<Uint128> = Item::new("next_serial");
+pub const RETIREMENTS: Map<u64, RetirementCertificate> = Map::new("retirements");
+pub const RETIREMENT_COUNTER: Item<u64> = Item::new("retirement_counter");
+pub const EMISSION_REPORTS: Map<(&Addr, u32, u32), EmissionsReport> = Map::new("emission_reports"); // (organization, period, version)
+pub const PERIOD_OFFSETS: Map<(&Addr, u32), Uint128> = Map::new("period_offsets"); // credits retired for a beneficiary's period
+/// Period holding emissions added without one, through `AddOrganizationEmission` or before reports
+pub const UNALLOCATED_EMISSIONS_PERIOD: u32 = 0;
+pub const ALLOWANCES: Map<(&Addr, &Addr), AllowanceResponse> = Map::new("allowances"); // (owner, spender)
+pub const LISTING_COUNTER: Item<u64> = Item::new("listing_counter");
+pub const LOAN_COUNTER: Item<u64> = Item::new("loan_counter");
+pub const LEND_OFFERS: Map<u64, LendOffer> = Map::new("lend_offers");
+pub const LEND_OFFER_COUNTER: Item<u64> = Item::new("lend_offer_counter");
+pub const POOL: Item<LendingPool> = Item::new("pool");
+pub const POOL_SHARES: Map<&Addr, Uint128> = Map::new("pool_shares");
+pub const POOL_DEBTS: Map<&Addr, PoolDebt> = Map::new("pool_debts");
+pub const REPUTATION_EVENTS: Map<(&Addr, u64), ReputationEvent> = Map::new("reputation_events");
+pub const REPUTATION_EVENT_COUNTER: Item<u64> = Item::new("reputation_event_counter");
+pub const ELIGIBILITY_POLICY: Item<EligibilityPolicy> = Item::new("eligibility_policy");
+pub const ELIGIBILITY_POLICY_VERSION: Item<u64> = Item::new("eligibility_policy_version"); // bumped on every update
+pub const PROOFS: Map<(&Addr, &Addr), EligibilityRecord> = Map::new("eligibility_proofs"); // (borrower, lender)
+pub const ELIGIBILITY_KEY: Item<Binary> = Item::new("eligibility_key"); // Groth16 verifying key, see zk
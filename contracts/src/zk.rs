@@ -0,0 +1,123 @@
+//! Groth16 verification of borrower eligibility proofs over BN254, and the circuit they are made for.
+//!
+//! The circuit's public inputs are, in order: the score threshold the borrower proves it meets,
+//! `borrower_binding`, so a proof can't be replayed for another address, the amount to be borrowed
+//! on it, so it can't be replayed for a larger one, and the six
+//! `EligibilityFactors` the contract's own snapshot of the borrower gives. The factors are contract
+//! state anyone can read, so they are inputs rather than hidden behind a commitment; what the proof
+//! adds is that the threshold was checked against exactly those factors.
+//! Keys and proofs use the compressed canonical serialisation of arkworks.
+
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{prepare_verifying_key, Groth16, Proof, VerifyingKey};
+use ark_relations::lc;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable};
+use ark_serialize::CanonicalDeserialize;
+use cosmwasm_std::{Addr, Uint128};
+use sha2::{Digest, Sha256};
+
+use crate::error::ContractError;
+use crate::state::EligibilityFactors;
+
+const PUBLIC_INPUTS: usize = 9;
+
+/// Bits the margin of the score over the threshold is decomposed into. Four gains below 2^128 sum
+/// to less than 2^130, while a negative margin wraps to near the field's modulus and has no such
+/// decomposition.
+const MARGIN_BITS: usize = 130;
+
+/// Public input tying a proof to `borrower`: the hash of its address in the scalar field
+pub fn borrower_binding(borrower: &Addr) -> Fr {
+    Fr::from_le_bytes_mod_order(&Sha256::digest(borrower.as_bytes()))
+}
+
+fn public_inputs(threshold: Uint128, borrower: &Addr, amount: Uint128, factors: &EligibilityFactors) -> [Fr; PUBLIC_INPUTS] {
+    [
+        Fr::from(threshold.u128()),
+        borrower_binding(borrower),
+        Fr::from(amount.u128()),
+        Fr::from(factors.base.u128()),
+        Fr::from(factors.reputation.u128()),
+        Fr::from(factors.returns.u128()),
+        Fr::from(factors.credits.u128()),
+        Fr::from(factors.debt.u128()),
+        Fr::from(factors.emissions.u128()),
+    ]
+}
+
+/// Proves `base + reputation + returns + credits - debt - emissions >= threshold`, the unclamped
+/// eligibility score of `EligibilityPolicy::score`, for `borrower` borrowing up to `amount`. Clamping only lowers the score
+/// to `max_score`, which the contract checks the threshold against itself.
+#[derive(Clone, Debug)]
+pub struct EligibilityCircuit {
+    pub threshold: Uint128,
+    pub borrower: Addr,
+    pub amount: Uint128,
+    pub factors: EligibilityFactors,
+}
+
+impl ConstraintSynthesizer<Fr> for EligibilityCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let values = public_inputs(self.threshold, &self.borrower, self.amount, &self.factors);
+        let mut inputs = vec![];
+        for value in values {
+            inputs.push(cs.new_input_variable(|| Ok(value))?);
+        }
+        let (threshold, binding, amount) = (inputs[0], inputs[1], inputs[2]);
+        let (gains, losses) = (&inputs[3..7], &inputs[7..9]);
+
+        // margin = gains - losses - threshold, as the sum of its bits
+        let margin = values[3..7].iter().sum::<Fr>() - values[7..9].iter().sum::<Fr>() - values[0];
+        let mut bits = lc!();
+        let mut power = Fr::from(1u64);
+        for margin_bit in margin.into_bigint().to_bits_le().into_iter().take(MARGIN_BITS) {
+            let bit = cs.new_witness_variable(|| Ok(Fr::from(margin_bit)))?;
+            cs.enforce_constraint(lc!() + bit, lc!() + bit, lc!() + bit)?;
+            bits += (power, bit);
+            power += power;
+        }
+        let mut score = lc!() - threshold;
+        for gain in gains {
+            score = score + *gain;
+        }
+        for loss in losses {
+            score = score - *loss;
+        }
+        cs.enforce_constraint(score, lc!() + Variable::One, bits)?;
+
+        // Keeps the binding and amount in the proof, as they take part in no other constraint
+        for (input, value) in [(binding, values[1]), (amount, values[2])] {
+            let squared = cs.new_witness_variable(|| Ok(value * value))?;
+            cs.enforce_constraint(lc!() + input, lc!() + input, lc!() + squared)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_verifying_key(verifying_key: &[u8]) -> Result<VerifyingKey<Bn254>, ContractError> {
+    let verifying_key = VerifyingKey::<Bn254>::deserialize_compressed(verifying_key)
+        .map_err(|_| ContractError::InvalidVerifyingKey {})?;
+    // One base point plus one per public input
+    if verifying_key.gamma_abc_g1.len() != PUBLIC_INPUTS + 1 {
+        return Err(ContractError::InvalidVerifyingKey {});
+    }
+    Ok(verifying_key)
+}
+
+pub fn validate_verifying_key(verifying_key: &[u8]) -> Result<(), ContractError> {
+    parse_verifying_key(verifying_key).map(|_| ())
+}
+
+/// Checks that `proof` shows `borrower` scores at least `threshold` on `factors`, for borrowing up to `amount`
+pub fn verify_eligibility_proof(verifying_key: &[u8], proof: &[u8], threshold: Uint128, borrower: &Addr, amount: Uint128, factors: &EligibilityFactors) -> Result<(), ContractError> {
+    let verifying_key = parse_verifying_key(verifying_key)?;
+    let proof = Proof::<Bn254>::deserialize_compressed(proof)
+        .map_err(|_| ContractError::InvalidProof {})?;
+    let valid = Groth16::<Bn254>::verify_proof(&prepare_verifying_key(&verifying_key), &proof, &public_inputs(threshold, borrower, amount, factors))
+        .map_err(|_| ContractError::InvalidProof {})?;
+    if !valid {
+        return Err(ContractError::InvalidProof {});
+    }
+    Ok(())
+}